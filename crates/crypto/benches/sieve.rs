@@ -7,17 +7,17 @@ fn main() {
 
 #[divan::bench]
 fn print_sieve_unopt(bencher: Bencher) {
-    let primes = sieve_of_eratosthenes(10000);
+    let primes = sieve_of_eratosthenes(10000).expect("failed to sieve");
 
     bencher.bench_local(move || {
-        black_box(print_sieve(&primes));
+        print_sieve(black_box(&primes));
     });
 }
 #[divan::bench]
 fn print_sieve_opt(bencher: Bencher) {
-    let primes = sieve_of_eratosthenes(10000);
+    let primes = sieve_of_eratosthenes(10000).expect("failed to sieve");
 
     bencher.bench_local(move || {
-        black_box(print_sieve_optimized(&primes));
+        print_sieve_optimized(black_box(&primes));
     });
 }
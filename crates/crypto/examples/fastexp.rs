@@ -8,12 +8,12 @@ fn main() -> Result<()> {
         let power = get_number("Enter the power:")?;
         let modulus = get_number("Enter the modulus:")?;
 
-        println!("fast_exp({number}, {power})={}", fast_exp(number, power));
+        println!("fast_exp({number}, {power})={}", fast_exp(number, power)?);
         println!("{number}.pow({power})={}", number.pow(power as u32));
 
         println!(
             "fast_exp_mod({number}, {power}, {modulus})={}",
-            fast_exp_mod(number, power, modulus)
+            fast_exp_mod(number, power, modulus)?
         );
         println!(
             "{number}.pow({power})%{modulus}={}",
@@ -8,6 +8,6 @@ fn main() -> Result<()> {
         let b = get_number("Second Value?:")?;
 
         println!("gcd({a}, {b})={}", gcd(a, b));
-        println!("lcm({a}, {b})={}", lcm(a, b));
+        println!("lcm({a}, {b})={}", lcm(a, b)?);
     }
 }
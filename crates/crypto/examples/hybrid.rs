@@ -0,0 +1,79 @@
+use anyhow::{anyhow, Result};
+use rand::RngCore;
+
+use crypto::{
+    chacha20_decrypt, chacha20_encrypt, fast_exp_mod, find_prime, get_number, inverse_mod,
+    random_exponent, totient, CHACHA20_KEY_LEN, CHACHA20_NONCE_LEN,
+};
+
+const MIN: usize = 1_000;
+const MAX: usize = 10_000;
+
+// The toy RSA modulus is at least MIN * MIN = 1_000_000, so each
+// two byte chunk of the symmetric key (< 65_536) is a valid message.
+const CHUNK_LEN: usize = 2;
+
+fn main() -> Result<()> {
+    let mut rng = rand::thread_rng();
+
+    // RSA keys, as in the rsa example.
+    let p = find_prime(&mut rng, MIN, MAX)?;
+    let q = find_prime(&mut rng, MIN, MAX)?;
+    let n = p * q;
+    let λn = totient(p, q)?;
+    let pub_key = random_exponent(&mut rng, λn)?;
+    let priv_key = inverse_mod(pub_key, λn)?;
+
+    println!("RSA modulus: {n}, e: {pub_key}, d: {priv_key}\n");
+
+    loop {
+        let msg: String = get_number("Enter a message (empty to exit):")?;
+        if msg.is_empty() {
+            println!("Bye!");
+            break;
+        }
+
+        // Sender: pick a random symmetric key and nonce, encrypt the message
+        // with ChaCha20 and wrap the key with the receiver's public key.
+        let mut key = [0u8; CHACHA20_KEY_LEN];
+        let mut nonce = [0u8; CHACHA20_NONCE_LEN];
+        rng.fill_bytes(&mut key);
+        rng.fill_bytes(&mut nonce);
+
+        let cipher_text = chacha20_encrypt(&key, 1, &nonce, msg.as_bytes())?;
+        let wrapped_key = wrap_key(&key, pub_key, n)?;
+
+        println!("Wrapped key: {wrapped_key:?}");
+        println!("Nonce:       {nonce:02x?}");
+        println!("Ciphertext:  {cipher_text:02x?}");
+
+        // Receiver: unwrap the key with the private key and decrypt.
+        let unwrapped = unwrap_key(&wrapped_key, priv_key, n)?;
+        let plain_text = chacha20_decrypt(&unwrapped, 1, &nonce, &cipher_text)?;
+
+        println!("Plaintext:   {}", String::from_utf8(plain_text)?);
+        println!();
+    }
+
+    Ok(())
+}
+
+fn wrap_key(key: &[u8; CHACHA20_KEY_LEN], e: usize, n: usize) -> Result<Vec<usize>> {
+    Ok(key
+        .chunks(CHUNK_LEN)
+        .map(|c| fast_exp_mod(u16::from_be_bytes([c[0], c[1]]) as usize, e, n))
+        .collect::<Result<_, _>>()?)
+}
+
+fn unwrap_key(wrapped: &[usize], d: usize, n: usize) -> Result<[u8; CHACHA20_KEY_LEN]> {
+    let mut bytes = Vec::with_capacity(CHACHA20_KEY_LEN);
+    for c in wrapped {
+        let chunk = u16::try_from(fast_exp_mod(*c, d, n)?)
+            .map_err(|_| anyhow!("wrapped key chunk out of range"))?;
+        bytes.extend(chunk.to_be_bytes());
+    }
+
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("wrapped key has the wrong length"))
+}
@@ -6,18 +6,10 @@ use rand::thread_rng;
 
 use crypto::{find_prime, get_number};
 
-const NUM_TESTS: usize = 20;
-
 fn main() -> Result<()> {
     // Prepare a Prng.
     let mut prng = thread_rng();
 
-    // Display the probability that a number is prime
-    // if it passes all NUM_TESTS tests.
-    let probability = 100f64 - (0.5f64.powi(NUM_TESTS as i32));
-
-    println!("Probability: {}%\n", probability);
-
     // Generate random primes.
     loop {
         // Get the number of digits.
@@ -35,7 +27,7 @@ fn main() -> Result<()> {
         } // 1 is not prime.
 
         // Find a prime.
-        println!("Prime: {}", find_prime(&mut prng, min, max, NUM_TESTS));
+        println!("Prime: {}", find_prime(&mut prng, min, max)?);
         println!("Found in {:?}", SystemTime::now().duration_since(start)?);
     }
 }
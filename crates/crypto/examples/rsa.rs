@@ -2,7 +2,6 @@ use anyhow::Result;
 
 use crypto::{fast_exp_mod, find_prime, get_number, inverse_mod, random_exponent, totient};
 
-const NUM_TESTS: usize = 20;
 const MIN: usize = 1_000;
 const MAX: usize = 10_000;
 
@@ -10,15 +9,15 @@ fn main() -> Result<()> {
     let mut rng = rand::thread_rng();
 
     // Primes
-    let p = find_prime(&mut rng, MIN, MAX, NUM_TESTS);
-    let q = find_prime(&mut rng, MIN, MAX, NUM_TESTS);
+    let p = find_prime(&mut rng, MIN, MAX)?;
+    let q = find_prime(&mut rng, MIN, MAX)?;
 
     // modulus and totient
     let n = p * q;
-    let λn = totient(p, q);
+    let λn = totient(p, q)?;
 
     // public (e) and private (d) keys
-    let pub_key = random_exponent(&mut rng, λn);
+    let pub_key = random_exponent(&mut rng, λn)?;
     let priv_key = inverse_mod(pub_key, λn)?;
 
     println!(
@@ -41,9 +40,9 @@ d:       {priv_key}
         }
 
         println!("Message:    {msg}");
-        let cipher_text = fast_exp_mod(msg, pub_key, n);
+        let cipher_text = fast_exp_mod(msg, pub_key, n)?;
         println!("Ciphertext: {cipher_text}");
-        let plain_text = fast_exp_mod(cipher_text, priv_key, n);
+        let plain_text = fast_exp_mod(cipher_text, priv_key, n)?;
         println!("Ciphertext: {plain_text}");
         println!();
     }
@@ -4,7 +4,7 @@ use crypto::{get_number, print_sieve_optimized, sieve_of_eratosthenes, sieve_to_
 
 fn main() -> Result<()> {
     let max = get_number("Enter max prime value:")?;
-    let primes = sieve_of_eratosthenes(max);
+    let primes = sieve_of_eratosthenes(max)?;
 
     sieve_to_primes(&primes).iter().for_each(|p| print!("{p} "));
     println!();
@@ -0,0 +1,509 @@
+//! AES-128 block cipher ([FIPS-197](https://csrc.nist.gov/pubs/fips/197/final))
+//! with the ECB, CBC and CTR modes of operation from
+//! [SP 800-38A](https://csrc.nist.gov/pubs/sp/800/38/a/final).
+//!
+//! Run `cargo r --release --example hybrid` to see it used alongside RSA.
+use crate::CryptoError;
+
+pub const AES_BLOCK_LEN: usize = 16;
+pub const AES128_KEY_LEN: usize = 16;
+
+const ROUNDS: usize = 10;
+
+const RCON: [u8; ROUNDS] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+const SBOX: [u8; 256] = build_sbox();
+const INV_SBOX: [u8; 256] = invert_sbox(&SBOX);
+
+/// Builds the S-box by walking the multiplicative group of GF(2^8) with
+/// generator 3 and its inverse 0xf6 in lockstep, then applying the affine
+/// transform to each inverse.
+const fn build_sbox() -> [u8; 256] {
+    let mut sbox = [0u8; 256];
+    let mut p: u8 = 1;
+    let mut q: u8 = 1;
+
+    loop {
+        // p *= 3
+        p = p ^ (p << 1) ^ if p & 0x80 != 0 { 0x1b } else { 0 };
+
+        // q /= 3
+        q ^= q << 1;
+        q ^= q << 2;
+        q ^= q << 4;
+        if q & 0x80 != 0 {
+            q ^= 0x09;
+        }
+
+        sbox[p as usize] =
+            q ^ q.rotate_left(1) ^ q.rotate_left(2) ^ q.rotate_left(3) ^ q.rotate_left(4) ^ 0x63;
+
+        if p == 1 {
+            break;
+        }
+    }
+    // 0 has no inverse.
+    sbox[0] = 0x63;
+
+    sbox
+}
+
+const fn invert_sbox(sbox: &[u8; 256]) -> [u8; 256] {
+    let mut inv = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        inv[sbox[i] as usize] = i as u8;
+        i += 1;
+    }
+
+    inv
+}
+
+/// Multiply by x (i.e. 2) in GF(2^8).
+fn xtime(b: u8) -> u8 {
+    (b << 1) ^ if b & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+/// Multiply two elements of GF(2^8).
+fn gmul(mut a: u8, mut b: u8) -> u8 {
+    let mut result = 0;
+    while b > 0 {
+        if b & 1 == 1 {
+            result ^= a;
+        }
+        a = xtime(a);
+        b >>= 1;
+    }
+
+    result
+}
+
+/// An expanded AES-128 key.
+///
+/// The state is kept column major, as in FIPS-197, so byte `r + 4c` is
+/// row `r` of column `c`.
+#[derive(Clone)]
+pub struct Aes128 {
+    round_keys: [[u8; AES_BLOCK_LEN]; ROUNDS + 1],
+}
+
+impl Aes128 {
+    pub fn new(key: &[u8; AES128_KEY_LEN]) -> Self {
+        let mut words = [[0u8; 4]; 4 * (ROUNDS + 1)];
+        for (i, word) in words.iter_mut().take(4).enumerate() {
+            word.copy_from_slice(&key[4 * i..4 * i + 4]);
+        }
+
+        for i in 4..words.len() {
+            let mut temp = words[i - 1];
+            if i % 4 == 0 {
+                // RotWord then SubWord then Rcon.
+                temp.rotate_left(1);
+                temp.iter_mut().for_each(|b| *b = SBOX[*b as usize]);
+                temp[0] ^= RCON[i / 4 - 1];
+            }
+            for (j, b) in temp.iter().enumerate() {
+                words[i][j] = words[i - 4][j] ^ b;
+            }
+        }
+
+        let mut round_keys = [[0u8; AES_BLOCK_LEN]; ROUNDS + 1];
+        for (round, rk) in round_keys.iter_mut().enumerate() {
+            for c in 0..4 {
+                rk[4 * c..4 * c + 4].copy_from_slice(&words[4 * round + c]);
+            }
+        }
+
+        Self { round_keys }
+    }
+
+    pub fn encrypt_block(&self, block: &mut [u8; AES_BLOCK_LEN]) {
+        add_round_key(block, &self.round_keys[0]);
+
+        for round in 1..ROUNDS {
+            sub_bytes(block, &SBOX);
+            shift_rows(block);
+            mix_columns(block);
+            add_round_key(block, &self.round_keys[round]);
+        }
+
+        sub_bytes(block, &SBOX);
+        shift_rows(block);
+        add_round_key(block, &self.round_keys[ROUNDS]);
+    }
+
+    pub fn decrypt_block(&self, block: &mut [u8; AES_BLOCK_LEN]) {
+        add_round_key(block, &self.round_keys[ROUNDS]);
+
+        for round in (1..ROUNDS).rev() {
+            inv_shift_rows(block);
+            sub_bytes(block, &INV_SBOX);
+            add_round_key(block, &self.round_keys[round]);
+            inv_mix_columns(block);
+        }
+
+        inv_shift_rows(block);
+        sub_bytes(block, &INV_SBOX);
+        add_round_key(block, &self.round_keys[0]);
+    }
+}
+
+fn add_round_key(state: &mut [u8; AES_BLOCK_LEN], key: &[u8; AES_BLOCK_LEN]) {
+    state.iter_mut().zip(key.iter()).for_each(|(s, k)| *s ^= k);
+}
+
+fn sub_bytes(state: &mut [u8; AES_BLOCK_LEN], table: &[u8; 256]) {
+    state.iter_mut().for_each(|b| *b = table[*b as usize]);
+}
+
+// Row r is rotated left by r.
+fn shift_rows(state: &mut [u8; AES_BLOCK_LEN]) {
+    let old = *state;
+    for r in 1..4 {
+        for c in 0..4 {
+            state[r + 4 * c] = old[r + 4 * ((c + r) % 4)];
+        }
+    }
+}
+
+fn inv_shift_rows(state: &mut [u8; AES_BLOCK_LEN]) {
+    let old = *state;
+    for r in 1..4 {
+        for c in 0..4 {
+            state[r + 4 * ((c + r) % 4)] = old[r + 4 * c];
+        }
+    }
+}
+
+fn mix_columns(state: &mut [u8; AES_BLOCK_LEN]) {
+    for col in state.chunks_exact_mut(4) {
+        let [a0, a1, a2, a3] = [col[0], col[1], col[2], col[3]];
+        col[0] = xtime(a0) ^ (xtime(a1) ^ a1) ^ a2 ^ a3;
+        col[1] = a0 ^ xtime(a1) ^ (xtime(a2) ^ a2) ^ a3;
+        col[2] = a0 ^ a1 ^ xtime(a2) ^ (xtime(a3) ^ a3);
+        col[3] = (xtime(a0) ^ a0) ^ a1 ^ a2 ^ xtime(a3);
+    }
+}
+
+fn inv_mix_columns(state: &mut [u8; AES_BLOCK_LEN]) {
+    for col in state.chunks_exact_mut(4) {
+        let [a0, a1, a2, a3] = [col[0], col[1], col[2], col[3]];
+        col[0] = gmul(a0, 14) ^ gmul(a1, 11) ^ gmul(a2, 13) ^ gmul(a3, 9);
+        col[1] = gmul(a0, 9) ^ gmul(a1, 14) ^ gmul(a2, 11) ^ gmul(a3, 13);
+        col[2] = gmul(a0, 13) ^ gmul(a1, 9) ^ gmul(a2, 14) ^ gmul(a3, 11);
+        col[3] = gmul(a0, 11) ^ gmul(a1, 13) ^ gmul(a2, 9) ^ gmul(a3, 14);
+    }
+}
+
+/// Pads `data` to a whole number of blocks. A full block of padding is added
+/// when `data` is already aligned so the padding can always be removed.
+pub fn pkcs7_pad(data: &[u8]) -> Vec<u8> {
+    let pad = AES_BLOCK_LEN - data.len() % AES_BLOCK_LEN;
+    let mut out = Vec::with_capacity(data.len() + pad);
+    out.extend_from_slice(data);
+    out.resize(data.len() + pad, pad as u8);
+
+    out
+}
+
+pub fn pkcs7_unpad(mut data: Vec<u8>) -> Result<Vec<u8>, CryptoError> {
+    let pad = *data.last().ok_or(CryptoError::InvalidPadding)? as usize;
+    if pad == 0 || pad > AES_BLOCK_LEN || pad > data.len() {
+        return Err(CryptoError::InvalidPadding);
+    }
+    if !data[data.len() - pad..].iter().all(|b| *b as usize == pad) {
+        return Err(CryptoError::InvalidPadding);
+    }
+
+    data.truncate(data.len() - pad);
+
+    Ok(data)
+}
+
+fn to_block(chunk: &[u8]) -> [u8; AES_BLOCK_LEN] {
+    let mut block = [0u8; AES_BLOCK_LEN];
+    block.copy_from_slice(chunk);
+
+    block
+}
+
+fn check_block_aligned(data: &[u8]) -> Result<(), CryptoError> {
+    if data.is_empty() || !data.len().is_multiple_of(AES_BLOCK_LEN) {
+        return Err(CryptoError::InvalidLength(data.len()));
+    }
+
+    Ok(())
+}
+
+/// Electronic codebook mode with PKCS#7 padding.
+/// Identical plaintext blocks give identical ciphertext blocks, so only use
+/// this to see why the other modes exist.
+pub fn ecb_encrypt(cipher: &Aes128, plaintext: &[u8]) -> Vec<u8> {
+    let mut data = pkcs7_pad(plaintext);
+    for chunk in data.chunks_exact_mut(AES_BLOCK_LEN) {
+        let mut block = to_block(chunk);
+        cipher.encrypt_block(&mut block);
+        chunk.copy_from_slice(&block);
+    }
+
+    data
+}
+
+pub fn ecb_decrypt(cipher: &Aes128, ciphertext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    check_block_aligned(ciphertext)?;
+
+    let mut data = ciphertext.to_vec();
+    for chunk in data.chunks_exact_mut(AES_BLOCK_LEN) {
+        let mut block = to_block(chunk);
+        cipher.decrypt_block(&mut block);
+        chunk.copy_from_slice(&block);
+    }
+
+    pkcs7_unpad(data)
+}
+
+/// Cipher block chaining mode with PKCS#7 padding.
+pub fn cbc_encrypt(cipher: &Aes128, iv: &[u8; AES_BLOCK_LEN], plaintext: &[u8]) -> Vec<u8> {
+    let mut data = pkcs7_pad(plaintext);
+    let mut prev = *iv;
+    for chunk in data.chunks_exact_mut(AES_BLOCK_LEN) {
+        let mut block = to_block(chunk);
+        add_round_key(&mut block, &prev);
+        cipher.encrypt_block(&mut block);
+        chunk.copy_from_slice(&block);
+        prev = block;
+    }
+
+    data
+}
+
+pub fn cbc_decrypt(
+    cipher: &Aes128,
+    iv: &[u8; AES_BLOCK_LEN],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    check_block_aligned(ciphertext)?;
+
+    let mut data = ciphertext.to_vec();
+    let mut prev = *iv;
+    for chunk in data.chunks_exact_mut(AES_BLOCK_LEN) {
+        let saved = to_block(chunk);
+        let mut block = saved;
+        cipher.decrypt_block(&mut block);
+        add_round_key(&mut block, &prev);
+        chunk.copy_from_slice(&block);
+        prev = saved;
+    }
+
+    pkcs7_unpad(data)
+}
+
+/// Counter mode. The 16 byte initial counter block is incremented as a
+/// big-endian 128 bit integer, and no padding is needed.
+///
+/// Returns `CryptoError::Overflow` rather than wrapping the counter, since
+/// reusing keystream leaks the XOR of the plaintexts.
+pub fn ctr_encrypt(
+    cipher: &Aes128,
+    counter: &[u8; AES_BLOCK_LEN],
+    data: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    let ctr = u128::from_be_bytes(*counter);
+    let blocks = data.len().div_ceil(AES_BLOCK_LEN) as u128;
+    if blocks > 0 && blocks - 1 > u128::MAX - ctr {
+        return Err(CryptoError::Overflow);
+    }
+
+    let mut out = Vec::with_capacity(data.len());
+
+    for (j, chunk) in data.chunks(AES_BLOCK_LEN).enumerate() {
+        let mut keystream = (ctr + j as u128).to_be_bytes();
+        cipher.encrypt_block(&mut keystream);
+        out.extend(chunk.iter().zip(keystream.iter()).map(|(d, k)| d ^ k));
+    }
+
+    Ok(out)
+}
+
+/// Counter mode decrypts by encrypting again.
+pub fn ctr_decrypt(
+    cipher: &Aes128,
+    counter: &[u8; AES_BLOCK_LEN],
+    data: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+    ctr_encrypt(cipher, counter, data)
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).expect("bad hex"))
+            .collect()
+    }
+
+    fn block(s: &str) -> [u8; AES_BLOCK_LEN] {
+        to_block(&hex(s))
+    }
+
+    // SP 800-38A appendix F uses this key and these four plaintext blocks.
+    const SP800_38A_KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+    const SP800_38A_PLAINTEXT: &str = concat!(
+        "6bc1bee22e409f96e93d7e117393172a",
+        "ae2d8a571e03ac9c9eb76fac45af8e51",
+        "30c81c46a35ce411e5fbc1191a0a52ef",
+        "f69f2445df4f9b17ad2b417be66c3710",
+    );
+
+    #[test]
+    fn sbox_spot_checks() {
+        assert_eq!(SBOX[0x00], 0x63);
+        assert_eq!(SBOX[0x01], 0x7c);
+        assert_eq!(SBOX[0x53], 0xed);
+        assert_eq!(SBOX[0xff], 0x16);
+        assert_eq!(INV_SBOX[0x63], 0x00);
+        assert_eq!(INV_SBOX[0xed], 0x53);
+    }
+
+    #[test]
+    fn fips197_appendix_b() {
+        let cipher = Aes128::new(&block(SP800_38A_KEY));
+        let mut state = block("3243f6a8885a308d313198a2e0370734");
+
+        cipher.encrypt_block(&mut state);
+        assert_eq!(state, block("3925841d02dc09fbdc118597196a0b32"));
+
+        cipher.decrypt_block(&mut state);
+        assert_eq!(state, block("3243f6a8885a308d313198a2e0370734"));
+    }
+
+    #[test]
+    fn fips197_appendix_c1() {
+        let cipher = Aes128::new(&block("000102030405060708090a0b0c0d0e0f"));
+        let mut state = block("00112233445566778899aabbccddeeff");
+
+        cipher.encrypt_block(&mut state);
+        assert_eq!(state, block("69c4e0d86a7b0430d8cdb78070b4c55a"));
+
+        cipher.decrypt_block(&mut state);
+        assert_eq!(state, block("00112233445566778899aabbccddeeff"));
+    }
+
+    #[test]
+    fn ecb_sp800_38a() {
+        let cipher = Aes128::new(&block(SP800_38A_KEY));
+        let plaintext = hex(SP800_38A_PLAINTEXT);
+        let want = hex(concat!(
+            "3ad77bb40d7a3660a89ecaf32466ef97",
+            "f5d3d58503b9699de785895a96fdbaaf",
+            "43b1cd7f598ece23881b00e3ed030688",
+            "7b0c785e27e8ad3f8223207104725dd4",
+        ));
+
+        let got = ecb_encrypt(&cipher, &plaintext);
+        // The last block is the full block of padding.
+        assert_eq!(got.len(), want.len() + AES_BLOCK_LEN);
+        assert_eq!(got[..want.len()], want);
+        assert_eq!(
+            ecb_decrypt(&cipher, &got).expect("failed to decrypt"),
+            plaintext
+        );
+    }
+
+    #[test]
+    fn cbc_sp800_38a() {
+        let cipher = Aes128::new(&block(SP800_38A_KEY));
+        let iv = block("000102030405060708090a0b0c0d0e0f");
+        let plaintext = hex(SP800_38A_PLAINTEXT);
+        let want = hex(concat!(
+            "7649abac8119b246cee98e9b12e9197d",
+            "5086cb9b507219ee95db113a917678b2",
+            "73bed6b8e3c1743b7116e69e22229516",
+            "3ff1caa1681fac09120eca307586e1a7",
+        ));
+
+        let got = cbc_encrypt(&cipher, &iv, &plaintext);
+        assert_eq!(got[..want.len()], want);
+        assert_eq!(
+            cbc_decrypt(&cipher, &iv, &got).expect("failed to decrypt"),
+            plaintext
+        );
+    }
+
+    #[test]
+    fn ctr_sp800_38a() {
+        let cipher = Aes128::new(&block(SP800_38A_KEY));
+        let counter = block("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+        let plaintext = hex(SP800_38A_PLAINTEXT);
+        let want = hex(concat!(
+            "874d6191b620e3261bef6864990db6ce",
+            "9806f66b7970fdff8617187bb9fffdff",
+            "5ae4df3edbd5d35e5b4f09020db03eab",
+            "1e031dda2fbe03d1792170a0f3009cee",
+        ));
+
+        assert_eq!(ctr_encrypt(&cipher, &counter, &plaintext), Ok(want.clone()));
+        assert_eq!(ctr_decrypt(&cipher, &counter, &want), Ok(plaintext.clone()));
+        // Partial final blocks are fine in counter mode.
+        assert_eq!(
+            ctr_encrypt(&cipher, &counter, &plaintext[..21]),
+            Ok(want[..21].to_vec())
+        );
+    }
+
+    #[test]
+    fn ctr_refuses_to_wrap_the_counter() {
+        let cipher = Aes128::new(&block(SP800_38A_KEY));
+        let last = u128::MAX.to_be_bytes();
+
+        assert!(ctr_encrypt(&cipher, &last, &[]).is_ok());
+        assert!(ctr_encrypt(&cipher, &last, &[0; AES_BLOCK_LEN]).is_ok());
+        assert_eq!(
+            ctr_encrypt(&cipher, &last, &[0; AES_BLOCK_LEN + 1]),
+            Err(CryptoError::Overflow)
+        );
+        assert_eq!(
+            ctr_decrypt(
+                &cipher,
+                &(u128::MAX - 1).to_be_bytes(),
+                &[0; 3 * AES_BLOCK_LEN]
+            ),
+            Err(CryptoError::Overflow)
+        );
+    }
+
+    #[test]
+    fn padding_round_trips() {
+        for len in 0..40 {
+            let data = vec![0xa5u8; len];
+            let padded = pkcs7_pad(&data);
+            assert_eq!(padded.len() % AES_BLOCK_LEN, 0);
+            assert!(padded.len() > data.len());
+            assert_eq!(pkcs7_unpad(padded).expect("failed to unpad"), data);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_ciphertext() {
+        let cipher = Aes128::new(&block(SP800_38A_KEY));
+        assert_eq!(
+            ecb_decrypt(&cipher, &[0u8; 15]),
+            Err(CryptoError::InvalidLength(15))
+        );
+        assert_eq!(
+            ecb_decrypt(&cipher, &[]),
+            Err(CryptoError::InvalidLength(0))
+        );
+        assert_eq!(
+            pkcs7_unpad(vec![1, 2, 3, 0]),
+            Err(CryptoError::InvalidPadding)
+        );
+        assert_eq!(
+            pkcs7_unpad(vec![1, 2, 2, 3]),
+            Err(CryptoError::InvalidPadding)
+        );
+    }
+}
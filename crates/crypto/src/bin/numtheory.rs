@@ -0,0 +1,478 @@
+//! Number theory from the command line, for scripting the crypto crate.
+//!
+//! Run `cargo r --release --bin numtheory -- --help` for usage.
+use std::{
+    env,
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    process::ExitCode,
+};
+
+use anyhow::{anyhow, Result};
+use rand::Rng;
+
+use crypto::{
+    fast_exp_mod, find_prime, find_primes, gcd, inverse_mod, is_prime, next_prime, random_exponent,
+    sieve_of_eratosthenes, sieve_to_primes, totient,
+};
+
+const USAGE: &str = "\
+Usage: numtheory [--json] [--file PATH] <COMMAND> [NUMBERS...]
+
+Commands (and the numbers each one takes):
+  factor N            prime factors of N
+  isprime N           whether N is prime
+  nextprime N         the smallest prime greater than N
+  sieve MAX           every prime up to MAX
+  gcd A B             greatest common divisor of A and B
+  modpow B E M        B^E mod M
+  modinv A M          the inverse of A mod M
+  rsa-keygen DIGITS   a toy RSA key pair from two DIGITS digit primes (1-9)
+
+Numbers on the command line are taken in groups, one group per command.
+Without numbers each line of PATH (or stdin when there is no --file) is
+one group, with the numbers separated by whitespace. Blank lines and lines
+starting with # are skipped.
+
+Options:
+  --json       print one JSON object per line instead of plain text
+  --file PATH  read groups from PATH, use - for stdin
+  -h, --help   print this help
+";
+
+fn main() -> ExitCode {
+    let opts = match Options::parse(env::args().skip(1)) {
+        Ok(Some(opts)) => opts,
+        Ok(None) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match run_main(&opts) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_main(opts: &Options) -> Result<bool> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut err = io::stderr();
+    let mut rng = rand::thread_rng();
+
+    let groups: Box<dyn BufRead> = match opts.file.as_deref() {
+        _ if !opts.numbers.is_empty() => Box::new(io::empty()),
+        None | Some("-") => Box::new(io::stdin().lock()),
+        Some(path) => Box::new(BufReader::new(
+            File::open(path).map_err(|e| anyhow!("failed to open {path}: {e}"))?,
+        )),
+    };
+
+    run(opts, &mut rng, groups, &mut out, &mut err)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Command {
+    Factor,
+    IsPrime,
+    NextPrime,
+    Sieve,
+    Gcd,
+    ModPow,
+    ModInv,
+    RsaKeygen,
+}
+
+impl Command {
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "factor" => Command::Factor,
+            "isprime" => Command::IsPrime,
+            "nextprime" => Command::NextPrime,
+            "sieve" => Command::Sieve,
+            "gcd" => Command::Gcd,
+            "modpow" => Command::ModPow,
+            "modinv" => Command::ModInv,
+            "rsa-keygen" => Command::RsaKeygen,
+            _ => return Err(anyhow!("unknown command: {name}")),
+        })
+    }
+
+    /// How many numbers make up one group of input.
+    fn arity(self) -> usize {
+        match self {
+            Command::Gcd | Command::ModInv => 2,
+            Command::ModPow => 3,
+            _ => 1,
+        }
+    }
+
+    fn run(self, rng: &mut impl Rng, nums: &[usize]) -> Result<Answer> {
+        Ok(match (self, nums) {
+            (Command::Factor, &[n]) => Answer::Factors(find_primes(n)),
+            (Command::IsPrime, &[n]) => Answer::IsPrime(is_prime(n)),
+            (Command::NextPrime, &[n]) => Answer::NextPrime(next_prime(n)?),
+            (Command::Sieve, &[max]) => {
+                Answer::Primes(sieve_to_primes(&sieve_of_eratosthenes(max)?))
+            }
+            (Command::Gcd, &[a, b]) => Answer::Gcd(gcd(a, b)),
+            (Command::ModPow, &[b, e, m]) => Answer::ModPow(fast_exp_mod(b, e, m)?),
+            (Command::ModInv, &[a, m]) => Answer::ModInv(inverse_mod(a, m)?),
+            (Command::RsaKeygen, &[digits]) => rsa_keygen(rng, digits)?,
+            _ => {
+                return Err(anyhow!(
+                    "expected {} number(s), got {}",
+                    self.arity(),
+                    nums.len()
+                ))
+            }
+        })
+    }
+}
+
+fn rsa_keygen(rng: &mut impl Rng, digits: usize) -> Result<Answer> {
+    // Two 9 digit primes are the most that keep n inside a u64.
+    if !(1..=9).contains(&digits) {
+        return Err(anyhow!("digits must be from 1-9, got {digits}"));
+    }
+
+    let max = 10usize.pow(digits as u32) - 1;
+    let min = (max / 10 + 1).max(3);
+    let mut prime = || find_prime(rng, min, max);
+
+    let p = prime()?;
+    let mut q = prime()?;
+    while q == p {
+        q = prime()?;
+    }
+
+    let n = p * q;
+    let λn = totient(p, q)?;
+    let e = random_exponent(rng, λn)?;
+    let d = inverse_mod(e, λn)?;
+
+    Ok(Answer::RsaKeys { n, e, d, p, q, λn })
+}
+
+#[derive(Debug, PartialEq)]
+enum Answer {
+    Factors(Vec<usize>),
+    IsPrime(bool),
+    NextPrime(usize),
+    Primes(Vec<usize>),
+    Gcd(usize),
+    ModPow(usize),
+    ModInv(usize),
+    RsaKeys {
+        n: usize,
+        e: usize,
+        d: usize,
+        p: usize,
+        q: usize,
+        λn: usize,
+    },
+}
+
+fn join(nums: &[usize], sep: &str) -> String {
+    nums.iter()
+        .map(|n| n.to_string())
+        .collect::<Vec<_>>()
+        .join(sep)
+}
+
+impl Answer {
+    fn text(&self) -> String {
+        match self {
+            Answer::Factors(v) | Answer::Primes(v) => join(v, " "),
+            Answer::IsPrime(b) => b.to_string(),
+            Answer::NextPrime(n) | Answer::Gcd(n) | Answer::ModPow(n) | Answer::ModInv(n) => {
+                n.to_string()
+            }
+            Answer::RsaKeys { n, e, d, p, q, λn } => {
+                format!("n={n} e={e} d={d} p={p} q={q} lambda={λn}")
+            }
+        }
+    }
+
+    /// The answer as the body of a JSON object, without the braces.
+    fn json(&self) -> String {
+        match self {
+            Answer::Factors(v) => format!(r#""factors":[{}]"#, join(v, ",")),
+            Answer::Primes(v) => format!(r#""primes":[{}]"#, join(v, ",")),
+            Answer::IsPrime(b) => format!(r#""prime":{b}"#),
+            Answer::NextPrime(n) => format!(r#""next_prime":{n}"#),
+            Answer::Gcd(n) => format!(r#""gcd":{n}"#),
+            Answer::ModPow(n) => format!(r#""modpow":{n}"#),
+            Answer::ModInv(n) => format!(r#""modinv":{n}"#),
+            Answer::RsaKeys { n, e, d, p, q, λn } => {
+                format!(r#""n":{n},"e":{e},"d":{d},"p":{p},"q":{q},"lambda":{λn}"#)
+            }
+        }
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str(r#"\""#),
+            '\\' => out.push_str(r"\\"),
+            '\n' => out.push_str(r"\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!(r"\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+
+    out
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    command: Option<Command>,
+    json: bool,
+    file: Option<String>,
+    numbers: Vec<String>,
+}
+
+impl Options {
+    /// Returns `None` when help was asked for.
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Self>> {
+        let mut opts = Options::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--json" => opts.json = true,
+                "--file" => {
+                    opts.file = Some(args.next().ok_or_else(|| anyhow!("--file needs a path"))?)
+                }
+                _ if opts.command.is_none() => opts.command = Some(Command::parse(&arg)?),
+                _ => opts.numbers.push(arg),
+            }
+        }
+
+        if opts.command.is_none() {
+            return Err(anyhow!("missing command"));
+        }
+        if !opts.numbers.is_empty() && opts.file.is_some() {
+            return Err(anyhow!(
+                "give numbers as arguments or with --file, not both"
+            ));
+        }
+
+        Ok(Some(opts))
+    }
+}
+
+/// Runs the command over every group, from the arguments if there are any
+/// and otherwise from `lines`. Returns false if any group failed.
+fn run(
+    opts: &Options,
+    rng: &mut impl Rng,
+    lines: impl BufRead,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> Result<bool> {
+    let command = opts.command.ok_or_else(|| anyhow!("missing command"))?;
+
+    let groups: Vec<String> = if opts.numbers.is_empty() {
+        lines
+            .lines()
+            .filter(|l| {
+                l.as_ref()
+                    .map_or(true, |l| !l.trim().is_empty() && !l.trim().starts_with('#'))
+            })
+            .collect::<io::Result<_>>()?
+    } else {
+        opts.numbers
+            .chunks(command.arity())
+            .map(|c| c.join(" "))
+            .collect()
+    };
+
+    let mut ok = true;
+    for group in groups {
+        let parsed = group
+            .split_whitespace()
+            .map(|n| n.parse::<usize>().map_err(|e| anyhow!("{n:?}: {e}")))
+            .collect::<Result<Vec<_>>>();
+
+        let result = parsed
+            .as_ref()
+            .map_err(|e| anyhow!("{e}"))
+            .and_then(|nums| command.run(rng, nums));
+
+        let input = match &parsed {
+            Ok(nums) => format!("[{}]", join(nums, ",")),
+            Err(_) => json_string(group.trim()),
+        };
+
+        match (&result, opts.json) {
+            (Ok(answer), true) => writeln!(out, r#"{{"input":{input},{}}}"#, answer.json())?,
+            (Ok(answer), false) => writeln!(out, "{}: {}", group.trim(), answer.text())?,
+            (Err(e), true) => writeln!(
+                out,
+                r#"{{"input":{input},"error":{}}}"#,
+                json_string(&e.to_string())
+            )?,
+            (Err(e), false) => writeln!(err, "{}: error: {e}", group.trim())?,
+        }
+        ok &= result.is_ok();
+    }
+
+    Ok(ok)
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    fn run_str(argv: &str, stdin: &str) -> (bool, String, String) {
+        let opts = Options::parse(args(argv))
+            .expect("failed to parse")
+            .expect("no help");
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let ok = run(
+            &opts,
+            &mut rand::thread_rng(),
+            stdin.as_bytes(),
+            &mut out,
+            &mut err,
+        )
+        .expect("failed to run");
+
+        (
+            ok,
+            String::from_utf8(out).expect("bad utf8"),
+            String::from_utf8(err).expect("bad utf8"),
+        )
+    }
+
+    #[test]
+    fn parses_options() {
+        let opts = Options::parse(args("--json factor 12 13"))
+            .expect("failed to parse")
+            .expect("no help");
+        assert_eq!(
+            opts,
+            Options {
+                command: Some(Command::Factor),
+                json: true,
+                file: None,
+                numbers: args("12 13"),
+            }
+        );
+
+        assert!(Options::parse(args("--help factor"))
+            .expect("failed to parse")
+            .is_none());
+        assert!(Options::parse(args("frobnicate 1")).is_err());
+        assert!(Options::parse(args("--json")).is_err());
+        assert!(Options::parse(args("factor --file")).is_err());
+        assert!(Options::parse(args("factor --file x 12")).is_err());
+    }
+
+    #[test]
+    fn runs_from_arguments() {
+        let (ok, out, _) = run_str("factor 714 17", "");
+        assert!(ok);
+        assert_eq!(out, "714: 2 3 7 17\n17: 17\n");
+
+        let (ok, out, _) = run_str("gcd 12 18 7469 2464", "");
+        assert!(ok);
+        assert_eq!(out, "12 18: 6\n7469 2464: 77\n");
+
+        let (ok, out, _) = run_str("modpow 4 13 497", "");
+        assert!(ok);
+        assert_eq!(out, "4 13 497: 445\n");
+    }
+
+    #[test]
+    fn runs_from_lines() {
+        let stdin = "# a comment\n3 7\n\n  3 26  \n";
+        let (ok, out, _) = run_str("modinv", stdin);
+        assert!(ok);
+        assert_eq!(out, "3 7: 5\n3 26: 9\n");
+
+        let (ok, out, _) = run_str("isprime", "561\n987654103\n");
+        assert!(ok);
+        assert_eq!(out, "561: false\n987654103: true\n");
+
+        let (ok, out, _) = run_str("nextprime", "24\n");
+        assert!(ok);
+        assert_eq!(out, "24: 29\n");
+
+        let (ok, out, _) = run_str("sieve", "30\n");
+        assert!(ok);
+        assert_eq!(out, "30: 2 3 5 7 11 13 17 19 23 29\n");
+    }
+
+    #[test]
+    fn prints_json() {
+        let (ok, out, _) = run_str("--json factor", "714\n");
+        assert!(ok);
+        assert_eq!(out, "{\"input\":[714],\"factors\":[2,3,7,17]}\n");
+
+        let (ok, out, _) = run_str("--json modinv 4 26", "");
+        assert!(!ok);
+        assert_eq!(
+            out,
+            "{\"input\":[4,26],\"error\":\"4 is not invertible mod 26\"}\n"
+        );
+
+        let (ok, out, _) = run_str("--json isprime", "abc\n");
+        assert!(!ok);
+        assert!(out.starts_with("{\"input\":\"abc\",\"error\":"));
+    }
+
+    #[test]
+    fn reports_errors_and_keeps_going() {
+        let (ok, out, err) = run_str("modpow", "2 3 0\n2 3 5\n2 3\n");
+        assert!(!ok);
+        assert_eq!(out, "2 3 5: 3\n");
+        assert_eq!(
+            err,
+            "2 3 0: error: modulus must be non-zero\n2 3: error: expected 3 number(s), got 2\n"
+        );
+    }
+
+    #[test]
+    fn generates_rsa_keys() {
+        let mut rng = rand::thread_rng();
+        for digits in 1..=9 {
+            match Command::RsaKeygen.run(&mut rng, &[digits]) {
+                Ok(Answer::RsaKeys { n, e, d, p, q, .. }) => {
+                    assert_eq!(n, p * q);
+                    assert!(is_prime(p) && is_prime(q) && p != q);
+                    let msg = 2;
+                    let cipher = fast_exp_mod(msg, e, n).expect("failed to encrypt");
+                    assert_eq!(fast_exp_mod(cipher, d, n), Ok(msg));
+                }
+                other => panic!("unexpected {other:?}"),
+            }
+        }
+        assert!(Command::RsaKeygen.run(&mut rng, &[10]).is_err());
+    }
+
+    #[test]
+    fn escapes_json() {
+        assert_eq!(json_string("a\"b\\c\nd\u{1}"), r#""a\"b\\c\nd\u0001""#);
+        assert_eq!(json_string("λ(n)"), "\"λ(n)\"");
+    }
+}
@@ -0,0 +1,41 @@
+use std::fmt::{Display, Error, Formatter};
+
+/// Everything that can go wrong in the crypto crate, so callers can match on
+/// the failure instead of parsing a message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CryptoError {
+    /// `value` has no inverse because it shares a factor with `modulus`.
+    NotInvertible { value: usize, modulus: usize },
+    /// A modulus of zero was given.
+    ZeroModulus,
+    /// The result does not fit in the output type.
+    Overflow,
+    /// An argument is outside the range the function accepts.
+    InvalidRange(String),
+    /// A ciphertext is not a whole number of blocks.
+    InvalidLength(usize),
+    /// Decrypted data does not end in valid PKCS#7 padding.
+    InvalidPadding,
+    /// A factoring method gave up without splitting its input.
+    NoFactorFound(u128),
+}
+
+impl Display for CryptoError {
+    fn fmt(&self, f: &mut Formatter) -> std::result::Result<(), Error> {
+        match self {
+            CryptoError::NotInvertible { value, modulus } => {
+                write!(f, "{value} is not invertible mod {modulus}")
+            }
+            CryptoError::ZeroModulus => write!(f, "modulus must be non-zero"),
+            CryptoError::Overflow => write!(f, "arithmetic overflow"),
+            CryptoError::InvalidRange(msg) => write!(f, "invalid range: {msg}"),
+            CryptoError::InvalidLength(len) => {
+                write!(f, "length {len} is not a whole number of blocks")
+            }
+            CryptoError::InvalidPadding => write!(f, "invalid padding"),
+            CryptoError::NoFactorFound(n) => write!(f, "no factor found for {n}"),
+        }
+    }
+}
+
+impl std::error::Error for CryptoError {}
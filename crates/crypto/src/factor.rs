@@ -1,6 +1,10 @@
 use std::sync::OnceLock;
 
-use crate::{sieve_of_eratosthenes, sieve_to_primes};
+use crate::{
+    is_prime_u128, isqrt, perfect_power, sieve_of_eratosthenes, sieve_to_primes, CryptoError,
+};
+
+mod quadratic_sieve;
 
 static BUNCHA_PRIMES: OnceLock<Vec<usize>> = OnceLock::new();
 
@@ -15,18 +19,42 @@ fn buncha_primes() -> &'static Vec<usize> {
     // This takes about 6.2 seconds to initialize a vec of usize
     // for the primes up to 100M.
     // Trading time for space here.
-    BUNCHA_PRIMES.get_or_init(|| sieve_to_primes(&sieve_of_eratosthenes(100_000_000)))
+    BUNCHA_PRIMES.get_or_init(|| {
+        sieve_to_primes(&sieve_of_eratosthenes(100_000_000).expect("100M is a valid sieve max"))
+    })
 }
 
 // Run `cargo r --release --example factor` to
 // interactively test in terminal.
-pub fn find_primes(mut num: usize) -> Vec<usize> {
+pub fn find_primes(num: usize) -> Vec<usize> {
+    with_perfect_powers(num, trial_division)
+}
+
+pub fn find_primes_sieve(num: usize) -> Vec<usize> {
+    with_perfect_powers(num, sieve_division)
+}
+
+// If num = base^exp, factor the (smaller) base and repeat each factor exp
+// times instead of trial dividing num itself.
+fn with_perfect_powers(num: usize, factorize: fn(usize) -> Vec<usize>) -> Vec<usize> {
+    match perfect_power(num) {
+        Some((base, exp)) => factorize(base)
+            .into_iter()
+            .flat_map(|p| std::iter::repeat_n(p, exp as usize))
+            .collect(),
+        None => factorize(num),
+    }
+}
+
+fn trial_division(mut num: usize) -> Vec<usize> {
     let mut i = 2;
     let mut factors: Vec<usize> = Vec::new();
+    // Computed exactly so i * i can't overflow near usize::MAX.
+    let mut end = isqrt(num);
 
-    while i * i <= num {
+    while i <= end {
         // doesn't divide evenly
-        if num % i != 0 {
+        if !num.is_multiple_of(i) {
             // it isn't 2.
             // if i != 2 {
             //     i += 1;
@@ -34,6 +62,7 @@ pub fn find_primes(mut num: usize) -> Vec<usize> {
             i += 1;
         } else {
             num /= i;
+            end = isqrt(num);
             factors.push(i)
         }
     }
@@ -46,20 +75,19 @@ pub fn find_primes(mut num: usize) -> Vec<usize> {
     factors
 }
 
-pub fn find_primes_sieve(mut num: usize) -> Vec<usize> {
+fn sieve_division(mut num: usize) -> Vec<usize> {
     let mut factors: Vec<usize> = Vec::new();
+    let mut end = isqrt(num);
 
     for i in buncha_primes() {
-        if i * i <= num {
-            // doesn't divide evenly
-            if num % i == 0 {
-                // it isn't 2.
-                // if i != 2 {
-                //     i += 1;
-                // }
-                factors.push(*i);
-                num /= i;
-            }
+        if *i > end {
+            break;
+        }
+        // A prime can divide more than once, e.g. 8 = 2 * 2 * 2.
+        while num.is_multiple_of(*i) {
+            factors.push(*i);
+            num /= i;
+            end = isqrt(num);
         }
     }
     if num > 1 {
@@ -70,6 +98,69 @@ pub fn find_primes_sieve(mut num: usize) -> Vec<usize> {
     factors
 }
 
+/// Trial division bound before `find_primes_u128` moves on to the
+/// quadratic sieve.
+const TRIAL_DIVISION_BOUND: u64 = 10_000;
+
+/// Finds a non-trivial factor of the composite `n` with the
+/// self-initializing quadratic sieve. Unlike trial division (or Pollard's
+/// rho), the running time only depends on the size of `n`, so balanced
+/// semiprimes are no harder than any other composite.
+///
+/// `n` must be below 2^127 so the sieve values fit in an i128.
+pub fn quadratic_sieve(n: u128) -> Result<u128, CryptoError> {
+    if n < 4 || n > i128::MAX as u128 {
+        return Err(CryptoError::InvalidRange(format!(
+            "quadratic sieve needs 4 <= n < 2^127, got {n}"
+        )));
+    }
+    if n.is_multiple_of(2) {
+        return Ok(2);
+    }
+    // Prime powers only give trivial congruences.
+    if let Some((base, _)) = perfect_power(n) {
+        return Ok(base);
+    }
+    if is_prime_u128(n) {
+        return Err(CryptoError::InvalidRange(format!("{n} is prime")));
+    }
+
+    quadratic_sieve::siqs(n).ok_or(CryptoError::NoFactorFound(n))
+}
+
+/// The prime factors of `n`, smallest first. Small factors come out by
+/// trial division and the rest are split with the quadratic sieve.
+pub fn find_primes_u128(n: u128) -> Result<Vec<u128>, CryptoError> {
+    if n < 2 {
+        return Ok(vec![]);
+    }
+
+    let (mut factors, rest) = quadratic_sieve::trial_divide(n, TRIAL_DIVISION_BOUND);
+
+    let mut composites = vec![rest];
+    while let Some(c) = composites.pop() {
+        if c == 1 {
+            continue;
+        }
+        if is_prime_u128(c) {
+            factors.push(c);
+            continue;
+        }
+        if let Some((base, exp)) = perfect_power(c) {
+            composites.extend(std::iter::repeat_n(base, exp as usize));
+            continue;
+        }
+
+        let f = quadratic_sieve(c)?;
+        composites.push(f);
+        composites.push(c / f);
+    }
+
+    factors.sort_unstable();
+
+    Ok(factors)
+}
+
 // // Just use the iter().product method.
 // fn multiply_vector(v: &[usize]) -> usize {
 //     v.iter().product()
@@ -79,6 +170,8 @@ pub fn find_primes_sieve(mut num: usize) -> Vec<usize> {
 mod unit {
     use super::*;
 
+    use crate::next_prime;
+
     #[test]
     fn finds_factors() {
         assert_eq!(find_primes(25), vec![5, 5]);
@@ -95,10 +188,76 @@ mod unit {
         assert_eq!(find_primes(64374108854777), vec![64374108854777]);
     }
 
+    #[test]
+    fn finds_perfect_power_factors() {
+        assert_eq!(find_primes(8), vec![2, 2, 2]);
+        assert_eq!(find_primes(36), vec![2, 2, 3, 3]);
+        assert_eq!(find_primes(6880691 * 6880691), vec![6880691, 6880691]);
+        assert_eq!(find_primes(1 << 63), vec![2; 63]);
+    }
+
+    #[test]
+    fn quadratic_sieve_factors() {
+        // Trial division takes ~6.9M steps for this one.
+        let f = quadratic_sieve(6795742697625173).expect("failed to factor");
+        assert!(f == 6880691 || f == 987654103);
+
+        assert_eq!(quadratic_sieve(1 << 20), Ok(2));
+        assert_eq!(quadratic_sieve(3u128.pow(50)), Ok(3));
+        assert!(matches!(
+            quadratic_sieve(987654103),
+            Err(CryptoError::InvalidRange(_))
+        ));
+        assert!(matches!(
+            quadratic_sieve(u128::MAX),
+            Err(CryptoError::InvalidRange(_))
+        ));
+    }
+
+    #[test]
+    fn finds_u128_factors() {
+        assert_eq!(find_primes_u128(0), Ok(vec![]));
+        assert_eq!(find_primes_u128(1), Ok(vec![]));
+        assert_eq!(find_primes_u128(714), Ok(vec![2, 3, 7, 17]));
+        assert_eq!(
+            find_primes_u128(6795742697625173),
+            Ok(vec![6880691, 987654103])
+        );
+        assert_eq!(
+            find_primes_u128(2 * 6880691 * 6880691 * 987654103),
+            Ok(vec![2, 6880691, 6880691, 987654103])
+        );
+        assert_eq!(
+            find_primes_u128(4_294_967_311 * 4_294_967_357 * 1_000_003),
+            Ok(vec![1_000_003, 4_294_967_311, 4_294_967_357])
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn quadratic_sieve_large_semiprimes() {
+        // Run with `cargo t --release -- --ignored` as these take a few
+        // seconds each in release and much longer in debug.
+        for (p, q) in [
+            // 30 digits
+            (1_000_000_000_000_000, 3_000_000_000_000_000),
+            // 34 digits
+            (10_000_000_000_000_000, 900_000_000_000_000_000),
+            // 38 digits
+            (10_000_000_000_000_000_000, 13_000_000_000_000_000_000),
+        ] {
+            let p = next_prime(p).expect("failed to find a prime") as u128;
+            let q = next_prime(q).expect("failed to find a prime") as u128;
+            assert_eq!(find_primes_u128(p * q), Ok(vec![p, q]));
+        }
+    }
+
     #[test]
     fn sieves_factors() {
         // makes the tests faster. 6 seconds with 100M
-        BUNCHA_PRIMES.get_or_init(|| sieve_to_primes(&sieve_of_eratosthenes(100_000)));
+        BUNCHA_PRIMES.get_or_init(|| {
+            sieve_to_primes(&sieve_of_eratosthenes(100_000).expect("failed to sieve"))
+        });
         assert_eq!(find_primes_sieve(25), vec![5, 5]);
         assert_eq!(find_primes_sieve(11), vec![11]);
         assert_eq!(find_primes_sieve(714), vec![2, 3, 7, 17]);
@@ -106,6 +265,9 @@ mod unit {
         assert_eq!(find_primes_sieve(147), vec![3, 7, 7]);
         assert_eq!(find_primes_sieve(17), vec![17]);
         assert_eq!(find_primes_sieve(330), vec![2, 3, 5, 11]);
+        assert_eq!(find_primes_sieve(8), vec![2, 2, 2]);
+        assert_eq!(find_primes_sieve(24), vec![2, 2, 2, 3]);
+        assert_eq!(find_primes_sieve(9973 * 9973 * 9973), vec![9973; 3]);
     }
 
     #[test]
@@ -0,0 +1,650 @@
+//! Self-initializing quadratic sieve (SIQS).
+//!
+//! For n we look for many x where Q(x) = (ax + b)^2 - n is smooth, i.e.
+//! factors completely over a base of small primes. Since
+//! (ax + b)^2 ≡ Q(x) (mod n), multiplying together a subset of relations
+//! whose Q values have only even exponents gives X^2 ≡ Y^2 (mod n), and
+//! gcd(X - Y, n) is a factor about half the time.
+//!
+//! Self-initializing means each `a` (a product of factor base primes) is
+//! reused for 2^(s-1) different `b`s, and the sieve roots for every `b` come
+//! cheaply from values computed once per `a`.
+//!
+//! See Contini, "Factoring integers with the self-initializing quadratic
+//! sieve" (1997) for the details.
+use std::collections::{HashMap, HashSet};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{gcd, isqrt, mul_mod_u128, sieve_of_eratosthenes, sieve_to_primes};
+
+/// Relations beyond the factor base size, so there are spare dependencies.
+const EXTRA_RELATIONS: usize = 20;
+
+/// Primes below this are not sieved; the threshold allows for them instead.
+const MIN_SIEVE_PRIME: u64 = 30;
+
+/// Primes below this are never used as factors of `a`.
+const MIN_A_PRIME: u64 = 50;
+
+/// Factor base size and sieve half-width by the number of decimal digits.
+fn parameters(digits: usize) -> (usize, i64) {
+    match digits {
+        0..=14 => (40, 4_096),
+        15..=18 => (80, 8_192),
+        19..=22 => (120, 16_384),
+        23..=26 => (180, 32_768),
+        27..=30 => (260, 65_536),
+        31..=34 => (380, 65_536),
+        _ => (560, 65_536),
+    }
+}
+
+struct FactorBase {
+    primes: Vec<u64>,
+    /// A square root of n mod each prime.
+    sqrt_n: Vec<u64>,
+    /// log2 of each prime, rounded.
+    logp: Vec<u8>,
+}
+
+/// A relation (lhs)^2 ≡ (-1)^sign * Π primes[i]^e_i * large^2 (mod n).
+#[derive(Clone)]
+struct Relation {
+    lhs: u128,
+    /// Indices into the factor base, one per power (so repeats are kept).
+    factors: Vec<usize>,
+    negative: bool,
+    /// Product of the large primes shared by the two partials it came from.
+    large: u128,
+}
+
+fn pow_mod(mut b: u64, mut e: u64, m: u64) -> u64 {
+    let mut r = 1 % m;
+    b %= m;
+    while e > 0 {
+        if e & 1 == 1 {
+            r = ((r as u128 * b as u128) % m as u128) as u64;
+        }
+        b = ((b as u128 * b as u128) % m as u128) as u64;
+        e >>= 1;
+    }
+
+    r
+}
+
+fn inverse_mod_prime(a: u64, p: u64) -> u64 {
+    pow_mod(a, p - 2, p)
+}
+
+/// A square root of `n` mod the odd prime `p`, if `n` is a quadratic
+/// residue, using Tonelli-Shanks.
+fn sqrt_mod(n: u64, p: u64) -> Option<u64> {
+    let n = n % p;
+    if p == 2 || n == 0 {
+        return Some(n);
+    }
+    if pow_mod(n, (p - 1) / 2, p) != 1 {
+        return None;
+    }
+
+    let s = (p - 1).trailing_zeros();
+    let q = (p - 1) >> s;
+    let mut z = 2;
+    while pow_mod(z, (p - 1) / 2, p) != p - 1 {
+        z += 1;
+    }
+
+    let mul = |a: u64, b: u64| ((a as u128 * b as u128) % p as u128) as u64;
+    let mut m = s;
+    let mut c = pow_mod(z, q, p);
+    let mut t = pow_mod(n, q, p);
+    let mut r = pow_mod(n, q.div_ceil(2), p);
+    while t != 1 {
+        let mut i = 0;
+        let mut t2 = t;
+        while t2 != 1 {
+            t2 = mul(t2, t2);
+            i += 1;
+        }
+        let b = pow_mod(c, 1 << (m - i - 1), p);
+        m = i;
+        c = mul(b, b);
+        t = mul(t, c);
+        r = mul(r, b);
+    }
+
+    Some(r)
+}
+
+/// Either a factor base, or a factor of n found while building it.
+fn factor_base(n: u128, size: usize) -> Result<FactorBase, u128> {
+    let mut primes = Vec::with_capacity(size);
+    let mut sqrt_n = Vec::with_capacity(size);
+
+    // Roughly half of all primes are quadratic residues, and the nth prime
+    // is about n ln n, so this bound is plenty.
+    let mut bound = (size * 4).max(100);
+    bound = (bound as f64 * (bound as f64).ln()) as usize;
+    let candidates = sieve_to_primes(&sieve_of_eratosthenes(bound).expect("bound is at least 2"));
+
+    for p in candidates.into_iter().map(|p| p as u64) {
+        let n_mod_p = (n % p as u128) as u64;
+        if n_mod_p == 0 {
+            if n != p as u128 {
+                return Err(p as u128);
+            }
+            continue;
+        }
+        if let Some(root) = sqrt_mod(n_mod_p, p) {
+            primes.push(p);
+            sqrt_n.push(root);
+            if primes.len() == size {
+                break;
+            }
+        }
+    }
+
+    let logp = primes
+        .iter()
+        .map(|p| (*p as f64).log2().round() as u8)
+        .collect();
+
+    Ok(FactorBase {
+        primes,
+        sqrt_n,
+        logp,
+    })
+}
+
+/// Picks the primes whose product is the next `a`, close to `target`.
+struct ACoefficients {
+    rng: StdRng,
+    /// Candidate factor base indices.
+    pool: Vec<usize>,
+    s: usize,
+    seen: HashSet<u128>,
+}
+
+impl ACoefficients {
+    fn new(fb: &FactorBase, target: f64, seed: u64) -> Option<Self> {
+        let pool: Vec<usize> = (0..fb.primes.len())
+            .filter(|&i| fb.primes[i] >= MIN_A_PRIME)
+            .collect();
+        let largest = fb.primes[*pool.last()?] as f64;
+
+        // The fewest primes that can reach the target from inside the base.
+        let s = (1..=pool.len().min(20)).find(|&s| target.powf(1.0 / s as f64) <= largest)?;
+
+        Some(Self {
+            rng: StdRng::seed_from_u64(seed),
+            pool,
+            s,
+            seen: HashSet::new(),
+        })
+    }
+
+    fn next(&mut self, fb: &FactorBase, target: f64) -> Option<Vec<usize>> {
+        let ideal = target.powf(1.0 / self.s as f64);
+        // Pool entries closest to the ideal prime size.
+        let center = self
+            .pool
+            .partition_point(|&i| (fb.primes[i] as f64) < ideal)
+            .min(self.pool.len() - 1);
+
+        for tries in 0..1_000 {
+            // Widen the window whenever it keeps producing repeats.
+            let window = ((4 * self.s).max(16) + tries / 4).min(self.pool.len());
+            let lo = center
+                .saturating_sub(window / 2)
+                .min(self.pool.len() - window);
+
+            let mut chosen: Vec<usize> = Vec::with_capacity(self.s);
+            let mut product = 1f64;
+            while chosen.len() + 1 < self.s {
+                let idx = self.pool[lo + self.rng.gen_range(0..window)];
+                if !chosen.contains(&idx) {
+                    product *= fb.primes[idx] as f64;
+                    chosen.push(idx);
+                }
+            }
+
+            if self.s == 1 {
+                chosen.push(self.pool[lo + self.rng.gen_range(0..window)]);
+            } else {
+                // The last prime brings the product as close to the target
+                // as the pool allows.
+                let want = target / product;
+                let last = self
+                    .pool
+                    .iter()
+                    .filter(|i| !chosen.contains(i))
+                    .min_by(|a, b| {
+                        let da = (fb.primes[**a] as f64 / want).ln().abs();
+                        let db = (fb.primes[**b] as f64 / want).ln().abs();
+                        da.total_cmp(&db)
+                    })?;
+                chosen.push(*last);
+            }
+
+            let a: u128 = chosen.iter().map(|i| fb.primes[*i] as u128).product();
+            if self.seen.insert(a) {
+                chosen.sort_unstable();
+                return Some(chosen);
+            }
+        }
+
+        None
+    }
+}
+
+/// Tries to find a non-trivial factor of the odd composite `n`, which must
+/// not be a perfect power.
+///
+/// Returns `None` if it gives up, which happens when `n` is prime or a
+/// prime power, since then every X^2 ≡ Y^2 congruence is trivial.
+pub(super) fn siqs(n: u128) -> Option<u128> {
+    let digits = n.ilog10() as usize + 1;
+    let (fb_size, m) = parameters(digits);
+
+    let fb = match factor_base(n, fb_size) {
+        Ok(fb) => fb,
+        Err(factor) => return Some(factor),
+    };
+    let num_primes = fb.primes.len();
+    let largest = *fb.primes.last()? as u128;
+    // Cofactors below this, after dividing out the base, are large primes.
+    let large_prime_bound = largest * largest.min(128);
+
+    // |Q(x)| / a is at most about m * sqrt(n / 2), and a ≈ sqrt(2n) / m
+    // keeps the values at both ends of the interval balanced.
+    let target = (2.0 * n as f64).sqrt() / m as f64;
+    let log_max = (m as f64).log2() + (n as f64).log2() / 2.0 - 0.5;
+    let slack = 1.8 * (largest as f64).log2() + 4.0;
+    let threshold = (log_max - slack).max(1.0) as u8;
+
+    let mut a_gen = ACoefficients::new(&fb, target, n as u64)?;
+
+    let mut relations: Vec<Relation> = Vec::new();
+    let mut seen_lhs: HashSet<u128> = HashSet::new();
+    let mut partials: HashMap<u128, Relation> = HashMap::new();
+    let mut sieve = vec![0u8; 2 * m as usize];
+
+    let wanted = num_primes + 1 + EXTRA_RELATIONS;
+    let mut attempts = 0;
+    while relations.len() < wanted {
+        attempts += 1;
+        if attempts > 10 {
+            // Plenty of relations and still only trivial dependencies.
+            return None;
+        }
+
+        while relations.len() < wanted {
+            let a_factors = a_gen.next(&fb, target)?;
+            let a: u128 = a_factors.iter().map(|i| fb.primes[*i] as u128).product();
+
+            // B_l ≡ sqrt(n) (mod q_l) and ≡ 0 (mod q_j) for j != l, so any
+            // b = Σ ±B_l has b^2 ≡ n (mod a).
+            let big_b: Vec<u128> = a_factors
+                .iter()
+                .map(|&l| {
+                    let q = fb.primes[l];
+                    let a_q = a / q as u128;
+                    let inv = inverse_mod_prime((a_q % q as u128) as u64, q);
+                    let mut gamma = ((fb.sqrt_n[l] as u128 * inv as u128) % q as u128) as u64;
+                    if gamma > q / 2 {
+                        gamma = q - gamma;
+                    }
+                    a_q * gamma as u128
+                })
+                .collect();
+
+            // a^-1 mod p for every prime not dividing a.
+            let a_inv: Vec<Option<u64>> = fb
+                .primes
+                .iter()
+                .enumerate()
+                .map(|(i, &p)| {
+                    (!a_factors.contains(&i)).then(|| inverse_mod_prime((a % p as u128) as u64, p))
+                })
+                .collect();
+
+            // The sign of B_0 is fixed since b and -b give the same values.
+            for signs in 0..(1u32 << (big_b.len() - 1)) {
+                let b: i128 = big_b
+                    .iter()
+                    .enumerate()
+                    .map(|(l, bl)| {
+                        if l > 0 && signs & (1 << (l - 1)) != 0 {
+                            -(*bl as i128)
+                        } else {
+                            *bl as i128
+                        }
+                    })
+                    .sum();
+
+                sieve_polynomial(
+                    n,
+                    &fb,
+                    &a_factors,
+                    &a_inv,
+                    a,
+                    b,
+                    m,
+                    threshold,
+                    large_prime_bound,
+                    &mut sieve,
+                    &mut |rel| {
+                        if rel.large == 1 {
+                            if seen_lhs.insert(rel.lhs) {
+                                relations.push(rel);
+                            }
+                        } else if let Some(other) = partials.get(&rel.large) {
+                            if other.lhs != rel.lhs {
+                                let combined = combine(n, other, &rel);
+                                if seen_lhs.insert(combined.lhs) {
+                                    relations.push(combined);
+                                }
+                            }
+                        } else {
+                            partials.insert(rel.large, rel);
+                        }
+                    },
+                );
+
+                if relations.len() >= wanted {
+                    break;
+                }
+            }
+        }
+
+        for dep in dependencies(&relations, num_primes) {
+            if let Some(f) = try_dependency(n, &fb, &relations, &dep) {
+                return Some(f);
+            }
+        }
+
+        // Every dependency was trivial; gather some more relations.
+        relations.truncate(relations.len().saturating_sub(EXTRA_RELATIONS / 2));
+        seen_lhs = relations.iter().map(|r| r.lhs).collect();
+    }
+
+    None
+}
+
+/// Two partial relations with the same large prime multiply to a full one.
+fn combine(n: u128, a: &Relation, b: &Relation) -> Relation {
+    let mut factors = a.factors.clone();
+    factors.extend_from_slice(&b.factors);
+
+    Relation {
+        lhs: mul_mod_u128(a.lhs, b.lhs, n).expect("n is non-zero"),
+        factors,
+        negative: a.negative != b.negative,
+        large: a.large,
+    }
+}
+
+/// Sieves Q(x) = (ax + b)^2 - n for x in [-m, m) and hands every smooth or
+/// partial relation to `found`.
+#[allow(clippy::too_many_arguments)]
+fn sieve_polynomial(
+    n: u128,
+    fb: &FactorBase,
+    a_factors: &[usize],
+    a_inv: &[Option<u64>],
+    a: u128,
+    b: i128,
+    m: i64,
+    threshold: u8,
+    large_prime_bound: u128,
+    sieve: &mut [u8],
+    found: &mut impl FnMut(Relation),
+) {
+    sieve.fill(0);
+    let len = sieve.len() as u64;
+
+    // Sieve index i is x = i - m, so the roots are shifted by m.
+    let mut roots: Vec<Option<(u64, u64)>> = vec![None; fb.primes.len()];
+    for (i, &p) in fb.primes.iter().enumerate() {
+        let Some(inv) = a_inv[i] else { continue };
+        let b_mod = b.rem_euclid(p as i128) as u64;
+        let t = fb.sqrt_n[i];
+        let shift = (m as u64) % p;
+        let r1 = (inv as u128 * ((t + p - b_mod) % p) as u128 % p as u128) as u64;
+        let r2 = (inv as u128 * ((2 * p - t - b_mod) % p) as u128 % p as u128) as u64;
+        let (r1, r2) = ((r1 + shift) % p, (r2 + shift) % p);
+        roots[i] = Some((r1, r2));
+
+        if p < MIN_SIEVE_PRIME {
+            continue;
+        }
+        let logp = fb.logp[i];
+        let starts = [r1, r2];
+        for &start in &starts[..if r1 == r2 { 1 } else { 2 }] {
+            let mut j = start;
+            while j < len {
+                sieve[j as usize] = sieve[j as usize].wrapping_add(logp);
+                j += p;
+            }
+        }
+    }
+
+    // Q(x) / a = a x^2 + 2 b x + c with c = (b^2 - n) / a exact.
+    let c = (b * b - n as i128) / a as i128;
+
+    for (i, &v) in sieve.iter().enumerate() {
+        if v < threshold {
+            continue;
+        }
+
+        let x = i as i128 - m as i128;
+        let g = (a as i128 * x + 2 * b) * x + c;
+        if g == 0 {
+            continue;
+        }
+
+        let mut rest = g.unsigned_abs();
+        // a's primes always divide Q(x) = a * g(x) once.
+        let mut factors: Vec<usize> = a_factors.to_vec();
+
+        for (k, &p) in fb.primes.iter().enumerate() {
+            let divides = match roots[k] {
+                Some((r1, r2)) => {
+                    let idx = i as u64 % p;
+                    idx == r1 || idx == r2
+                }
+                None => rest.is_multiple_of(p as u128),
+            };
+            if !divides {
+                continue;
+            }
+            while rest.is_multiple_of(p as u128) {
+                rest /= p as u128;
+                factors.push(k);
+            }
+        }
+
+        let large = match rest {
+            1 => 1,
+            r if r < large_prime_bound => r,
+            _ => continue,
+        };
+
+        let lhs = (a as i128 * x + b).rem_euclid(n as i128) as u128;
+        found(Relation {
+            lhs,
+            factors,
+            negative: g < 0,
+            large,
+        });
+    }
+}
+
+/// Gaussian elimination over GF(2). Returns subsets of the relations (as
+/// index lists) whose products are perfect squares.
+fn dependencies(relations: &[Relation], num_primes: usize) -> Vec<Vec<usize>> {
+    let cols = num_primes + 1;
+    let col_words = cols.div_ceil(64);
+    let row_words = relations.len().div_ceil(64);
+
+    // Each row is its exponent parity vector and which relations it is
+    // made of.
+    let mut rows: Vec<(Vec<u64>, Vec<u64>)> = relations
+        .iter()
+        .enumerate()
+        .map(|(r, rel)| {
+            let mut bits = vec![0u64; col_words];
+            if rel.negative {
+                bits[0] ^= 1;
+            }
+            for &f in &rel.factors {
+                let c = f + 1;
+                bits[c / 64] ^= 1 << (c % 64);
+            }
+            let mut history = vec![0u64; row_words];
+            history[r / 64] |= 1 << (r % 64);
+            (bits, history)
+        })
+        .collect();
+
+    let mut pivot_row = 0;
+    for c in 0..cols {
+        let (w, bit) = (c / 64, 1u64 << (c % 64));
+        let Some(p) = (pivot_row..rows.len()).find(|&r| rows[r].0[w] & bit != 0) else {
+            continue;
+        };
+        rows.swap(pivot_row, p);
+
+        let (pivot_bits, pivot_hist) = rows[pivot_row].clone();
+        for (r, row) in rows.iter_mut().enumerate() {
+            if r != pivot_row && row.0[w] & bit != 0 {
+                row.0.iter_mut().zip(&pivot_bits).for_each(|(a, b)| *a ^= b);
+                row.1.iter_mut().zip(&pivot_hist).for_each(|(a, b)| *a ^= b);
+            }
+        }
+        pivot_row += 1;
+    }
+
+    rows[pivot_row..]
+        .iter()
+        .map(|(_, history)| {
+            (0..relations.len())
+                .filter(|r| history[r / 64] & (1 << (r % 64)) != 0)
+                .collect()
+        })
+        .collect()
+}
+
+fn try_dependency(n: u128, fb: &FactorBase, relations: &[Relation], dep: &[usize]) -> Option<u128> {
+    let mul = |a: u128, b: u128| mul_mod_u128(a, b, n).expect("n is non-zero");
+
+    let mut x = 1u128;
+    let mut y = 1u128;
+    let mut exponents = vec![0u32; fb.primes.len()];
+    for &r in dep {
+        let rel = &relations[r];
+        x = mul(x, rel.lhs);
+        y = mul(y, rel.large % n);
+        rel.factors.iter().for_each(|f| exponents[*f] += 1);
+    }
+
+    for (k, e) in exponents.into_iter().enumerate() {
+        debug_assert!(e % 2 == 0);
+        for _ in 0..e / 2 {
+            y = mul(y, fb.primes[k] as u128);
+        }
+    }
+
+    let diff = (x + n - y) % n;
+    let f = gcd_u128(diff, n);
+
+    (f > 1 && f < n).then_some(f)
+}
+
+fn gcd_u128(a: u128, b: u128) -> u128 {
+    if let (Ok(a), Ok(b)) = (usize::try_from(a), usize::try_from(b)) {
+        return gcd(a, b) as u128;
+    }
+    if b == 0 {
+        return a;
+    }
+
+    gcd_u128(b, a % b)
+}
+
+/// Splits off small factors by trial division, returning them and what is
+/// left over.
+pub(super) fn trial_divide(mut n: u128, bound: u64) -> (Vec<u128>, u128) {
+    let mut factors = Vec::new();
+    let mut p = 2u64;
+    while p <= bound && (p as u128) * (p as u128) <= n {
+        while n.is_multiple_of(p as u128) {
+            factors.push(p as u128);
+            n /= p as u128;
+        }
+        p += if p == 2 { 1 } else { 2 };
+    }
+
+    // What's left is prime if it has no factor up to its square root.
+    if n > 1 && (isqrt(n) as u64) <= p {
+        factors.push(n);
+        n = 1;
+    }
+
+    (factors, n)
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+
+    #[test]
+    fn sqrt_mod_works() {
+        for p in [3u64, 5, 7, 13, 17, 97, 65537, 1_000_000_007] {
+            for n in 1..200u64 {
+                if let Some(r) = sqrt_mod(n, p) {
+                    assert_eq!(r * r % p, n % p, "sqrt({n}) mod {p}");
+                }
+            }
+        }
+        assert_eq!(sqrt_mod(3, 7), None);
+    }
+
+    #[test]
+    fn finds_dependencies() {
+        // 2 * 3, 2, 3 and 5, 5: {0, 1, 2} and {3} are squares.
+        let rel = |factors: Vec<usize>| Relation {
+            lhs: 1,
+            factors,
+            negative: false,
+            large: 1,
+        };
+        let relations = vec![rel(vec![0, 1]), rel(vec![0]), rel(vec![1]), rel(vec![2, 2])];
+
+        let mut deps = dependencies(&relations, 3);
+        deps.sort();
+        assert_eq!(deps, vec![vec![0, 1, 2], vec![3]]);
+    }
+
+    #[test]
+    fn trial_divides() {
+        assert_eq!(trial_divide(2 * 2 * 3 * 97, 100), (vec![2, 2, 3, 97], 1));
+        assert_eq!(
+            trial_divide(7 * 6880691 * 987654103, 100),
+            (vec![7], 6880691 * 987654103)
+        );
+    }
+
+    #[test]
+    fn sieves_small_semiprimes() {
+        for (p, q) in [
+            (1_000_003u128, 1_000_033),
+            (6880691, 987654103),
+            (4_294_967_311, 4_294_967_357),
+        ] {
+            let f = siqs(p * q).expect("failed to factor");
+            assert!(f == p || f == q, "{f} is not a factor of {}", p * q);
+        }
+    }
+}
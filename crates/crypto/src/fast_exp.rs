@@ -1,26 +1,39 @@
 //! Run `cargo r --release --example fastexp` to run
 //! a looping program to interactively test inputs.
-pub fn fast_exp(mut num: usize, mut pow: usize) -> usize {
-    assert!(num >= 1);
+use crate::CryptoError;
 
-    let mut result = 1;
+/// `num^pow` by repeated squaring.
+/// Returns `CryptoError::Overflow` when the result does not fit in a usize.
+pub fn fast_exp(mut num: usize, mut pow: usize) -> Result<usize, CryptoError> {
+    let mut result: usize = 1;
 
     while pow > 0 {
         // if pow & 1 == 1
         if pow % 2 == 1 {
-            result *= num;
+            result = result.checked_mul(num).ok_or(CryptoError::Overflow)?;
         }
 
         pow /= 2; // pow >>= 1;
-        num *= num;
+        if pow > 0 {
+            // Only square when another bit needs it, or this can overflow
+            // even though the result fits.
+            num = num.checked_mul(num).ok_or(CryptoError::Overflow)?;
+        }
     }
 
-    result
+    Ok(result)
 }
 
-pub fn fast_exp_mod(mut num: usize, mut pow: usize, modulus: usize) -> usize {
-    assert!(num >= 1);
-    let mut result = 1;
+/// `num^pow % modulus` by repeated squaring.
+/// Products are taken in u128 so any usize modulus works.
+pub fn fast_exp_mod(num: usize, mut pow: usize, modulus: usize) -> Result<usize, CryptoError> {
+    if modulus == 0 {
+        return Err(CryptoError::ZeroModulus);
+    }
+
+    let modulus = modulus as u128;
+    let mut num = num as u128 % modulus;
+    let mut result = 1 % modulus;
     while pow > 0 {
         if pow % 2 == 1 {
             result = (result * num) % modulus;
@@ -30,7 +43,56 @@ pub fn fast_exp_mod(mut num: usize, mut pow: usize, modulus: usize) -> usize {
         num = (num * num) % modulus;
     }
 
-    result
+    Ok(result as usize)
+}
+
+/// `a * b % modulus` for u128 values, where the product itself can need 256
+/// bits. Uses shift and add so no intermediate value overflows.
+pub fn mul_mod_u128(a: u128, b: u128, modulus: u128) -> Result<u128, CryptoError> {
+    if modulus == 0 {
+        return Err(CryptoError::ZeroModulus);
+    }
+
+    let (mut a, mut b) = (a % modulus, b % modulus);
+    let mut result = 0;
+    while b > 0 {
+        if b & 1 == 1 {
+            result = add_mod_u128(result, a, modulus);
+        }
+        a = add_mod_u128(a, a, modulus);
+        b >>= 1;
+    }
+
+    Ok(result)
+}
+
+// a + b % m for a, b < m without overflowing.
+fn add_mod_u128(a: u128, b: u128, m: u128) -> u128 {
+    if a >= m - b {
+        a - (m - b)
+    } else {
+        a + b
+    }
+}
+
+/// `num^pow % modulus` for u128 values.
+pub fn fast_exp_mod_u128(num: u128, mut pow: u128, modulus: u128) -> Result<u128, CryptoError> {
+    if modulus == 0 {
+        return Err(CryptoError::ZeroModulus);
+    }
+
+    let mut num = num % modulus;
+    let mut result = 1 % modulus;
+    while pow > 0 {
+        if pow % 2 == 1 {
+            result = mul_mod_u128(result, num, modulus)?;
+        }
+        pow /= 2;
+
+        num = mul_mod_u128(num, num, modulus)?;
+    }
+
+    Ok(result)
 }
 
 #[cfg(test)]
@@ -39,6 +101,8 @@ mod unit {
 
     #[test]
     fn test_fast_exp() {
+        let fast_exp = |n, p| fast_exp(n, p).expect("failed to exponentiate");
+
         assert_eq!(fast_exp(8, 6), 8usize.pow(6));
         assert_eq!(fast_exp(8, 6), 262144);
         assert_eq!(fast_exp(7, 10), 7usize.pow(10));
@@ -50,8 +114,20 @@ mod unit {
         assert_eq!(fast_exp(213, 5), 213usize.pow(5));
         assert_eq!(fast_exp(213, 5), 438427732293);
     }
+    #[test]
+    fn test_fast_exp_errors() {
+        assert_eq!(fast_exp(0, 0), Ok(1));
+        assert_eq!(fast_exp(0, 5), Ok(0));
+        assert_eq!(fast_exp(2, 63), Ok(1 << 63));
+        assert_eq!(fast_exp(2, 64), Err(CryptoError::Overflow));
+        assert_eq!(fast_exp(3, 40), Ok(3usize.pow(40)));
+        assert_eq!(fast_exp(3, 41), Err(CryptoError::Overflow));
+    }
+
     #[test]
     fn test_fast_exp_mod() {
+        let fast_exp_mod = |n, p, m| fast_exp_mod(n, p, m).expect("failed to exponentiate");
+
         assert_eq!(fast_exp_mod(8, 6, 10), 8usize.pow(6) % 10);
         assert_eq!(fast_exp_mod(8, 6, 10), 4);
         assert_eq!(fast_exp_mod(7, 10, 101), 7usize.pow(10) % 101);
@@ -62,5 +138,30 @@ mod unit {
 
         assert_eq!(fast_exp_mod(213, 5, 1000), 213usize.pow(5) % 1000);
         assert_eq!(fast_exp_mod(213, 5, 1000), 293);
+
+        assert_eq!(fast_exp_mod(0, 5, 7), 0);
+        assert_eq!(fast_exp_mod(5, 0, 1), 0);
+        // Squaring these overflows a usize.
+        assert_eq!(fast_exp_mod(usize::MAX - 1, 2, usize::MAX), 1);
+    }
+
+    #[test]
+    fn test_u128() {
+        assert_eq!(mul_mod_u128(u128::MAX - 1, u128::MAX - 1, u128::MAX), Ok(1));
+        assert_eq!(mul_mod_u128(1 << 100, 1 << 100, 1 << 127), Ok(0));
+        assert_eq!(
+            mul_mod_u128(12345, 67890, 1_000_007),
+            Ok(12345 * 67890 % 1_000_007)
+        );
+        assert_eq!(fast_exp_mod_u128(9, 13, 283), Ok(179));
+        // Fermat's little theorem with the prime 2^127 - 1.
+        let m = u128::MAX >> 1;
+        assert_eq!(fast_exp_mod_u128(3, m - 1, m), Ok(1));
+        assert_eq!(fast_exp_mod_u128(3, 4, 0), Err(CryptoError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_fast_exp_mod_zero_modulus() {
+        assert_eq!(fast_exp_mod(2, 3, 0), Err(CryptoError::ZeroModulus));
     }
 }
@@ -1,5 +1,7 @@
 //! Run `cargo r --release --example gcdlcm` to run
 //! a looping program to test inputs.
+use crate::CryptoError;
+
 pub fn gcd(a: usize, b: usize) -> usize {
     if b == 0 {
         return a;
@@ -8,8 +10,13 @@ pub fn gcd(a: usize, b: usize) -> usize {
     gcd(b, a % b)
 }
 
-pub fn lcm(a: usize, b: usize) -> usize {
-    a * b / gcd(a, b)
+pub fn lcm(a: usize, b: usize) -> Result<usize, CryptoError> {
+    if a == 0 || b == 0 {
+        return Ok(0);
+    }
+
+    // Divide first so only a genuinely too large result overflows.
+    (a / gcd(a, b)).checked_mul(b).ok_or(CryptoError::Overflow)
 }
 
 #[cfg(test)]
@@ -34,9 +41,12 @@ mod unit {
 
     #[test]
     fn lcm_works() {
-        assert_eq!(lcm(12, 18), 36);
-        assert_eq!(lcm(270, 192), 8640);
-        assert_eq!(lcm(7469, 2464), 239008);
-        assert_eq!(lcm(55290, 115430), 6579510);
+        assert_eq!(lcm(12, 18), Ok(36));
+        assert_eq!(lcm(270, 192), Ok(8640));
+        assert_eq!(lcm(7469, 2464), Ok(239008));
+        assert_eq!(lcm(55290, 115430), Ok(6579510));
+        assert_eq!(lcm(0, 0), Ok(0));
+        assert_eq!(lcm(usize::MAX, usize::MAX), Ok(usize::MAX));
+        assert_eq!(lcm(usize::MAX, 2), Err(CryptoError::Overflow));
     }
 }
@@ -0,0 +1,184 @@
+//! Exact integer roots.
+//!
+//! Going through `f64` loses precision once values pass 2^53, so
+//! `(n as f64).sqrt() as usize` can be off by one (or more) near `u64::MAX`.
+//! These stay in integer arithmetic the whole way.
+use std::num::NonZeroU32;
+
+use crate::CryptoError;
+
+const TWO: NonZeroU32 = NonZeroU32::new(2).unwrap();
+const THREE: NonZeroU32 = NonZeroU32::new(3).unwrap();
+
+/// Unsigned integer types that support exact roots.
+pub trait IntRoot: Copy + Ord + From<u8> {
+    /// The largest `r` such that `r^k <= self`.
+    fn iroot(self, k: NonZeroU32) -> Self;
+
+    /// `base^exp`, or `None` when it overflows.
+    fn checked_power(self, exp: u32) -> Option<Self>;
+}
+
+macro_rules! impl_int_root {
+    ($($t:ty),*) => {$(
+        impl IntRoot for $t {
+            fn iroot(self, k: NonZeroU32) -> Self {
+                let k = k.get();
+                let n = self;
+                if n < 2 || k == 1 {
+                    return n;
+                }
+
+                let bits = <$t>::BITS - n.leading_zeros();
+                if k >= bits {
+                    // 2^k > n, so the root is 1.
+                    return 1;
+                }
+
+                // Newton's method from above: 2^ceil(bits / k) > root.
+                let k_t = k as $t;
+                let mut x: $t = 1 << bits.div_ceil(k);
+                loop {
+                    // n / x^(k-1); an overflowing power means the quotient is 0.
+                    let q = x.checked_pow(k - 1).map_or(0, |p| n / p);
+                    let y = ((k_t - 1) * x + q) / k_t;
+                    if y >= x {
+                        return x;
+                    }
+                    x = y;
+                }
+            }
+
+            fn checked_power(self, exp: u32) -> Option<Self> {
+                self.checked_pow(exp)
+            }
+        }
+    )*};
+}
+
+impl_int_root!(usize, u128);
+
+/// The largest `r` such that `r^k <= n`.
+/// The zeroth root is undefined and returns `CryptoError::InvalidRange`.
+pub fn iroot<T: IntRoot>(n: T, k: u32) -> Result<T, CryptoError> {
+    let k = NonZeroU32::new(k)
+        .ok_or_else(|| CryptoError::InvalidRange("the zeroth root is undefined".to_string()))?;
+
+    Ok(n.iroot(k))
+}
+
+/// The floor of the square root of `n`.
+pub fn isqrt<T: IntRoot>(n: T) -> T {
+    n.iroot(TWO)
+}
+
+/// The floor of the cube root of `n`.
+pub fn icbrt<T: IntRoot>(n: T) -> T {
+    n.iroot(THREE)
+}
+
+pub fn is_perfect_square<T: IntRoot>(n: T) -> bool {
+    let r = isqrt(n);
+    r.checked_power(2) == Some(n)
+}
+
+/// Finds `n = base^exp` with the largest `exp > 1`, so `base` itself is not
+/// a perfect power. Returns `None` when `n` is not a perfect power.
+pub fn perfect_power<T: IntRoot>(n: T) -> Option<(T, u32)> {
+    // 0, 1, 2 and 3 have no decomposition worth reporting.
+    if n < T::from(4) {
+        return None;
+    }
+
+    // Every exponent up to the one whose root drops below 2 is a
+    // candidate, and the last that fits exactly wins.
+    let mut found = None;
+    for exp in 2.. {
+        let base = n.iroot(NonZeroU32::new(exp)?);
+        if base < T::from(2) {
+            break;
+        }
+        if base.checked_power(exp) == Some(n) {
+            found = Some((base, exp));
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+
+    #[test]
+    fn isqrt_matches_brute_force() {
+        let mut r = 0usize;
+        for n in 0..10_000usize {
+            if (r + 1) * (r + 1) <= n {
+                r += 1;
+            }
+            assert_eq!(isqrt(n), r, "isqrt({n})");
+        }
+    }
+
+    #[test]
+    fn isqrt_near_max() {
+        assert_eq!(isqrt(usize::MAX), u32::MAX as usize);
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+
+        // f64 rounds (2^32 - 1)^2 - 1 up to a perfect square.
+        let n = (u32::MAX as usize) * (u32::MAX as usize) - 1;
+        assert_eq!(isqrt(n), u32::MAX as usize - 1);
+        assert_eq!((n as f64).sqrt() as usize, u32::MAX as usize);
+    }
+
+    #[test]
+    fn icbrt_and_iroot() {
+        assert_eq!(icbrt(26usize), 2);
+        assert_eq!(icbrt(27usize), 3);
+        assert_eq!(icbrt(usize::MAX), 2_642_245);
+        assert_eq!(icbrt(u128::MAX), 6_981_463_658_331);
+
+        assert_eq!(iroot(1usize << 60, 60), Ok(2));
+        assert_eq!(iroot((1usize << 60) - 1, 60), Ok(1));
+        assert_eq!(iroot(usize::MAX, 1), Ok(usize::MAX));
+        assert_eq!(iroot(u128::MAX, 64), Ok(3));
+        assert_eq!(iroot(u128::MAX, 65), Ok(3));
+        assert_eq!(iroot(0usize, 5), Ok(0));
+        assert!(matches!(
+            iroot(8usize, 0),
+            Err(CryptoError::InvalidRange(_))
+        ));
+
+        for k in 1..20 {
+            for n in 0..5_000usize {
+                let r = iroot(n, k).expect("failed to take root");
+                assert!(r.pow(k) <= n);
+                assert!((r + 1).checked_pow(k).is_none_or(|p| p > n));
+            }
+        }
+    }
+
+    #[test]
+    fn perfect_squares() {
+        assert!(is_perfect_square(0usize));
+        assert!(is_perfect_square(1usize));
+        assert!(is_perfect_square(144usize));
+        assert!(!is_perfect_square(145usize));
+        assert!(is_perfect_square((u64::MAX as u128) * (u64::MAX as u128)));
+        assert!(!is_perfect_square(usize::MAX));
+    }
+
+    #[test]
+    fn perfect_powers() {
+        assert_eq!(perfect_power(3usize), None);
+        assert_eq!(perfect_power(4usize), Some((2, 2)));
+        assert_eq!(perfect_power(64usize), Some((2, 6)));
+        assert_eq!(perfect_power(72usize), None);
+        assert_eq!(perfect_power(3usize.pow(40)), Some((3, 40)));
+        assert_eq!(perfect_power(6880691usize.pow(2)), Some((6880691, 2)));
+        assert_eq!(perfect_power(1u128 << 127), Some((2, 127)));
+        assert_eq!(perfect_power(10u128.pow(38)), Some((10, 38)));
+        assert_eq!(perfect_power(usize::MAX), None);
+    }
+}
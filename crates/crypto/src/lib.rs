@@ -5,23 +5,42 @@ use std::{
 
 use anyhow::{anyhow, Result};
 
+mod error;
+pub use error::CryptoError;
+
+mod int_roots;
+pub use int_roots::{icbrt, iroot, is_perfect_square, isqrt, perfect_power, IntRoot};
 mod gcd_lcm;
 pub use gcd_lcm::{gcd, lcm};
 mod fast_exp;
-pub use fast_exp::{fast_exp, fast_exp_mod};
+pub use fast_exp::{fast_exp, fast_exp_mod, fast_exp_mod_u128, mul_mod_u128};
 mod sieve_eratosthenes;
 pub use sieve_eratosthenes::{
     print_sieve, print_sieve_optimized, sieve_of_eratosthenes, sieve_to_primes,
 };
 mod factor;
-pub use factor::{find_primes, find_primes_sieve, init_prime_sieve};
+pub use factor::{
+    find_primes, find_primes_sieve, find_primes_u128, init_prime_sieve, quadratic_sieve,
+};
 
 mod primality;
-pub use primality::find_prime;
+pub use primality::{find_prime, is_prime, is_prime_u128, next_prime};
 
 mod rsa;
 pub use rsa::{inverse_mod, random_exponent, totient};
 
+mod chacha20;
+pub use chacha20::{
+    chacha20_block, chacha20_decrypt, chacha20_encrypt, CHACHA20_BLOCK_LEN, CHACHA20_KEY_LEN,
+    CHACHA20_NONCE_LEN,
+};
+
+mod aes;
+pub use aes::{
+    cbc_decrypt, cbc_encrypt, ctr_decrypt, ctr_encrypt, ecb_decrypt, ecb_encrypt, pkcs7_pad,
+    pkcs7_unpad, Aes128, AES128_KEY_LEN, AES_BLOCK_LEN,
+};
+
 /// Get's a number from the user.
 pub fn get_number<T>(prompt: &str) -> Result<T>
 where
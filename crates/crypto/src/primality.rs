@@ -1,37 +1,141 @@
 use rand::Rng;
 
-use crate::fast_exp_mod;
+use crate::{fast_exp_mod, fast_exp_mod_u128, mul_mod_u128, CryptoError};
 
+/// A random prime in `[min, max]`, checked with [`is_prime`] so it's never
+/// a Carmichael number or other pseudoprime.
 // Run `cargo r --release --example primality` to
 // manually test in the terminal.
-pub fn find_prime(rng: &mut impl Rng, min: usize, max: usize, tests: usize) -> usize {
-    loop {
-        let n = rand_odd_num(rng, min, max);
-        if is_prime(rng, n, tests) {
-            return n;
+pub fn find_prime(rng: &mut impl Rng, min: usize, max: usize) -> Result<usize, CryptoError> {
+    // Make sure there is a prime to find before drawing at random, since
+    // a range with no primes in it would never finish.
+    let first = match next_prime(min.saturating_sub(1)) {
+        Ok(p) if p <= max => p,
+        _ => {
+            return Err(CryptoError::InvalidRange(format!(
+                "no primes in [{min}, {max}]"
+            )))
+        }
+    };
+
+    // Primes near 2^64 are about 1 in 44 numbers, so this many odd draws
+    // all missing is vanishingly unlikely in a range that isn't tiny. If
+    // it happens anyway, or there's nothing odd to draw, fall back to the
+    // first prime.
+    if first < max {
+        for _ in 0..MAX_ATTEMPTS {
+            let n = rand_odd_num(rng, first.max(3), max);
+            if is_prime(n) {
+                return Ok(n);
+            }
         }
     }
+
+    Ok(first)
 }
 
-fn is_prime(rng: &mut impl Rng, candidate: usize, num_tests: usize) -> bool {
-    for _ in 0..num_tests {
-        let n = rng.gen_range(1..candidate);
+const MAX_ATTEMPTS: usize = 10_000;
+
+// Testing against the first 12 primes as witnesses is enough to make
+// Miller-Rabin exact for every 64 bit number.
+const WITNESSES: [usize; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
 
-        if fast_exp_mod(n, candidate - 1, candidate) != 1 {
-            // Definitely not prime.
+fn mul_mod(a: usize, b: usize, m: usize) -> usize {
+    (a as u128 * b as u128 % m as u128) as usize
+}
+
+/// Deterministic Miller-Rabin primality test.
+pub fn is_prime(n: usize) -> bool {
+    if n < 2 {
+        return false;
+    }
+    for p in WITNESSES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+
+    // n - 1 = d * 2^s with d odd.
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+
+    'witness: for a in WITNESSES {
+        let mut x = fast_exp_mod(a, d, n).expect("n is non-zero");
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'witness;
+            }
+        }
+
+        // a proves n is composite.
+        return false;
+    }
+
+    true
+}
+
+// Deterministic below 3.3 * 10^24, and a strong probable prime test above.
+const WITNESSES_U128: [u128; 20] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71,
+];
+
+/// Miller-Rabin for u128. Exact for anything that fits in a usize or is
+/// below 3.3 * 10^24; beyond that a composite passing all 20 witnesses is
+/// astronomically unlikely but possible.
+pub fn is_prime_u128(n: u128) -> bool {
+    if let Ok(n) = usize::try_from(n) {
+        return is_prime(n);
+    }
+    for p in WITNESSES_U128 {
+        if n.is_multiple_of(p) {
             return false;
         }
     }
 
-    // Probably prime.
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+
+    'witness: for a in WITNESSES_U128 {
+        let mut x = fast_exp_mod_u128(a, d, n).expect("n is non-zero");
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod_u128(x, x, n).expect("n is non-zero");
+            if x == n - 1 {
+                continue 'witness;
+            }
+        }
+
+        return false;
+    }
+
     true
 }
 
+/// The smallest prime strictly greater than `n`.
+pub fn next_prime(n: usize) -> Result<usize, CryptoError> {
+    if n < 2 {
+        return Ok(2);
+    }
+
+    // Start at the next odd number.
+    let mut candidate = n.checked_add(1 + n % 2).ok_or(CryptoError::Overflow)?;
+    while !is_prime(candidate) {
+        candidate = candidate.checked_add(2).ok_or(CryptoError::Overflow)?;
+    }
+
+    Ok(candidate)
+}
+
 fn rand_odd_num(rng: &mut impl Rng, min: usize, max: usize) -> usize {
     let mut n: usize = 2;
-    while n % 2 == 0 {
-        // gen_range is half open [min, max)
-        n = rng.gen_range(min..max + 1);
+    while n.is_multiple_of(2) {
+        n = rng.gen_range(min..=max);
     }
 
     n
@@ -45,7 +149,79 @@ mod unit {
     fn gets_rand_odd_num() {
         let mut rng = rand::thread_rng();
         for i in 1..1000 {
-            assert!(rand_odd_num(&mut rng, i * 100, i * 1000) % 2 != 0);
+            assert!(!rand_odd_num(&mut rng, i * 100, i * 1000).is_multiple_of(2));
+        }
+    }
+
+    #[test]
+    fn checks_primes() {
+        let primes: Vec<usize> = (0..1000).filter(|n| is_prime(*n)).collect();
+        let sieved =
+            crate::sieve_to_primes(&crate::sieve_of_eratosthenes(999).expect("failed to sieve"));
+        assert_eq!(primes, sieved);
+
+        // Carmichael numbers fool the Fermat test for coprime bases.
+        assert!(!is_prime(561));
+        assert!(!is_prime(3_215_031_751));
+        assert!(is_prime(987654103));
+        assert!(!is_prime(6795742697625173));
+        assert!(is_prime(18446744073709551557));
+        assert!(!is_prime(usize::MAX));
+    }
+
+    #[test]
+    fn checks_u128_primes() {
+        assert!(is_prime_u128(987654103));
+        assert!(is_prime_u128(u128::MAX >> 1));
+        assert!(!is_prime_u128(u128::MAX));
+        // 2^89 - 1 is a Mersenne prime, 2^88 - 1 is not.
+        assert!(is_prime_u128((1 << 89) - 1));
+        assert!(!is_prime_u128((1 << 88) - 1));
+        assert!(!is_prime_u128(6880691 * 987654103 * 987654103));
+        assert!(!is_prime_u128(18446744073709551557 * 18446744073709551557));
+    }
+
+    #[test]
+    fn finds_next_prime() {
+        assert_eq!(next_prime(0), Ok(2));
+        assert_eq!(next_prime(2), Ok(3));
+        assert_eq!(next_prime(3), Ok(5));
+        assert_eq!(next_prime(24), Ok(29));
+        assert_eq!(next_prime(18446744073709551556), Ok(18446744073709551557));
+        assert_eq!(next_prime(18446744073709551557), Err(CryptoError::Overflow));
+    }
+
+    #[test]
+    fn finds_primes() {
+        let mut rng = rand::thread_rng();
+        let p = find_prime(&mut rng, 1000, 10_000).expect("failed to find a prime");
+        assert!((1000..=10_000).contains(&p));
+        assert_eq!(find_prime(&mut rng, 7, 7), Ok(7));
+
+        assert!(matches!(
+            find_prime(&mut rng, 10, 5),
+            Err(CryptoError::InvalidRange(_))
+        ));
+        assert!(matches!(
+            find_prime(&mut rng, 8, 8),
+            Err(CryptoError::InvalidRange(_))
+        ));
+
+        // Odd candidates but no primes.
+        assert!(matches!(
+            find_prime(&mut rng, 24, 28),
+            Err(CryptoError::InvalidRange(_))
+        ));
+        assert!(matches!(
+            find_prime(&mut rng, 8, 9),
+            Err(CryptoError::InvalidRange(_))
+        ));
+        assert_eq!(find_prime(&mut rng, 2, 2), Ok(2));
+        // 561 is a Carmichael number, which fools the Fermat test.
+        for _ in 0..100 {
+            assert_eq!(find_prime(&mut rng, 561, 563), Ok(563));
         }
+        assert_eq!(find_prime(&mut rng, 0, 2), Ok(2));
+        assert!(find_prime(&mut rng, usize::MAX - 100, usize::MAX).is_ok());
     }
 }
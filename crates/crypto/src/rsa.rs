@@ -1,17 +1,23 @@
-use anyhow::{anyhow, Result};
 use rand::Rng;
 
-use crate::{gcd, lcm};
+use crate::{gcd, lcm, CryptoError};
 
 /// Pick a random exponent _e_ in the range [3,ln) such that
 /// gcd(e, ln) = 1.
-/// To do that, use the Prng struct next_i64 function in a loop and repeat
-/// until you pick a random value e such that gcd(e, λ_n) = 1.
-pub fn random_exponent(rng: &mut impl Rng, ln: usize) -> usize {
+/// Draws from `rng` in a loop until it picks an e with gcd(e, λ_n) = 1.
+pub fn random_exponent(rng: &mut impl Rng, ln: usize) -> Result<usize, CryptoError> {
+    // λ(n) is even for any RSA modulus so there is always a coprime e, but
+    // there needs to be something to pick from.
+    if ln < 4 {
+        return Err(CryptoError::InvalidRange(format!(
+            "λ(n) must be at least 4, got {ln}"
+        )));
+    }
+
     loop {
-        let e = rng.gen_range(2..ln);
+        let e = rng.gen_range(3..ln);
         if gcd(e, ln) == 1 {
-            return e;
+            return Ok(e);
         }
     }
 }
@@ -25,8 +31,13 @@ pub fn random_exponent(rng: &mut impl Rng, ln: usize) -> usize {
 /// but call it inverse_mod.
 /// (Just implementing the function is enough for this project,
 /// but more power to you if you read the rest of the page to figure out how it works!)
-pub fn inverse_mod(e: usize, m: usize) -> Result<usize> {
-    assert!(m > 1);
+pub fn inverse_mod(e: usize, m: usize) -> Result<usize, CryptoError> {
+    match m {
+        0 => return Err(CryptoError::ZeroModulus),
+        // Everything is congruent to 0 (and so to 1) mod 1.
+        1 => return Ok(0),
+        _ => {}
+    }
 
     let mut t: i128 = 0;
     let mut new_t: i128 = 1;
@@ -40,7 +51,10 @@ pub fn inverse_mod(e: usize, m: usize) -> Result<usize> {
     }
 
     if r > 1 {
-        return Err(anyhow!("{} is not invertable", e));
+        return Err(CryptoError::NotInvertible {
+            value: e,
+            modulus: m,
+        });
     }
 
     if t < 0 {
@@ -55,7 +69,13 @@ pub fn inverse_mod(e: usize, m: usize) -> Result<usize> {
 /// So  `λ(3233) = totient(61,53) = 780`
 /// So we are calculating it from the known q and q inputs to
 /// `λ(3233)` or 61, and 53.
-pub fn totient(p: usize, q: usize) -> usize {
+pub fn totient(p: usize, q: usize) -> Result<usize, CryptoError> {
+    if p < 2 || q < 2 {
+        return Err(CryptoError::InvalidRange(format!(
+            "primes must be at least 2, got {p} and {q}"
+        )));
+    }
+
     lcm(p - 1, q - 1)
 }
 
@@ -65,21 +85,36 @@ mod unit {
 
     #[test]
     fn test_totient() {
-        assert_eq!(totient(61, 53), 780);
-        assert_eq!(totient(53, 61), 780);
-        assert_eq!(totient(3449, 5009), 2158448);
-        assert_eq!(totient(5009, 3449), 2158448);
+        assert_eq!(totient(61, 53), Ok(780));
+        assert_eq!(totient(53, 61), Ok(780));
+        assert_eq!(totient(3449, 5009), Ok(2158448));
+        assert_eq!(totient(5009, 3449), Ok(2158448));
+        assert!(matches!(totient(1, 53), Err(CryptoError::InvalidRange(_))));
     }
 
     #[test]
     fn test_random_exponenet() {
         let mut rng = rand::thread_rng();
-        assert!(random_exponent(&mut rng, 780) > 0);
+        let e = random_exponent(&mut rng, 780).expect("failed to pick exponent");
+        assert!((3..780).contains(&e));
+        assert_eq!(gcd(e, 780), 1);
+        assert!(matches!(
+            random_exponent(&mut rng, 2),
+            Err(CryptoError::InvalidRange(_))
+        ));
     }
 
     #[test]
     fn test_inverse_mod() {
         assert_eq!(inverse_mod(3, 7).expect("failed to invert"), 5);
         assert_eq!(inverse_mod(3, 26).expect("failed to invert"), 9);
+        assert_eq!(
+            inverse_mod(4, 26),
+            Err(CryptoError::NotInvertible {
+                value: 4,
+                modulus: 26
+            })
+        );
+        assert_eq!(inverse_mod(3, 0), Err(CryptoError::ZeroModulus));
     }
 }
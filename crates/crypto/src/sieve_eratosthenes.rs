@@ -1,15 +1,26 @@
 //! Run `cargo r --release --example sieve`
 //! to for an interactive env.
+use crate::{isqrt, CryptoError};
 
-pub fn sieve_of_eratosthenes(max: usize) -> Vec<bool> {
-    assert!(max > 1);
-
-    let mut primes = vec![true; max + 1];
+pub fn sieve_of_eratosthenes(max: usize) -> Result<Vec<bool>, CryptoError> {
+    if max < 2 {
+        return Err(CryptoError::InvalidRange(format!(
+            "sieve max must be at least 2, got {max}"
+        )));
+    }
+    // Leave room for the extra slot for max itself, and fail rather than
+    // panic when that many flags can't be allocated.
+    let len = max.checked_add(1).ok_or(CryptoError::Overflow)?;
+    let mut primes = Vec::new();
+    primes
+        .try_reserve_exact(len)
+        .map_err(|_| CryptoError::Overflow)?;
+    primes.resize(len, true);
     primes[0] = false;
     primes[1] = false;
 
     let mut start = 2;
-    let end = (max as f64).sqrt() as usize;
+    let end = isqrt(max);
 
     while start <= end {
         // Only start if next prime.
@@ -27,7 +38,7 @@ pub fn sieve_of_eratosthenes(max: usize) -> Vec<bool> {
         start += 1;
     }
 
-    primes
+    Ok(primes)
 }
 
 // Benchmarks run with divan, see `benches/sieve.rs`.
@@ -100,4 +111,29 @@ mod unit {
         ];
         assert_eq!(sieve_to_primes(&ONE_HUNDRED), want);
     }
+
+    #[test]
+    fn sieves_one_hundred() {
+        assert_eq!(
+            sieve_of_eratosthenes(100).expect("failed to sieve"),
+            ONE_HUNDRED
+        );
+        assert!(matches!(
+            sieve_of_eratosthenes(1),
+            Err(CryptoError::InvalidRange(_))
+        ));
+    }
+
+    #[test]
+    fn refuses_huge_sieves() {
+        assert_eq!(
+            sieve_of_eratosthenes(usize::MAX),
+            Err(CryptoError::Overflow)
+        );
+        assert_eq!(
+            sieve_of_eratosthenes(usize::MAX - 1),
+            Err(CryptoError::Overflow)
+        );
+        assert_eq!(sieve_of_eratosthenes(1 << 62), Err(CryptoError::Overflow));
+    }
 }
@@ -11,7 +11,9 @@ pub struct Items<'a>(pub &'a [Item]); //Vec<Item>);
 /// Example
 ///
 /// ```
-/// println!("{}", Items(items.clone()));
+/// # use dynprog::item::{Item, Items};
+/// # let items = vec![Item::default(); 3];
+/// println!("{}", Items(&items));
 /// ```
 impl Display for Items<'_> {
     fn fmt(&self, f: &mut Formatter) -> std::result::Result<(), Error> {
@@ -43,6 +45,8 @@ pub struct Item {
 /// Example
 ///
 /// ```
+/// # use dynprog::item::Item;
+/// # let items = vec![Item::default(); 3];
 /// println!(
 ///     "[ {} ]",
 ///     items
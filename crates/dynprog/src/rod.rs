@@ -32,7 +32,7 @@ pub fn rods_technique_sorted(
 ) -> Result<(Vec<Item>, isize, usize)> {
     make_block_lists(items);
 
-    items.sort_by(|a, b| b.block_list.len().cmp(&a.block_list.len()));
+    items.sort_by_key(|it| std::cmp::Reverse(it.block_list.len()));
     items.iter_mut().enumerate().for_each(|(idx, it)| {
         it.id = idx;
     });
@@ -162,7 +162,7 @@ mod tests {
     #[test]
     fn test_fibiter() {
         let mut fibg = Fibonacci::default();
-        for (_, want) in fib_seq_u128().iter().enumerate() {
+        for want in fib_seq_u128().iter() {
             assert_eq!(fibg.next(), Some(*want));
         }
     }
@@ -66,8 +66,8 @@ mod tests {
         let mut posts = vec![vec![0; size]; 3];
 
         // Put the disks on the first post in order, smallest first (on top).
-        for i in 0..size {
-            posts[0][i] = i + 1;
+        for (i, disk) in posts[0].iter_mut().enumerate() {
+            *disk = i + 1;
         }
 
         move_disk(&mut posts, 1, 3);
@@ -241,9 +241,9 @@ mod unit {
     #[test]
     fn test_board_visit() {
         let mut tut = Board::new(None);
-        assert_eq!(tut.visited(&Cell { x: 0, y: 0 }), false);
+        assert!(!tut.visited(&Cell { x: 0, y: 0 }));
         tut.visit(&Cell { x: 0, y: 0 }, 1);
-        assert_eq!(tut.visited(&Cell { x: 0, y: 0 }), true);
+        assert!(tut.visited(&Cell { x: 0, y: 0 }));
     }
 
     #[test]
@@ -293,14 +293,14 @@ mod unit {
         let mut tut = Board::new(None);
         let start_cell = &Cell { x: 0, y: 0 };
         tut.visit(start_cell, 0);
-        assert_eq!(tut.valid_move(start_cell, &(-1, -2)), false);
-        assert_eq!(tut.valid_move(start_cell, &(-1, 2)), false);
-        assert_eq!(tut.valid_move(start_cell, &(-2, 1)), false);
-        assert_eq!(tut.valid_move(start_cell, &(1, -2)), false);
-        assert_eq!(tut.valid_move(start_cell, &(1, 2)), true);
-        assert_eq!(tut.valid_move(start_cell, &(2, -1)), false);
-        assert_eq!(tut.valid_move(start_cell, &(2, -1)), false);
-        assert_eq!(tut.valid_move(start_cell, &(2, 1)), true);
+        assert!(!tut.valid_move(start_cell, &(-1, -2)));
+        assert!(!tut.valid_move(start_cell, &(-1, 2)));
+        assert!(!tut.valid_move(start_cell, &(-2, 1)));
+        assert!(!tut.valid_move(start_cell, &(1, -2)));
+        assert!(tut.valid_move(start_cell, &(1, 2)));
+        assert!(!tut.valid_move(start_cell, &(2, -1)));
+        assert!(!tut.valid_move(start_cell, &(2, -1)));
+        assert!(tut.valid_move(start_cell, &(2, 1)));
     }
 
     #[test]
@@ -308,13 +308,13 @@ mod unit {
         let mut tut = Board::new(None);
         let start_cell = &Cell { x: 3, y: 3 };
         tut.visit(start_cell, 0);
-        assert_eq!(tut.valid_move(start_cell, &(-1, -2)), true);
-        assert_eq!(tut.valid_move(start_cell, &(-1, 2)), true);
-        assert_eq!(tut.valid_move(start_cell, &(-2, 1)), true);
-        assert_eq!(tut.valid_move(start_cell, &(1, -2)), true);
-        assert_eq!(tut.valid_move(start_cell, &(1, 2)), true);
-        assert_eq!(tut.valid_move(start_cell, &(2, -1)), true);
-        assert_eq!(tut.valid_move(start_cell, &(2, -1)), true);
-        assert_eq!(tut.valid_move(start_cell, &(2, 1)), true);
+        assert!(tut.valid_move(start_cell, &(-1, -2)));
+        assert!(tut.valid_move(start_cell, &(-1, 2)));
+        assert!(tut.valid_move(start_cell, &(-2, 1)));
+        assert!(tut.valid_move(start_cell, &(1, -2)));
+        assert!(tut.valid_move(start_cell, &(1, 2)));
+        assert!(tut.valid_move(start_cell, &(2, -1)));
+        assert!(tut.valid_move(start_cell, &(2, -1)));
+        assert!(tut.valid_move(start_cell, &(2, 1)));
     }
 }
@@ -52,7 +52,7 @@ fn place_queens_1(board: &mut Vec<Vec<char>>, row: usize, _col: usize) -> bool {
     false
 }
 
-fn cell_is_safe(board: &Vec<Vec<char>>, row: usize, col: usize) -> bool {
+fn cell_is_safe(board: &[Vec<char>], row: usize, col: usize) -> bool {
     // Check if there is a Queen in the column already.
     for (i, _) in board[row].iter().enumerate() {
         if board[i][col] == QUEEN {
@@ -63,9 +63,9 @@ fn cell_is_safe(board: &Vec<Vec<char>>, row: usize, col: usize) -> bool {
 
     // Check the diagonals
     // k rows
-    for k in 0..board.len() {
+    for (k, cells) in board.iter().enumerate() {
         // l columns
-        for l in 0..board.len() {
+        for (l, cell) in cells.iter().enumerate() {
             // diag down and right || diag up and left
             if k + l == row + col || k - l == row - col {
                 /*
@@ -75,7 +75,7 @@ fn cell_is_safe(board: &Vec<Vec<char>>, row: usize, col: usize) -> bool {
                 3, 4, 5, 6 ...,
                 ..., ..., ...,
                 */
-                if board[k][l] == QUEEN {
+                if *cell == QUEEN {
                     // whoops already a queen in the diagonal, not safe.
                     return false;
                 }
@@ -0,0 +1,68 @@
+//! Sequential sorts against their parallel versions on `make_one` input.
+//!
+//! `cargo bench -p sorting --bench parallel`
+use divan::{black_box, Bencher};
+use sorting::{
+    introsort, make_one, merge_sort, par_merge_sort, par_quicksort, par_radix_sort, radix_sort,
+    ParallelConfig,
+};
+
+const SIZES: [usize; 3] = [100_000, 1_000_000, 10_000_000];
+
+fn main() {
+    divan::main();
+}
+
+#[divan::bench(args = SIZES)]
+fn seq_quicksort(bencher: Bencher, n: usize) {
+    bencher
+        .with_inputs(|| make_one(n, n))
+        .bench_local_refs(|v| introsort(black_box(v)));
+}
+
+#[divan::bench(args = SIZES)]
+fn par_quicksort_default(bencher: Bencher, n: usize) {
+    bencher
+        .with_inputs(|| make_one(n, n))
+        .bench_local_refs(|v| par_quicksort(black_box(v), ParallelConfig::default()));
+}
+
+#[divan::bench(args = SIZES)]
+fn seq_merge_sort(bencher: Bencher, n: usize) {
+    bencher
+        .with_inputs(|| make_one(n, n))
+        .bench_local_refs(|v| merge_sort(black_box(v)));
+}
+
+#[divan::bench(args = SIZES)]
+fn par_merge_sort_default(bencher: Bencher, n: usize) {
+    bencher
+        .with_inputs(|| make_one(n, n))
+        .bench_local_refs(|v| par_merge_sort(black_box(v), ParallelConfig::default()));
+}
+
+#[divan::bench(args = SIZES)]
+fn seq_radix_sort(bencher: Bencher, n: usize) {
+    bencher
+        .with_inputs(|| make_one(n, n))
+        .bench_local_refs(|v| radix_sort(black_box(v)));
+}
+
+#[divan::bench(args = SIZES)]
+fn par_radix_sort_default(bencher: Bencher, n: usize) {
+    bencher
+        .with_inputs(|| make_one(n, n))
+        .bench_local_refs(|v| par_radix_sort(black_box(v), ParallelConfig::default()));
+}
+
+// How the thread count scales on a fixed 10^6 elements.
+#[divan::bench(args = [1, 2, 4, 8, 16])]
+fn par_quicksort_threads(bencher: Bencher, threads: usize) {
+    let cfg = ParallelConfig {
+        threads,
+        ..ParallelConfig::default()
+    };
+    bencher
+        .with_inputs(|| make_one(1_000_000, 1_000_000))
+        .bench_local_refs(|v| par_quicksort(black_box(v), cfg));
+}
@@ -0,0 +1,71 @@
+//! The searches on sorted arrays of even numbers, looking up a fixed set of
+//! random targets, half of which miss.
+//!
+//! `cargo bench -p sorting --bench search`
+use divan::{black_box, Bencher};
+use sorting::{
+    binary_search, equal_range, exponential_search, interpolation_search, linear_search,
+    lower_bound, Prng,
+};
+
+const SIZES: [usize; 6] = [100, 1_000, 10_000, 100_000, 1_000_000, 10_000_000];
+const QUERIES: usize = 1000;
+
+fn main() {
+    divan::main();
+}
+
+fn setup(n: usize) -> (Vec<i64>, Vec<i64>) {
+    let v: Vec<i64> = (0..n as i64).map(|i| 2 * i).collect();
+    let mut prng = Prng::from_seed(1);
+    let targets = (0..QUERIES)
+        .map(|_| prng.below_u64(2 * n as u64) as i64)
+        .collect();
+
+    (v, targets)
+}
+
+fn bench<R>(bencher: Bencher, n: usize, search: impl Fn(&[i64], &i64) -> R) {
+    let (v, targets) = setup(n);
+    bencher.counter(QUERIES).bench_local(|| {
+        for t in &targets {
+            black_box(search(black_box(&v), t));
+        }
+    });
+}
+
+#[divan::bench(args = SIZES)]
+fn std_binary_search(bencher: Bencher, n: usize) {
+    bench(bencher, n, |v, t| v.binary_search(t));
+}
+
+#[divan::bench(args = SIZES)]
+fn binary(bencher: Bencher, n: usize) {
+    bench(bencher, n, binary_search);
+}
+
+#[divan::bench(args = SIZES)]
+fn lower_bound_search(bencher: Bencher, n: usize) {
+    bench(bencher, n, lower_bound);
+}
+
+#[divan::bench(args = SIZES)]
+fn equal_range_search(bencher: Bencher, n: usize) {
+    bench(bencher, n, equal_range);
+}
+
+#[divan::bench(args = SIZES)]
+fn exponential(bencher: Bencher, n: usize) {
+    bench(bencher, n, exponential_search);
+}
+
+#[divan::bench(args = SIZES)]
+fn interpolation(bencher: Bencher, n: usize) {
+    bench(bencher, n, interpolation_search);
+}
+
+// Linear search is O(n) per query, so stop before it gets silly.
+#[divan::bench(args = [100, 1_000, 10_000])]
+fn linear(bencher: Bencher, n: usize) {
+    bench(bencher, n, linear_search);
+}
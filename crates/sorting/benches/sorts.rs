@@ -0,0 +1,107 @@
+//! Every sort on every input distribution, from 10^2 to 10^7 elements.
+//!
+//! The full suite takes a long while; filter it, e.g.
+//! `cargo bench -p sorting --bench sorts -- killer` or `-- Timsort`. For
+//! CSV output to track over time, use the `sortbench` binary instead.
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use divan::{black_box, Bencher};
+use sorting::{
+    introsort, network_sort, BottomUpMergeSort, CountingSort, Distribution, Heapsort, Introsort,
+    MergeSort, ParMergeSort, ParQuicksort, ParRadixSort, Quicksort, RadixSort, Sorter, Timsort,
+};
+
+const SIZES: [usize; 6] = [100, 1_000, 10_000, 100_000, 1_000_000, 10_000_000];
+
+// Short slices, where the sorting networks apply.
+const SMALL: [usize; 4] = [4, 8, 12, 16];
+
+fn main() {
+    divan::main();
+}
+
+// Generating a killer input runs a whole (slow) sort, so each one is only
+// made once and shared between the sorts.
+fn input(dist: Distribution, n: usize) -> Vec<i32> {
+    if dist != Distribution::Killer {
+        return dist.generate(n, 1);
+    }
+
+    static KILLERS: OnceLock<Mutex<HashMap<usize, Vec<i32>>>> = OnceLock::new();
+    let killers = KILLERS.get_or_init(Default::default);
+    let mut killers = killers.lock().expect("killer cache poisoned");
+
+    killers
+        .entry(n)
+        .or_insert_with(|| dist.generate(n, 1))
+        .clone()
+}
+
+fn bench<S: Sorter<i32> + Default>(bencher: Bencher, dist: Distribution, n: usize) {
+    let v = input(dist, n);
+    let sorter = S::default();
+
+    bencher
+        .with_inputs(|| v.clone())
+        .bench_local_refs(|v| sorter.sort(black_box(v)));
+}
+
+// Plain quicksort goes quadratic (and overflows the stack) on everything
+// but random input, so it only runs on that.
+macro_rules! benches {
+    ($($name:ident: $dist:expr;)*) => {$(
+        #[divan::bench(
+            types = [
+                Introsort, MergeSort, BottomUpMergeSort, Timsort, Heapsort, RadixSort,
+                CountingSort, ParQuicksort, ParMergeSort, ParRadixSort,
+            ],
+            args = SIZES,
+            max_time = 1,
+        )]
+        fn $name<S: Sorter<i32> + Default>(bencher: Bencher, n: usize) {
+            bench::<S>(bencher, $dist, n);
+        }
+    )*};
+}
+
+#[divan::bench(types = [Quicksort], args = SIZES, max_time = 1)]
+fn random_quicksort<S: Sorter<i32> + Default>(bencher: Bencher, n: usize) {
+    bench::<S>(bencher, Distribution::Random, n);
+}
+
+benches! {
+    random: Distribution::Random;
+    sorted: Distribution::Sorted;
+    reversed: Distribution::Reversed;
+    nearly_sorted: Distribution::NearlySorted { swaps: 16 };
+    sawtooth: Distribution::Sawtooth { teeth: 16 };
+    organ_pipe: Distribution::OrganPipe;
+    few_unique: Distribution::FewUnique { values: 16 };
+    all_equal: Distribution::AllEqual;
+    zipf: Distribution::Zipf { values: 1000, exponent: 1.0 };
+    killer: Distribution::Killer;
+}
+
+fn small(bencher: Bencher, n: usize, sort: fn(&mut [i32])) {
+    let v = Distribution::Random.generate(n, 1);
+
+    bencher
+        .with_inputs(|| v.clone())
+        .bench_local_refs(|v| sort(black_box(v)));
+}
+
+#[divan::bench(args = SMALL)]
+fn small_network_sort(bencher: Bencher, n: usize) {
+    small(bencher, n, network_sort);
+}
+
+#[divan::bench(args = SMALL)]
+fn small_introsort(bencher: Bencher, n: usize) {
+    small(bencher, n, introsort);
+}
+
+#[divan::bench(args = SMALL)]
+fn small_sort_unstable(bencher: Bencher, n: usize) {
+    small(bencher, n, |v| v.sort_unstable());
+}
@@ -1,8 +1,6 @@
-use std::cmp::Ordering;
-
 use anyhow::Result;
 
-use sorting::{get_count, make_one, quicksort};
+use sorting::{binary_search_by, get_count, make_one, quicksort, Comparisons};
 
 fn main() -> Result<()> {
     let size = get_count("How many:")?;
@@ -16,60 +14,12 @@ fn main() -> Result<()> {
         match query {
             -1 => std::process::exit(0),
             _ => {
-                let res = binary_search(&mut v, query);
-                if res.0 != -1 {
-                    println!("numbers[{}] = {}, {} tests", res.0, query, res.1);
-                } else {
-                    println!("Target {} not found, {} tests", query, res.1);
-                }
-            }
-        }
-    }
-}
-
-fn binary_search(input: &mut Vec<i32>, query: i32) -> (i32, i32) {
-    let mut left = 0;
-    let mut right = input.len() - 1;
-    let mut tests = 0;
-
-    while left <= right {
-        tests += 1;
-        let middle = (left + right) / 2;
-
-        match input[middle].cmp(&query) {
-            Ordering::Greater => left = middle + 1,
-            Ordering::Less => {
-                if middle > 0 {
-                    right = middle - 1
-                } else {
-                    break;
+                let tests = Comparisons::new();
+                match binary_search_by(&v, tests.counted(|e: &i32| e.cmp(&query))) {
+                    Ok(i) => println!("numbers[{i}] = {query}, {} tests", tests.count()),
+                    Err(_) => println!("Target {query} not found, {} tests", tests.count()),
                 }
             }
-            Ordering::Equal => return (middle as i32, tests),
-        }
-    }
-
-    (-1, tests)
-}
-
-#[cfg(test)]
-mod unit {
-    use super::*;
-
-    #[test]
-    fn test_binary_search() {
-        let mut v = vec![
-            22, 10, 52, 9, 78, 76, 41, 36, 99, 23, 1, 74, 38, 12, 44, 52, 67, 14, 1, 58,
-        ];
-        quicksort(&mut v);
-
-        assert_eq!(binary_search(&mut v, 10), (3, 5));
-
-        // Light test to ensure it doesn't panic.
-        for i in 5..4000 {
-            let mut v = make_one(i, i * 2);
-
-            binary_search(&mut v, (i + i) as i32);
         }
     }
 }
@@ -25,7 +25,7 @@ fn main() -> Result<()> {
 }
 
 // Bubble sort a vector
-fn bubble_sort(v: &mut Vec<i32>) {
+fn bubble_sort(v: &mut [i32]) {
     if v.len() < 2 {
         return;
     }
@@ -1,21 +1,21 @@
 use core::fmt;
-use std::{slice::Iter, vec::IntoIter};
 
 use anyhow::Result;
 
-use sorting::{get_count, Prng};
+use sorting::{counting_sort_by_key, get_count, Prng};
 
 fn main() -> Result<()> {
     let count = get_count::<usize>("How many elements?")?;
     let max = get_count::<usize>("With what max value?")?;
 
-    let v = make_customers(count, max);
+    let mut v = make_customers(count, max);
 
-    let c = counting_sort(Customers(v));
+    // Stable, so customers with the same count keep their id order.
+    counting_sort_by_key(&mut v, |c| c.num_purchases as usize)?;
 
-    let sorted = sorted_customers(&c);
-    let c = Customers(c.into_iter().take(20).collect::<Vec<Customer>>());
-    println!("{} {} sorted", c, {
+    let sorted = sorted_customers(&v);
+    v.truncate(20);
+    println!("{} {} sorted", Customers(v), {
         if sorted {
             "is"
         } else {
@@ -26,64 +26,14 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-// Traditionally this takes an in put array and a max element.
-// counting_sort(input: Vec<i32>, k: usize)
-// We find the max as a first step in the fuction.
-fn counting_sort(input: Customers) -> Customers {
-    // Get the max value
-    let max = input.iter().fold(0, |a, b| a.max(b.num_purchases)) as usize;
-
-    let mut counts = vec![0i32; max + 1];
-    let mut output = vec![
-        Customer {
-            id: "".to_string(),
-            num_purchases: 0
-        };
-        input.len()
-    ];
-
-    // Build the counts vec first.
-    input
-        .iter()
-        .for_each(|elem| counts[elem.num_purchases as usize] += 1);
-
-    // Accumulate positions in the vec for populating the output vec.
-    // We start at one and add any zeros in the zero place.
-    // Spent an embarassing amount of time after setting 0 to 0 and starting at one.
-    // 🤦‍
-    for idx in 1..counts.len() {
-        counts[idx] += counts[idx - 1]
-    }
-
-    // Place the elements in order in the output array.
-    input.iter().rev().for_each(|k| {
-        counts[k.num_purchases as usize] -= 1;
-        output[counts[k.num_purchases as usize] as usize] = k.clone();
-    });
-
-    Customers(output)
-}
-
 struct Customers(pub Vec<Customer>);
 impl fmt::Display for Customers {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.iter()
+        self.0
+            .iter()
             .try_fold((), |_, customer| writeln!(f, "{}", customer))
     }
 }
-impl Customers {
-    fn len(&self) -> usize {
-        self.0.len()
-    }
-
-    fn iter(&self) -> Iter<'_, Customer> {
-        self.0.iter()
-    }
-
-    fn into_iter(self) -> IntoIter<Customer> {
-        self.0.into_iter()
-    }
-}
 
 #[derive(Clone)]
 struct Customer {
@@ -102,21 +52,9 @@ impl fmt::Display for Customer {
 }
 
 /// Check that a vec is sorted.
-fn sorted_customers(v: &Customers) -> bool {
-    if v.len() == 0 {
-        return true;
-    }
-
-    let mut prev = -1;
-
-    for c in &v.0 {
-        if c.num_purchases < prev {
-            return false;
-        }
-        prev = c.num_purchases;
-    }
-
-    true
+fn sorted_customers(v: &[Customer]) -> bool {
+    v.windows(2)
+        .all(|w| w[0].num_purchases <= w[1].num_purchases)
 }
 
 /// Makes a vec of len `num_items` of customers with a max value of max.
@@ -141,9 +79,10 @@ mod unit {
     #[test]
     fn test_counting_sort() {
         for i in 5..1000 {
-            let v = make_customers(i, i * 2);
-            let got = counting_sort(Customers(v));
-            assert!(sorted_customers(&got));
+            let mut v = make_customers(i, i * 2);
+            counting_sort_by_key(&mut v, |c| c.num_purchases as usize)
+                .expect("failed to sort customers");
+            assert!(sorted_customers(&v));
         }
     }
 }
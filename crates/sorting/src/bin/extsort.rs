@@ -0,0 +1,193 @@
+//! Sorts files bigger than memory with `sorting::external_sort`.
+//!
+//! Run `cargo r --release --bin extsort -- --help` for usage.
+use std::{
+    env,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{anyhow, Result};
+
+use sorting::{external_sort_reader, ExternalSortConfig, ExternalSortSummary, Record};
+
+const USAGE: &str = "\
+Usage: extsort [OPTIONS] INPUT OUTPUT
+
+Sorts INPUT into OUTPUT using at most about --memory of RAM, spilling
+sorted runs to temporary files. INPUT may be - for stdin, and OUTPUT may
+be the same file as INPUT.
+
+Options:
+  --format FORMAT  lines (default), or i32, u32, i64, u64 for fixed width
+                   little-endian binary records. Lines are written back
+                   with \\n endings, even if they were read with \\r\\n
+  --memory MIB     memory budget per run in MiB (default 64)
+  --fan-in N       most runs to merge at once (default 64)
+  --temp-dir DIR   where to put the runs (default the system temp dir)
+  -v, --verbose    print record, run and merge pass counts to stderr
+  -h, --help       print this help
+";
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    format: String,
+    cfg: ExternalSortConfig,
+    verbose: bool,
+    input: String,
+    output: PathBuf,
+}
+
+impl Options {
+    /// Returns `None` when help was asked for.
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Self>> {
+        let mut format = "lines".to_string();
+        let mut cfg = ExternalSortConfig::default();
+        let mut verbose = false;
+        let mut paths = vec![];
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or_else(|| anyhow!("{name} needs a value"));
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "-v" | "--verbose" => verbose = true,
+                "--format" => format = value("--format")?,
+                "--memory" => {
+                    let mib: usize = value("--memory")?
+                        .parse()
+                        .map_err(|e| anyhow!("bad --memory: {e}"))?;
+                    cfg.memory = mib
+                        .checked_mul(1 << 20)
+                        .ok_or_else(|| anyhow!("bad --memory: too large"))?;
+                }
+                "--fan-in" => {
+                    cfg.fan_in = value("--fan-in")?
+                        .parse()
+                        .map_err(|e| anyhow!("bad --fan-in: {e}"))?;
+                }
+                "--temp-dir" => cfg.temp_dir = value("--temp-dir")?.into(),
+                _ if arg.starts_with('-') && arg != "-" => {
+                    return Err(anyhow!("unknown option: {arg}"))
+                }
+                _ => paths.push(arg),
+            }
+        }
+
+        let [input, output]: [String; 2] = paths
+            .try_into()
+            .map_err(|_| anyhow!("expected an INPUT and an OUTPUT"))?;
+
+        Ok(Some(Self {
+            format,
+            cfg,
+            verbose,
+            input,
+            output: output.into(),
+        }))
+    }
+}
+
+fn main() -> ExitCode {
+    let opts = match Options::parse(env::args().skip(1)) {
+        Ok(Some(opts)) => opts,
+        Ok(None) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&opts) {
+        Ok(summary) => {
+            if opts.verbose {
+                eprintln!(
+                    "{} records, {} runs, {} merge passes",
+                    summary.records, summary.runs, summary.merge_passes
+                );
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(opts: &Options) -> Result<ExternalSortSummary> {
+    match opts.format.as_str() {
+        "lines" => sort::<String>(opts),
+        "i32" => sort::<i32>(opts),
+        "u32" => sort::<u32>(opts),
+        "i64" => sort::<i64>(opts),
+        "u64" => sort::<u64>(opts),
+        f => Err(anyhow!("unknown format: {f}")),
+    }
+}
+
+fn sort<R: Record>(opts: &Options) -> Result<ExternalSortSummary> {
+    let summary = if opts.input == "-" {
+        external_sort_reader::<R, _>(io::stdin().lock(), &opts.output, &opts.cfg)
+    } else {
+        let file = std::fs::File::open(Path::new(&opts.input))
+            .map_err(|e| anyhow!("failed to open {}: {e}", opts.input))?;
+        external_sort_reader::<R, _>(BufReader::new(file), &opts.output, &opts.cfg)
+    };
+
+    Ok(summary?)
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Options>> {
+        Options::parse(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse() {
+        let opts = parse(&[
+            "--format", "u64", "--memory", "2", "--fan-in", "8", "in", "out",
+        ])
+        .expect("failed to parse")
+        .expect("asked for help");
+        assert_eq!(opts.format, "u64");
+        assert_eq!(opts.cfg.memory, 2 << 20);
+        assert_eq!(opts.cfg.fan_in, 8);
+        assert_eq!(opts.input, "in");
+        assert_eq!(opts.output, PathBuf::from("out"));
+
+        let opts = parse(&["-", "out"])
+            .expect("failed to parse")
+            .expect("asked for help");
+        assert_eq!(opts.input, "-");
+        assert_eq!(opts.format, "lines");
+
+        assert!(parse(&["--help"]).expect("failed to parse").is_none());
+        assert!(parse(&["in"]).is_err());
+        assert!(parse(&["in", "out", "extra"]).is_err());
+        assert!(parse(&["--memory", "lots", "in", "out"]).is_err());
+        let big = parse(&["--memory", "17592186044416", "in", "out"]);
+        assert_eq!(
+            big.expect_err("parsed a memory size that wraps")
+                .to_string(),
+            "bad --memory: too large"
+        );
+        assert!(parse(&["--bogus", "in", "out"]).is_err());
+        assert!(parse(&["in", "out", "--format"]).is_err());
+    }
+
+    #[test]
+    fn test_unknown_format() {
+        let mut opts = parse(&["in", "out"])
+            .expect("failed to parse")
+            .expect("asked for help");
+        opts.format = "f16".to_string();
+        assert!(run(&opts).is_err());
+    }
+}
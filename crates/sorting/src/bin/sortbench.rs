@@ -0,0 +1,398 @@
+//! Times every sort on every input distribution and size, and appends the
+//! results to a CSV file so runs can be compared over time.
+//!
+//! Run `cargo r --release --bin sortbench -- --help` for usage.
+use std::{
+    env,
+    fs::OpenOptions,
+    hint::black_box,
+    io::Write,
+    path::PathBuf,
+    process::ExitCode,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+
+use sorting::{
+    sort_stats, BottomUpMergeSort, Counted, CountingSort, Distribution, Heapsort, Introsort,
+    MergeSort, ParMergeSort, ParQuicksort, ParRadixSort, Quicksort, RadixSort, Sorter, Timsort,
+};
+
+const USAGE: &str = "\
+Usage: sortbench [OPTIONS]
+
+Times every sort on every input distribution at each size, printing a
+table, and optionally appending the results to a CSV file.
+
+Options:
+  --sizes LIST   comma separated sizes (default 100,1000,10000,100000,1000000)
+  --sorts LIST   only these sorts, e.g. introsort,timsort
+  --dists LIST   only these distributions, e.g. random,killer
+  --runs N       timed runs per case; the median is reported (default 5)
+  --stats        also count comparisons, swaps and recursion depth, with
+                 one extra run per case
+  --csv FILE     append the results to FILE, with a header if it's new
+  --label TEXT   a tag for this run's CSV rows, e.g. a git commit
+  -h, --help     print this help
+";
+
+const CSV_HEADER: &str = "unix_time,label,sort,distribution,n,runs,median_ns,min_ns,\
+ns_per_element,comparisons,swaps,max_depth";
+
+// Plain quicksort is quadratic on anything but random input, and recurses
+// once per element, so it's skipped past this size.
+const QUADRATIC_LIMIT: usize = 10_000;
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    sizes: Vec<usize>,
+    sorts: Vec<String>,
+    dists: Vec<String>,
+    runs: usize,
+    stats: bool,
+    csv: Option<PathBuf>,
+    label: String,
+}
+
+impl Options {
+    /// Returns `None` when help was asked for.
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Self>> {
+        let mut opts = Self {
+            sizes: vec![100, 1_000, 10_000, 100_000, 1_000_000],
+            sorts: vec![],
+            dists: vec![],
+            runs: 5,
+            stats: false,
+            csv: None,
+            label: String::new(),
+        };
+
+        let list = |s: String| -> Vec<String> { s.split(',').map(str::to_string).collect() };
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or_else(|| anyhow!("{name} needs a value"));
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--sizes" => {
+                    opts.sizes = list(value("--sizes")?)
+                        .iter()
+                        .map(|s| s.parse().map_err(|e| anyhow!("bad --sizes: {e}")))
+                        .collect::<Result<_>>()?;
+                }
+                "--sorts" => opts.sorts = list(value("--sorts")?),
+                "--dists" => opts.dists = list(value("--dists")?),
+                "--runs" => {
+                    opts.runs = value("--runs")?
+                        .parse()
+                        .map_err(|e| anyhow!("bad --runs: {e}"))?;
+                    if opts.runs == 0 {
+                        return Err(anyhow!("--runs must be at least 1"));
+                    }
+                }
+                "--stats" => opts.stats = true,
+                "--csv" => opts.csv = Some(value("--csv")?.into()),
+                "--label" => opts.label = value("--label")?,
+                _ => return Err(anyhow!("unknown option: {arg}")),
+            }
+        }
+
+        Ok(Some(opts))
+    }
+}
+
+// A sort to time, and the same sort on counted elements for --stats if
+// it's a comparison sort that runs on this thread.
+struct Entry {
+    sorter: Box<dyn Sorter<i32>>,
+    counted: Option<Box<dyn Sorter<Counted<i32>>>>,
+}
+
+fn entries() -> Vec<Entry> {
+    fn both<S>(s: S) -> Entry
+    where
+        S: Sorter<i32> + Sorter<Counted<i32>> + Copy + 'static,
+    {
+        Entry {
+            sorter: Box::new(s),
+            counted: Some(Box::new(s)),
+        }
+    }
+
+    fn plain(s: impl Sorter<i32> + 'static) -> Entry {
+        Entry {
+            sorter: Box::new(s),
+            counted: None,
+        }
+    }
+
+    vec![
+        both(Quicksort),
+        both(Introsort),
+        both(MergeSort),
+        both(BottomUpMergeSort),
+        both(Timsort),
+        both(Heapsort),
+        plain(RadixSort),
+        plain(CountingSort),
+        plain(ParQuicksort::default()),
+        plain(ParMergeSort::default()),
+        plain(ParRadixSort::default()),
+    ]
+}
+
+// "nearly_sorted(16)" is picked by "nearly_sorted".
+fn dist_name(dist: &Distribution) -> String {
+    let name = dist.to_string();
+    name.split('(').next().unwrap_or(&name).to_string()
+}
+
+fn wanted(filter: &[String], name: &str) -> bool {
+    filter.is_empty() || filter.iter().any(|f| f == name)
+}
+
+#[derive(Debug)]
+struct Row {
+    sort: &'static str,
+    dist: String,
+    n: usize,
+    runs: usize,
+    median_ns: u128,
+    min_ns: u128,
+    comparisons: Option<u64>,
+    swaps: Option<u64>,
+    max_depth: Option<u32>,
+}
+
+impl Row {
+    fn ns_per_element(&self) -> f64 {
+        self.median_ns as f64 / self.n.max(1) as f64
+    }
+
+    fn to_csv(&self, time: u64, label: &str) -> String {
+        let opt = |x: Option<String>| x.unwrap_or_default();
+        format!(
+            "{time},{},{},{},{},{},{},{},{:.3},{},{},{}",
+            quote(label),
+            self.sort,
+            quote(&self.dist),
+            self.n,
+            self.runs,
+            self.median_ns,
+            self.min_ns,
+            self.ns_per_element(),
+            opt(self.comparisons.map(|c| c.to_string())),
+            opt(self.swaps.map(|c| c.to_string())),
+            opt(self.max_depth.map(|c| c.to_string())),
+        )
+    }
+}
+
+// A CSV field in quotes, with quotes inside doubled, so commas and quotes
+// in labels and distribution names don't split it.
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
+
+fn time(
+    entry: &Entry,
+    v: &[i32],
+    runs: usize,
+    stats: bool,
+) -> (u128, u128, Option<(u64, u64, u32)>) {
+    let mut times: Vec<u128> = (0..runs)
+        .map(|_| {
+            let mut w = v.to_vec();
+            let start = Instant::now();
+            entry.sorter.sort(black_box(&mut w));
+            start.elapsed().as_nanos()
+        })
+        .collect();
+    times.sort_unstable();
+
+    let counts = match (&entry.counted, stats) {
+        (Some(counted), true) => {
+            let s = sort_stats(counted.as_ref(), v);
+            Some((s.comparisons, s.swaps, s.max_depth))
+        }
+        _ => None,
+    };
+
+    (times[times.len() / 2], times[0], counts)
+}
+
+fn run(opts: &Options) -> Result<Vec<Row>> {
+    let entries: Vec<Entry> = entries()
+        .into_iter()
+        .filter(|e| wanted(&opts.sorts, e.sorter.name()))
+        .collect();
+    let dists: Vec<Distribution> = Distribution::all()
+        .into_iter()
+        .filter(|d| wanted(&opts.dists, &dist_name(d)))
+        .collect();
+    if entries.is_empty() || dists.is_empty() {
+        return Err(anyhow!("no sorts or distributions match"));
+    }
+
+    let mut rows = vec![];
+    for dist in &dists {
+        for &n in &opts.sizes {
+            let v = dist.generate(n, 1);
+            for entry in &entries {
+                let name = entry.sorter.name();
+                if name == "quicksort" && *dist != Distribution::Random && n > QUADRATIC_LIMIT {
+                    continue;
+                }
+
+                let (median_ns, min_ns, counts) = time(entry, &v, opts.runs, opts.stats);
+                let row = Row {
+                    sort: name,
+                    dist: dist.to_string(),
+                    n,
+                    runs: opts.runs,
+                    median_ns,
+                    min_ns,
+                    comparisons: counts.map(|c| c.0),
+                    swaps: counts.map(|c| c.1),
+                    max_depth: counts.map(|c| c.2),
+                };
+                println!(
+                    "{:<20} {:<18} {:>10} {:>14} ns {:>10.2} ns/elem",
+                    row.sort,
+                    row.dist,
+                    row.n,
+                    row.median_ns,
+                    row.ns_per_element()
+                );
+                rows.push(row);
+            }
+        }
+    }
+
+    Ok(rows)
+}
+
+fn write_csv(path: &PathBuf, rows: &[Row], label: &str) -> Result<()> {
+    let new = !path.exists();
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| anyhow!("failed to open {}: {e}", path.display()))?;
+
+    let time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    if new {
+        writeln!(file, "{CSV_HEADER}")?;
+    }
+    for row in rows {
+        writeln!(file, "{}", row.to_csv(time, label))?;
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let opts = match Options::parse(env::args().skip(1)) {
+        Ok(Some(opts)) => opts,
+        Ok(None) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let result = run(&opts).and_then(|rows| match &opts.csv {
+        Some(path) => write_csv(path, &rows, &opts.label),
+        None => Ok(()),
+    });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Options>> {
+        Options::parse(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse() {
+        let opts = parse(&[
+            "--sizes", "10,20", "--sorts", "timsort", "--runs", "3", "--stats", "--csv", "o.csv",
+        ])
+        .expect("failed to parse")
+        .expect("asked for help");
+        assert_eq!(opts.sizes, [10, 20]);
+        assert_eq!(opts.sorts, ["timsort"]);
+        assert_eq!(opts.runs, 3);
+        assert!(opts.stats);
+        assert_eq!(opts.csv, Some(PathBuf::from("o.csv")));
+
+        assert!(parse(&["-h"]).expect("failed to parse").is_none());
+        assert!(parse(&["--sizes", "10,x"]).is_err());
+        assert!(parse(&["--runs", "0"]).is_err());
+        assert!(parse(&["--runs"]).is_err());
+        assert!(parse(&["extra"]).is_err());
+    }
+
+    #[test]
+    fn test_run_and_csv() {
+        let opts = parse(&[
+            "--sizes",
+            "50,20000",
+            "--sorts",
+            "quicksort,timsort",
+            "--dists",
+            "random,sorted",
+            "--runs",
+            "1",
+            "--stats",
+        ])
+        .expect("failed to parse")
+        .expect("asked for help");
+        let rows = run(&opts).expect("failed to run");
+
+        // Quicksort on 20000 sorted elements is skipped.
+        assert_eq!(rows.len(), 7);
+        let timsort = rows
+            .iter()
+            .find(|r| r.sort == "timsort" && r.dist == "sorted" && r.n == 50)
+            .expect("missing row");
+        assert_eq!(timsort.comparisons, Some(49));
+
+        let dir = env::temp_dir().join(format!("sortbench-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("failed to make temp dir");
+        let path = dir.join("out.csv");
+        write_csv(&path, &rows, "test").expect("failed to write");
+        write_csv(&path, &rows, "test").expect("failed to append");
+        let csv = std::fs::read_to_string(&path).expect("failed to read");
+        std::fs::remove_dir_all(&dir).expect("failed to clean up");
+
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 1 + 2 * rows.len());
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines[1].split(',').count(), CSV_HEADER.split(',').count());
+        assert!(lines[1].contains(",\"test\",quicksort,\"random\","));
+
+        // Commas and quotes in the label stay inside its field.
+        let row = rows[0].to_csv(0, "fix a, \"b\"");
+        assert!(
+            row.starts_with("0,\"fix a, \"\"b\"\"\",quicksort,"),
+            "{row}"
+        );
+
+        let opts = parse(&["--sorts", "bogus"])
+            .expect("failed to parse")
+            .expect("help");
+        assert!(run(&opts).is_err());
+    }
+}
@@ -0,0 +1,242 @@
+//! Shows what a sort does, step by step, as an animated bar chart in the
+//! terminal or as SVG frames.
+//!
+//! Run `cargo r --bin sortviz -- --help` for usage.
+use std::{env, fs, io, path::PathBuf, process::ExitCode, time::Duration};
+
+use anyhow::{anyhow, Result};
+
+use sorting::{
+    animate, html_page, msd_radix_sort, multikey_quicksort, trace, trace_sorter, write_svg_frames,
+    BottomUpMergeSort, CountingSort, Distribution, Heapsort, Introsort, MergeSort, ParMergeSort,
+    ParQuicksort, ParRadixSort, ParallelConfig, Quicksort, RadixSort, Sorter, Timsort, Trace,
+};
+
+const USAGE: &str = "\
+Usage: sortviz [OPTIONS] SORT
+
+Records every comparison, swap and copy SORT makes and plays them back as
+a bar chart, with compared bars in yellow and written ones in red.
+
+Sorts: quicksort, introsort, merge_sort, bottom_up_merge_sort, timsort,
+heapsort, radix_sort, counting_sort, msd_radix_sort, multikey_quicksort,
+par_quicksort, par_merge_sort, par_radix_sort
+
+Options:
+  -n N           elements to sort (default 40)
+  --dist NAME    input distribution, e.g. random, reversed, killer
+                 (default random)
+  --seed SEED    seed for the input (default 1)
+  --delay MS     time per frame (default 40)
+  --every K      draw every Kth step (default 1)
+  --height ROWS  terminal chart height (default 16)
+  --svg DIR      write SVG frames and an index.html to DIR instead
+  --html FILE    write one self-contained HTML page to FILE instead
+  -h, --help     print this help
+";
+
+// The parallel sorts split down to this, so small inputs still spread
+// over threads.
+const PARALLEL: ParallelConfig = ParallelConfig {
+    threads: 4,
+    cutoff: 8,
+};
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    sort: String,
+    n: usize,
+    dist: String,
+    seed: u64,
+    delay: Duration,
+    every: usize,
+    height: usize,
+    svg: Option<PathBuf>,
+    html: Option<PathBuf>,
+}
+
+impl Options {
+    /// Returns `None` when help was asked for.
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Self>> {
+        let mut opts = Self {
+            sort: String::new(),
+            n: 40,
+            dist: "random".to_string(),
+            seed: 1,
+            delay: Duration::from_millis(40),
+            every: 1,
+            height: 16,
+            svg: None,
+            html: None,
+        };
+        let mut sorts = vec![];
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or_else(|| anyhow!("{name} needs a value"));
+            let number = |name: &str, s: String| -> Result<u64> {
+                s.parse().map_err(|e| anyhow!("bad {name}: {e}"))
+            };
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "-n" => opts.n = number("-n", value("-n")?)? as usize,
+                "--dist" => opts.dist = value("--dist")?,
+                "--seed" => opts.seed = number("--seed", value("--seed")?)?,
+                "--delay" => {
+                    opts.delay = Duration::from_millis(number("--delay", value("--delay")?)?);
+                }
+                "--every" => opts.every = number("--every", value("--every")?)?.max(1) as usize,
+                "--height" => opts.height = number("--height", value("--height")?)?.max(1) as usize,
+                "--svg" => opts.svg = Some(value("--svg")?.into()),
+                "--html" => opts.html = Some(value("--html")?.into()),
+                _ if arg.starts_with('-') => return Err(anyhow!("unknown option: {arg}")),
+                _ => sorts.push(arg),
+            }
+        }
+
+        match sorts.as_slice() {
+            [sort] => opts.sort = sort.clone(),
+            [] => return Err(anyhow!("which sort?")),
+            _ => return Err(anyhow!("one sort at a time")),
+        }
+
+        Ok(Some(opts))
+    }
+}
+
+fn distribution(name: &str) -> Result<Distribution> {
+    Distribution::all()
+        .into_iter()
+        .find(|d| d.to_string().split('(').next() == Some(name))
+        .ok_or_else(|| anyhow!("unknown distribution: {name}"))
+}
+
+// Traces a byte string sort on i32s. Big-endian with the sign bit flipped
+// sorts bytewise like i32.
+fn bytewise(v: &[i32], sort: fn(&mut [[u8; 4]])) -> Trace<i32> {
+    let key = |x: i32| ((x as u32) ^ (1 << 31)).to_be_bytes();
+    let bytes: Vec<[u8; 4]> = v.iter().map(|&x| key(x)).collect();
+    trace(&bytes, sort).map(|b| (u32::from_be_bytes(b) ^ (1 << 31)) as i32)
+}
+
+fn record(sort: &str, v: &[i32]) -> Result<Trace<i32>> {
+    let comparison: Option<Box<dyn Sorter<_>>> = match sort {
+        "quicksort" => Some(Box::new(Quicksort)),
+        "introsort" => Some(Box::new(Introsort)),
+        "merge_sort" => Some(Box::new(MergeSort)),
+        "bottom_up_merge_sort" => Some(Box::new(BottomUpMergeSort)),
+        "timsort" => Some(Box::new(Timsort)),
+        "heapsort" => Some(Box::new(Heapsort)),
+        "par_quicksort" => Some(Box::new(ParQuicksort(PARALLEL))),
+        "par_merge_sort" => Some(Box::new(ParMergeSort(PARALLEL))),
+        _ => None,
+    };
+    if let Some(sorter) = comparison {
+        return Ok(trace_sorter(sorter.as_ref(), v));
+    }
+
+    Ok(match sort {
+        "radix_sort" => trace(v, |s| RadixSort.sort(s)),
+        "counting_sort" => trace(v, |s| CountingSort.sort(s)),
+        "par_radix_sort" => trace(v, |s| ParRadixSort(PARALLEL).sort(s)),
+        "msd_radix_sort" => bytewise(v, msd_radix_sort),
+        "multikey_quicksort" => bytewise(v, multikey_quicksort),
+        _ => return Err(anyhow!("unknown sort: {sort}")),
+    })
+}
+
+fn run(opts: &Options) -> Result<()> {
+    let v = distribution(&opts.dist)?.generate(opts.n, opts.seed);
+    let trace = record(&opts.sort, &v)?;
+    let title = format!("{} on {} {}", opts.sort, opts.n, opts.dist);
+
+    if let Some(dir) = &opts.svg {
+        let frames = write_svg_frames(&trace, dir, opts.every, opts.delay)
+            .map_err(|e| anyhow!("failed to write {}: {e}", dir.display()))?;
+        println!("{title}: wrote {frames} frames to {}", dir.display());
+    } else if let Some(path) = &opts.html {
+        fs::write(path, html_page(&trace, &title, opts.every, opts.delay))
+            .map_err(|e| anyhow!("failed to write {}: {e}", path.display()))?;
+        println!("{title}: wrote {}", path.display());
+    } else {
+        animate(
+            &trace,
+            &mut io::stdout().lock(),
+            opts.height,
+            opts.every,
+            opts.delay,
+        )?;
+        println!("{title}: {}", trace.stats);
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let opts = match Options::parse(env::args().skip(1)) {
+        Ok(Some(opts)) => opts,
+        Ok(None) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&opts) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Options>> {
+        Options::parse(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse() {
+        let opts = parse(&["timsort", "-n", "20", "--dist", "killer", "--every", "0"])
+            .expect("failed to parse")
+            .expect("asked for help");
+        assert_eq!(opts.sort, "timsort");
+        assert_eq!(opts.n, 20);
+        assert_eq!(opts.dist, "killer");
+        assert_eq!(opts.every, 1);
+
+        assert!(parse(&["--help"]).expect("failed to parse").is_none());
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["timsort", "heapsort"]).is_err());
+        assert!(parse(&["timsort", "-n", "x"]).is_err());
+        assert!(parse(&["timsort", "--bogus"]).is_err());
+    }
+
+    #[test]
+    fn test_record() {
+        let v = distribution("nearly_sorted")
+            .expect("missing distribution")
+            .generate(100, 3);
+        let mut want = v.clone();
+        want.sort();
+
+        let sorts = USAGE
+            .split("Sorts: ")
+            .nth(1)
+            .and_then(|s| s.split("\n\n").next())
+            .expect("no sorts in usage");
+        for sort in sorts.split(',').map(str::trim) {
+            let trace = record(sort, &v).expect("failed to record");
+            assert_eq!(trace.result(), want, "{sort}");
+        }
+
+        assert!(record("bogus", &v).is_err());
+        assert!(distribution("bogus").is_err());
+    }
+}
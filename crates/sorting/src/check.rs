@@ -0,0 +1,492 @@
+//! Checking a sort's output against `slice::sort`.
+//!
+//! [`check_sorted`](crate::check_sorted) only looks at the order, so a sort
+//! that drops or duplicates elements passes it. [`check_sort`] also checks
+//! that the output holds exactly the input's elements, and
+//! [`check_stable_sort`] that equal keys kept their original order.
+//! [`check_sorter`] and [`check_stable_sorter`] run a sort over a set of
+//! [`cases`]: random, edge-case and adversarial inputs, fixed by a seed.
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::{antiqsort, quicksort_by, Distribution, Prng};
+
+/// How a sort's output was wrong.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CheckError {
+    /// The output has a different number of elements than the input.
+    Length { want: usize, got: usize },
+    /// `output[at] > output[at + 1]`.
+    Unordered { at: usize },
+    /// In order, but not the input's elements; `at` is the first index
+    /// where the output differs from the input sorted.
+    Changed { at: usize },
+    /// The input's elements in order, but equal keys aren't in their
+    /// original order, first at `at`.
+    Unstable { at: usize },
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CheckError::Length { want, got } => {
+                write!(f, "expected {want} elements, got {got}")
+            }
+            CheckError::Unordered { at } => write!(f, "out of order at {at}"),
+            CheckError::Changed { at } => {
+                write!(f, "not a permutation of the input, first differs at {at}")
+            }
+            CheckError::Unstable { at } => write!(f, "equal keys reordered at {at}"),
+        }
+    }
+}
+
+impl std::error::Error for CheckError {}
+
+/// A check that failed on one of the [`cases`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheckFailure {
+    pub case: String,
+    pub error: CheckError,
+}
+
+impl fmt::Display for CheckFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.case, self.error)
+    }
+}
+
+impl std::error::Error for CheckFailure {}
+
+/// Checks that `output` is `input` in order: the same length, ascending,
+/// and the same elements as `slice::sort` gives.
+pub fn check_sort<T: Ord + Clone>(input: &[T], output: &[T]) -> Result<(), CheckError> {
+    if output.len() != input.len() {
+        return Err(CheckError::Length {
+            want: input.len(),
+            got: output.len(),
+        });
+    }
+    if let Some(at) = output.windows(2).position(|w| w[0] > w[1]) {
+        return Err(CheckError::Unordered { at });
+    }
+
+    let mut want = input.to_vec();
+    want.sort();
+    match want.iter().zip(output).position(|(a, b)| a != b) {
+        Some(at) => Err(CheckError::Changed { at }),
+        None => Ok(()),
+    }
+}
+
+/// A key with a tag to tell equal keys apart, for checking stability.
+/// Compares by key alone.
+#[derive(Clone, Copy, Debug)]
+pub struct Tagged {
+    pub key: i32,
+    pub tag: u32,
+}
+
+impl PartialEq for Tagged {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for Tagged {}
+
+impl PartialOrd for Tagged {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Tagged {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+/// Like [`check_sort`], and also checks that equal keys come out in the
+/// order they went in, as the stable `slice::sort` leaves them.
+pub fn check_stable_sort(input: &[Tagged], output: &[Tagged]) -> Result<(), CheckError> {
+    check_sort(input, output)?;
+
+    let mut want = input.to_vec();
+    want.sort();
+    let Some(at) = want.iter().zip(output).position(|(a, b)| a.tag != b.tag) else {
+        return Ok(());
+    };
+
+    // Equal keys that swapped places, or a tag that went missing.
+    let tags = |s: &[Tagged]| {
+        let mut tags: Vec<(i32, u32)> = s.iter().map(|t| (t.key, t.tag)).collect();
+        tags.sort_unstable();
+        tags
+    };
+    if tags(input) == tags(output) {
+        Err(CheckError::Unstable { at })
+    } else {
+        Err(CheckError::Changed { at })
+    }
+}
+
+/// An input to check a sort on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Case {
+    /// What the input is, e.g. "few_unique(16) n=33".
+    pub name: String,
+    pub input: Vec<i32>,
+}
+
+/// The inputs [`check_sorter`] runs a sort over: edge cases, every
+/// [`Distribution`] at sizes around the small-slice cutoffs and up, values
+/// spread over the whole `i32` range, and a killer for the plain quicksort.
+pub fn cases(seed: u64) -> Vec<Case> {
+    let case = |name: &str, input: Vec<i32>| Case {
+        name: name.to_string(),
+        input,
+    };
+    let mut prng = Prng::from_seed(seed);
+
+    let mut cases = vec![
+        case("empty", vec![]),
+        case("one", vec![7]),
+        case("two in order", vec![1, 2]),
+        case("two reversed", vec![2, 1]),
+        case("two equal", vec![3, 3]),
+        case(
+            "extremes",
+            vec![i32::MAX, i32::MIN, 0, -1, 1, i32::MIN, i32::MAX, 0],
+        ),
+        case("alternating", (0..100).map(|i| i % 2).collect()),
+    ];
+
+    for dist in Distribution::all() {
+        for n in [3, 16, 17, 33, 100, 1000] {
+            cases.push(case(&format!("{dist} n={n}"), dist.generate(n, seed)));
+        }
+    }
+
+    let random = Distribution::Random.generate(1000, seed);
+    cases.push(case("negative", random.iter().map(|x| x - 500).collect()));
+    cases.push(case(
+        "full range",
+        (0..1000).map(|_| prng.next_u64() as i32).collect(),
+    ));
+    cases.push(case(
+        "quicksort killer",
+        antiqsort(500, |s, cmp| quicksort_by(s, cmp)),
+    ));
+    cases.push(case(
+        "large random",
+        Distribution::Random.generate(20_000, seed),
+    ));
+
+    cases
+}
+
+/// Runs `sort` on a copy of each case's input and checks the result with
+/// [`check_sort`], stopping at the first failure.
+pub fn check_sorter(cases: &[Case], mut sort: impl FnMut(&mut [i32])) -> Result<(), CheckFailure> {
+    for case in cases {
+        let mut output = case.input.clone();
+        sort(&mut output);
+        check_sort(&case.input, &output).map_err(|error| CheckFailure {
+            case: case.name.clone(),
+            error,
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Like [`check_sorter`] for a stable sort: each case's values are tagged
+/// with their index and checked with [`check_stable_sort`].
+pub fn check_stable_sorter(
+    cases: &[Case],
+    mut sort: impl FnMut(&mut [Tagged]),
+) -> Result<(), CheckFailure> {
+    for case in cases {
+        let input: Vec<Tagged> = case
+            .input
+            .iter()
+            .enumerate()
+            .map(|(i, &key)| Tagged { key, tag: i as u32 })
+            .collect();
+        let mut output = input.clone();
+        sort(&mut output);
+        check_stable_sort(&input, &output).map_err(|error| CheckFailure {
+            case: case.name.clone(),
+            error,
+        })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod unit {
+    use std::fs;
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{
+        bottom_up_merge_sort, counting_sort, counting_sort_by_key, counting_sort_by_signed_key,
+        dary_heapsort_by, external_sort_reader, heapsort, introsort, introsort_by_key, merge_sort,
+        merge_sort_by_key, msd_radix_sort, multikey_quicksort, par_merge_sort, par_quicksort,
+        par_radix_sort, partial_sort, quicksort, quicksort_by_key, radix_sort, radix_sort_by_key,
+        sort_floats, timsort, timsort_by_key, BottomUpMergeSort, CountingSort, ExternalSortConfig,
+        Heapsort, Introsort, MergeSort, NanPlacement, ParMergeSort, ParQuicksort, ParRadixSort,
+        ParallelConfig, Quicksort, RadixSort, Sorter, Timsort,
+    };
+
+    const SEED: u64 = 0x5eed;
+
+    // Small enough that the parallel sorts split on these inputs.
+    const PAR: ParallelConfig = ParallelConfig {
+        threads: 4,
+        cutoff: 32,
+    };
+
+    // The counting sort functions allocate a counter per value in the
+    // range, so they only get cases where that's a reasonable number.
+    fn countable(cases: &[Case]) -> Vec<Case> {
+        cases
+            .iter()
+            .filter(|c| {
+                let (min, max) = (c.input.iter().min(), c.input.iter().max());
+                min.zip(max)
+                    .is_none_or(|(&lo, &hi)| (hi as i64 - lo as i64) < 1 << 24)
+            })
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn test_check_sort() {
+        assert_eq!(check_sort(&[3, 1, 2], &[1, 2, 3]), Ok(()));
+        assert_eq!(
+            check_sort(&[3, 1, 2], &[1, 2]),
+            Err(CheckError::Length { want: 3, got: 2 })
+        );
+        assert_eq!(
+            check_sort(&[3, 1, 2], &[1, 3, 2]),
+            Err(CheckError::Unordered { at: 1 })
+        );
+        // Sorted, which is all check_sorted looks at, but 2 was lost.
+        assert_eq!(
+            check_sort(&[3, 1, 2], &[1, 1, 3]),
+            Err(CheckError::Changed { at: 1 })
+        );
+
+        let tag = |key, tag| Tagged { key, tag };
+        let input = [tag(2, 0), tag(1, 1), tag(2, 2)];
+        assert_eq!(
+            check_stable_sort(&input, &[tag(1, 1), tag(2, 0), tag(2, 2)]),
+            Ok(())
+        );
+        assert_eq!(
+            check_stable_sort(&input, &[tag(1, 1), tag(2, 2), tag(2, 0)]),
+            Err(CheckError::Unstable { at: 1 })
+        );
+        assert_eq!(
+            check_stable_sort(&input, &[tag(1, 1), tag(2, 0), tag(2, 0)]),
+            Err(CheckError::Changed { at: 2 })
+        );
+
+        // A sort that drops the last element and pads with the first.
+        let cases = cases(SEED);
+        let broken = check_sorter(&cases, |s| {
+            if s.len() > 1 {
+                s[s.len() - 1] = s[0];
+            }
+            s.sort();
+        });
+        assert!(matches!(
+            broken,
+            Err(CheckFailure {
+                error: CheckError::Changed { .. },
+                ..
+            })
+        ));
+        // The standard library's unstable sort isn't stable.
+        assert!(check_stable_sorter(&cases, |s| s.sort_unstable()).is_err());
+    }
+
+    #[test]
+    fn test_cases() {
+        let cases = cases(SEED);
+        assert_eq!(cases, super::cases(SEED));
+        assert!(cases.iter().any(|c| c.input.is_empty()));
+        assert!(cases.iter().any(|c| c.input.contains(&i32::MIN)));
+        assert!(cases.iter().any(|c| c.input.len() >= 10_000));
+        assert!(countable(&cases).len() < cases.len());
+    }
+
+    #[test]
+    fn test_every_sort() {
+        let cases = cases(SEED);
+        let check = |name: &str, result: Result<(), CheckFailure>| {
+            if let Err(e) = result {
+                panic!("{name} failed on {e}");
+            }
+        };
+
+        let sorters: [Box<dyn Sorter<i32>>; 11] = [
+            Box::new(Quicksort),
+            Box::new(Introsort),
+            Box::new(MergeSort),
+            Box::new(BottomUpMergeSort),
+            Box::new(Timsort),
+            Box::new(Heapsort),
+            Box::new(RadixSort),
+            Box::new(ParQuicksort(PAR)),
+            Box::new(ParMergeSort(PAR)),
+            Box::new(ParRadixSort(PAR)),
+            Box::new(CountingSort),
+        ];
+        for sorter in &sorters {
+            check(sorter.name(), check_sorter(&cases, |s| sorter.sort(s)));
+        }
+
+        check("quicksort", check_sorter(&cases, quicksort));
+        check(
+            "quicksort_by_key",
+            check_sorter(&cases, |s| quicksort_by_key(s, |&x| x)),
+        );
+        check("introsort", check_sorter(&cases, introsort));
+        check(
+            "introsort_by_key",
+            check_sorter(&cases, |s| introsort_by_key(s, |&x| x)),
+        );
+        check("heapsort", check_sorter(&cases, heapsort));
+        for arity in [3, 4, 8] {
+            check(
+                "dary_heapsort_by",
+                check_sorter(&cases, |s| dary_heapsort_by(s, arity, i32::cmp)),
+            );
+        }
+        check(
+            "partial_sort",
+            check_sorter(&cases, |s| partial_sort(s, s.len())),
+        );
+        check("radix_sort", check_sorter(&cases, radix_sort));
+        check(
+            "par_quicksort",
+            check_sorter(&cases, |s| par_quicksort(s, PAR)),
+        );
+        check(
+            "par_radix_sort",
+            check_sorter(&cases, |s| par_radix_sort(s, PAR)),
+        );
+
+        // Big-endian with the sign bit flipped sorts bytewise like i32.
+        let bytewise = |sort: fn(&mut [[u8; 4]])| {
+            move |s: &mut [i32]| {
+                let mut bytes: Vec<[u8; 4]> = s
+                    .iter()
+                    .map(|&x| ((x as u32) ^ (1 << 31)).to_be_bytes())
+                    .collect();
+                sort(&mut bytes);
+                for (x, b) in s.iter_mut().zip(bytes) {
+                    *x = (u32::from_be_bytes(b) ^ (1 << 31)) as i32;
+                }
+            }
+        };
+        check(
+            "msd_radix_sort",
+            check_sorter(&cases, bytewise(msd_radix_sort)),
+        );
+        check(
+            "multikey_quicksort",
+            check_sorter(&cases, bytewise(multikey_quicksort)),
+        );
+
+        // Every i32 is exactly an f64.
+        let floats = |s: &mut [i32]| {
+            let mut f: Vec<f64> = s.iter().map(|&x| x as f64).collect();
+            sort_floats(&mut f, NanPlacement::Last);
+            for (x, f) in s.iter_mut().zip(f) {
+                *x = f as i32;
+            }
+        };
+        check("sort_floats", check_sorter(&cases, floats));
+
+        check(
+            "counting_sort",
+            check_sorter(&cases, |s| {
+                let sorted = counting_sort(s.to_vec());
+                s.copy_from_slice(&sorted);
+            }),
+        );
+
+        let dir = std::env::temp_dir().join(format!("check-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("failed to make temp dir");
+        let cfg = ExternalSortConfig {
+            memory: 256,
+            fan_in: 3,
+            temp_dir: dir.clone(),
+        };
+        let out = dir.join("out");
+        let external = |s: &mut [i32]| {
+            let bytes: Vec<u8> = s.iter().flat_map(|x| x.to_le_bytes()).collect();
+            external_sort_reader::<i32, _>(Cursor::new(bytes), &out, &cfg).expect("failed to sort");
+            let sorted = fs::read(&out).expect("failed to read output");
+            for (x, b) in s.iter_mut().zip(sorted.chunks_exact(4)) {
+                *x = i32::from_le_bytes(b.try_into().expect("short record"));
+            }
+        };
+        let result = check_sorter(&cases, external);
+        fs::remove_dir_all(&dir).expect("failed to clean up");
+        check("external_sort", result);
+    }
+
+    #[test]
+    fn test_every_stable_sort() {
+        let cases = cases(SEED);
+        let check = |name: &str, result: Result<(), CheckFailure>| {
+            if let Err(e) = result {
+                panic!("{name} failed on {e}");
+            }
+        };
+
+        check("merge_sort", check_stable_sorter(&cases, merge_sort));
+        check(
+            "merge_sort_by_key",
+            check_stable_sorter(&cases, |s| merge_sort_by_key(s, |t| t.key)),
+        );
+        check(
+            "bottom_up_merge_sort",
+            check_stable_sorter(&cases, bottom_up_merge_sort),
+        );
+        check("timsort", check_stable_sorter(&cases, timsort));
+        check(
+            "timsort_by_key",
+            check_stable_sorter(&cases, |s| timsort_by_key(s, |t| t.key)),
+        );
+        check(
+            "par_merge_sort",
+            check_stable_sorter(&cases, |s| par_merge_sort(s, PAR)),
+        );
+        check(
+            "radix_sort_by_key",
+            check_stable_sorter(&cases, |s| radix_sort_by_key(s, |t| t.key)),
+        );
+
+        let countable = countable(&cases);
+        check(
+            "counting_sort_by_signed_key",
+            check_stable_sorter(&countable, |s| {
+                counting_sort_by_signed_key(s, |t| t.key as i64).expect("failed to count")
+            }),
+        );
+        check(
+            "counting_sort_by_key",
+            check_stable_sorter(&countable, |s| {
+                let min = s.iter().map(|t| t.key).min().unwrap_or(0);
+                counting_sort_by_key(s, |t| (t.key - min) as usize).expect("failed to count")
+            }),
+        );
+    }
+}
@@ -0,0 +1,178 @@
+//! Locale-free string comparisons: plain or case-insensitive ASCII
+//! collation, and natural order, where digit runs compare as numbers so
+//! "file9" sorts before "file10".
+//!
+//! Every comparison here is a total order consistent with `==` on the
+//! strings: when two different strings tie under the collation, e.g.
+//! "Apple" and "apple" ignoring case or "a01" and "a1" in natural order,
+//! they're ordered by their bytes.
+use std::cmp::Ordering;
+
+/// How bytes compare.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Collation {
+    /// By byte value, as `str::cmp` does.
+    #[default]
+    Bytes,
+    /// By byte value with ASCII letters lowercased, so "apple" and "Apple"
+    /// sort together, before "banana". Other bytes are left alone.
+    IgnoreAsciiCase,
+}
+
+impl Collation {
+    /// The byte to compare `b` as.
+    pub fn fold(self, b: u8) -> u8 {
+        match self {
+            Collation::Bytes => b,
+            Collation::IgnoreAsciiCase => b.to_ascii_lowercase(),
+        }
+    }
+
+    /// Compares `a` and `b` under this collation, then by bytes.
+    pub fn cmp(self, a: &[u8], b: &[u8]) -> Ordering {
+        self.cmp_folded(a, b).then_with(|| a.cmp(b))
+    }
+
+    // Compares folded bytes only, without the tiebreak.
+    pub(crate) fn cmp_folded(self, a: &[u8], b: &[u8]) -> Ordering {
+        match self {
+            Collation::Bytes => a.cmp(b),
+            Collation::IgnoreAsciiCase => a
+                .iter()
+                .map(|&x| self.fold(x))
+                .cmp(b.iter().map(|&x| self.fold(x))),
+        }
+    }
+}
+
+/// Compares strings ignoring ASCII case, then by bytes.
+pub fn ascii_caseless_cmp(a: &str, b: &str) -> Ordering {
+    Collation::IgnoreAsciiCase.cmp(a.as_bytes(), b.as_bytes())
+}
+
+/// Natural order: runs of ASCII digits compare by their numeric value,
+/// however long, and everything else byte by byte.
+///
+/// ```
+/// use sorting::natural_cmp;
+///
+/// let mut files = ["file10.txt", "file9.txt", "file1.txt"];
+/// files.sort_by(|a, b| natural_cmp(a, b));
+/// assert_eq!(files, ["file1.txt", "file9.txt", "file10.txt"]);
+/// ```
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    natural_cmp_with(a, b, Collation::Bytes)
+}
+
+/// [`natural_cmp`] with the non-digit parts compared under `collation`.
+pub fn natural_cmp_with(a: &str, b: &str, collation: Collation) -> Ordering {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let order = if a[i].is_ascii_digit() && b[j].is_ascii_digit() {
+            let x = digits(&a[i..]);
+            let y = digits(&b[j..]);
+            i += x.len();
+            j += y.len();
+            number_cmp(x, y)
+        } else {
+            let order = collation.fold(a[i]).cmp(&collation.fold(b[j]));
+            i += 1;
+            j += 1;
+            order
+        };
+        if order != Ordering::Equal {
+            return order;
+        }
+    }
+
+    // A prefix sorts first, and what's left to break a tie is the bytes.
+    (a.len() - i).cmp(&(b.len() - j)).then_with(|| a.cmp(b))
+}
+
+// The run of ASCII digits at the start of `s`.
+fn digits(s: &[u8]) -> &[u8] {
+    let n = s.iter().take_while(|b| b.is_ascii_digit()).count();
+    &s[..n]
+}
+
+// Compares digit strings by value: without leading zeros, a longer number
+// is bigger, and equal lengths compare digit by digit.
+fn number_cmp(x: &[u8], y: &[u8]) -> Ordering {
+    let (x, y) = (trim_zeros(x), trim_zeros(y));
+    x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+}
+
+fn trim_zeros(s: &[u8]) -> &[u8] {
+    let zeros = s.iter().take_while(|&&b| b == b'0').count();
+    &s[zeros..]
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+
+    #[test]
+    fn test_collation() {
+        let mut words = ["banana", "Apple", "apple", "Cherry", "_x", "a"];
+        words.sort_by(|a, b| ascii_caseless_cmp(a, b));
+        assert_eq!(words, ["_x", "a", "Apple", "apple", "banana", "Cherry"]);
+
+        words.sort_by(|a, b| Collation::Bytes.cmp(a.as_bytes(), b.as_bytes()));
+        assert_eq!(words, ["Apple", "Cherry", "_x", "a", "apple", "banana"]);
+
+        // Non-ASCII bytes aren't touched.
+        assert_eq!(ascii_caseless_cmp("É", "é"), "É".cmp("é"));
+        assert_eq!(ascii_caseless_cmp("same", "same"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        let mut v = [
+            "file10", "file9", "file1", "file", "file01", "x2y10", "x2y9", "x10y1", "a",
+        ];
+        v.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            v,
+            ["a", "file", "file01", "file1", "file9", "file10", "x2y9", "x2y10", "x10y1"]
+        );
+
+        // Longer than any integer type.
+        assert_eq!(
+            natural_cmp("v123456789012345678901234567890", "v99999999999999999999"),
+            Ordering::Greater
+        );
+        assert_eq!(natural_cmp("007", "7"), Ordering::Less);
+        assert_eq!(natural_cmp("7", "007"), Ordering::Greater);
+        assert_eq!(natural_cmp("", ""), Ordering::Equal);
+        assert_eq!(natural_cmp("", "0"), Ordering::Less);
+
+        let mut v = ["IMG_10.png", "img_2.png", "Img_1.png"];
+        v.sort_by(|a, b| natural_cmp_with(a, b, Collation::IgnoreAsciiCase));
+        assert_eq!(v, ["Img_1.png", "img_2.png", "IMG_10.png"]);
+    }
+
+    #[test]
+    fn test_natural_total_order() {
+        // Antisymmetric and transitive over a set with every kind of tie.
+        let v = [
+            "", "0", "00", "1", "01", "a", "A", "a1", "a01", "A1", "a1b", "a10", "a9", "b", "1a",
+        ];
+        for x in v {
+            for y in v {
+                let xy = natural_cmp_with(x, y, Collation::IgnoreAsciiCase);
+                assert_eq!(
+                    xy,
+                    natural_cmp_with(y, x, Collation::IgnoreAsciiCase).reverse()
+                );
+                assert_eq!(xy == Ordering::Equal, x == y, "{x:?} {y:?}");
+                for z in v {
+                    let yz = natural_cmp_with(y, z, Collation::IgnoreAsciiCase);
+                    if xy == yz && xy != Ordering::Equal {
+                        assert_eq!(natural_cmp_with(x, z, Collation::IgnoreAsciiCase), xy);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use crate::radix_sort::apply_permutation;
+use crate::{CountingSort, SortError, Sorter};
+
+/// Counting sort on plain `i32`s, negative ones included. Sorts through
+/// [`CountingSort`], so a key range too wide to count is radix sorted.
+pub fn counting_sort(mut input: Vec<i32>) -> Vec<i32> {
+    CountingSort.sort(&mut input);
+
+    input
+}
+
+/// Stable counting sort on a `usize` key pulled out of each element.
+/// O(n + max key) time and space.
+///
+/// Returns `SortError::KeyRangeTooLarge` instead of panicking when the
+/// largest key needs more counters than can be allocated.
+pub fn counting_sort_by_key<T, F>(s: &mut [T], key: F) -> Result<(), SortError>
+where
+    F: Fn(&T) -> usize,
+{
+    let keys: Vec<usize> = s.iter().map(key).collect();
+    let Some(&max) = keys.iter().max() else {
+        return Ok(());
+    };
+
+    let range = max
+        .checked_add(1)
+        .ok_or(SortError::KeyRangeTooLarge(max as u128 + 1))?;
+    counting_permute(s, &keys, range)
+}
+
+/// Stable counting sort on a signed key. Keys are offset by the smallest
+/// one, so it's O(n + max - min) and negative keys are fine.
+///
+/// Returns `SortError::KeyRangeTooLarge` when `max - min + 1` counters
+/// can't be allocated.
+pub fn counting_sort_by_signed_key<T, F>(s: &mut [T], key: F) -> Result<(), SortError>
+where
+    F: Fn(&T) -> i64,
+{
+    let keys: Vec<i64> = s.iter().map(key).collect();
+    let (Some(&min), Some(&max)) = (keys.iter().min(), keys.iter().max()) else {
+        return Ok(());
+    };
+
+    let span = (max as i128 - min as i128 + 1) as u128;
+    let range = usize::try_from(span).map_err(|_| SortError::KeyRangeTooLarge(span))?;
+    // Both fit now that the span does.
+    let keys: Vec<usize> = keys.iter().map(|&k| k.abs_diff(min) as usize).collect();
+
+    counting_permute(s, &keys, range)
+}
+
+// Counts keys in 0..range and moves s[i] to its stable sorted position.
+fn counting_permute<T>(s: &mut [T], keys: &[usize], range: usize) -> Result<(), SortError> {
+    let mut counts: Vec<usize> = Vec::new();
+    counts
+        .try_reserve_exact(range)
+        .map_err(|_| SortError::KeyRangeTooLarge(range as u128))?;
+    counts.resize(range, 0);
+
+    keys.iter().for_each(|&k| counts[k] += 1);
+
+    // Turn the counts into the first slot for each key.
+    let mut next = 0;
+    for c in counts.iter_mut() {
+        let count = *c;
+        *c = next;
+        next += count;
+    }
+
+    // perm[slot] is the index of the element that belongs there.
+    let mut perm = vec![0; keys.len()];
+    for (i, &k) in keys.iter().enumerate() {
+        perm[counts[k]] = i;
+        counts[k] += 1;
+    }
+
+    apply_permutation(s, &perm);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+    use crate::{check_sort, make_one};
+
+    #[test]
+    fn test_counting_sort() {
+        for i in 5..1000 {
+            let v = make_one(i, i * 2);
+            let got = counting_sort(v.clone());
+            assert_eq!(check_sort(&v, &got), Ok(()));
+        }
+
+        let v = vec![3, -7, 0, i32::MIN, -7, 12];
+        assert_eq!(counting_sort(v), [i32::MIN, -7, -7, 0, 3, 12]);
+    }
+
+    #[test]
+    fn test_counting_sort_by_key() {
+        for i in 0..300 {
+            let mut v: Vec<(usize, usize)> = make_one(i, 20)
+                .into_iter()
+                .enumerate()
+                .map(|(idx, k)| (k as usize, idx))
+                .collect();
+            let mut want = v.clone();
+            want.sort_by_key(|p| p.0);
+
+            counting_sort_by_key(&mut v, |p| p.0).expect("failed to sort");
+            assert_eq!(v, want);
+        }
+    }
+
+    #[test]
+    fn test_counting_sort_by_signed_key() {
+        for i in 0..300 {
+            let mut v: Vec<(i32, usize)> = make_one(i, 200)
+                .into_iter()
+                .enumerate()
+                .map(|(idx, k)| (k - 100, idx))
+                .collect();
+            let mut want = v.clone();
+            want.sort_by_key(|p| p.0);
+
+            counting_sort_by_signed_key(&mut v, |p| p.0 as i64).expect("failed to sort");
+            assert_eq!(v, want);
+        }
+
+        let mut v = vec![i64::MAX, i64::MAX - 3, i64::MAX - 1];
+        counting_sort_by_signed_key(&mut v, |&x| x).expect("failed to sort");
+        assert_eq!(v, [i64::MAX - 3, i64::MAX - 1, i64::MAX]);
+    }
+
+    #[test]
+    fn test_counting_sort_range_too_large() {
+        let mut v = vec![3, usize::MAX, 7];
+        assert_eq!(
+            counting_sort_by_key(&mut v, |&x| x),
+            Err(SortError::KeyRangeTooLarge(1 << 64))
+        );
+        assert_eq!(v, [3, usize::MAX, 7]);
+
+        let mut v = vec![0, 1 << 60];
+        assert!(counting_sort_by_key(&mut v, |&x| x).is_err());
+
+        let mut v = vec![i64::MIN, 0, i64::MAX];
+        assert_eq!(
+            counting_sort_by_signed_key(&mut v, |&x| x),
+            Err(SortError::KeyRangeTooLarge(1 << 64))
+        );
+
+        let mut empty: Vec<i64> = vec![];
+        assert_eq!(counting_sort_by_signed_key(&mut empty, |&x| x), Ok(()));
+    }
+}
@@ -0,0 +1,317 @@
+//! Input distributions for testing and benchmarking sorts. Uniformly
+//! random input is the easy case for most of them; these are the shapes
+//! that show where each one does badly.
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::{introsort_by, Prng};
+
+/// A family of inputs. [`generate`](Self::generate) makes one of length
+/// `n`, the same every time for the same seed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Distribution {
+    /// Uniform over `0..=n`.
+    Random,
+    /// `0..n` in order.
+    Sorted,
+    /// `0..n` backwards.
+    Reversed,
+    /// Sorted, then this many random pairs swapped.
+    NearlySorted { swaps: usize },
+    /// This many ascending runs of equal length, each starting from 0.
+    Sawtooth { teeth: usize },
+    /// Ascending to the middle then descending, like organ pipes.
+    OrganPipe,
+    /// Uniform over this many distinct values.
+    FewUnique { values: usize },
+    /// Every element the same.
+    AllEqual,
+    /// Values from `1..=values`, value k turning up in proportion to
+    /// `1 / k^exponent`, so a few values make up most of the input.
+    Zipf { values: usize, exponent: f64 },
+    /// An input built by [`antiqsort`] to drive [`crate::introsort`] to its
+    /// depth limit. Doesn't depend on the seed.
+    Killer,
+}
+
+impl Distribution {
+    /// Every family, with parameters that suit sizes from thousands up.
+    pub fn all() -> [Distribution; 10] {
+        [
+            Distribution::Random,
+            Distribution::Sorted,
+            Distribution::Reversed,
+            Distribution::NearlySorted { swaps: 16 },
+            Distribution::Sawtooth { teeth: 16 },
+            Distribution::OrganPipe,
+            Distribution::FewUnique { values: 16 },
+            Distribution::AllEqual,
+            Distribution::Zipf {
+                values: 1000,
+                exponent: 1.0,
+            },
+            Distribution::Killer,
+        ]
+    }
+
+    /// A vec of length `n` from this family.
+    ///
+    /// Panics if `n` is `i32::MAX` or more, since values go up to `n`.
+    pub fn generate(&self, n: usize, seed: u64) -> Vec<i32> {
+        let top = i32::try_from(n)
+            .ok()
+            .filter(|&top| top < i32::MAX)
+            .unwrap_or_else(|| panic!("can't generate {n} values, the most is {}", i32::MAX - 1));
+        let mut prng = Prng::from_seed(seed);
+
+        match *self {
+            Distribution::Random => (0..n).map(|_| prng.next_i32(0, top + 1)).collect(),
+            Distribution::Sorted => (0..top).collect(),
+            Distribution::Reversed => (0..top).rev().collect(),
+            Distribution::NearlySorted { swaps } => {
+                let mut v: Vec<i32> = (0..top).collect();
+                if n > 1 {
+                    for _ in 0..swaps {
+                        v.swap(prng.below(n), prng.below(n));
+                    }
+                }
+                v
+            }
+            Distribution::Sawtooth { teeth } => {
+                let tooth = n.div_ceil(teeth.max(1)).max(1);
+                (0..n).map(|i| (i % tooth) as i32).collect()
+            }
+            Distribution::OrganPipe => (0..n).map(|i| i.min(n - 1 - i) as i32).collect(),
+            Distribution::FewUnique { values } => {
+                let values = values.clamp(1, i32::MAX as usize) as i32;
+                (0..n).map(|_| prng.next_i32(0, values)).collect()
+            }
+            Distribution::AllEqual => vec![0; n],
+            Distribution::Zipf { values, exponent } => zipf(&mut prng, n, values, exponent),
+            Distribution::Killer => antiqsort(n, |s, cmp| introsort_by(s, cmp)),
+        }
+    }
+}
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Distribution::Random => write!(f, "random"),
+            Distribution::Sorted => write!(f, "sorted"),
+            Distribution::Reversed => write!(f, "reversed"),
+            Distribution::NearlySorted { swaps } => write!(f, "nearly_sorted({swaps})"),
+            Distribution::Sawtooth { teeth } => write!(f, "sawtooth({teeth})"),
+            Distribution::OrganPipe => write!(f, "organ_pipe"),
+            Distribution::FewUnique { values } => write!(f, "few_unique({values})"),
+            Distribution::AllEqual => write!(f, "all_equal"),
+            Distribution::Zipf { values, exponent } => write!(f, "zipf({values}, {exponent})"),
+            Distribution::Killer => write!(f, "killer"),
+        }
+    }
+}
+
+// Samples by binary searching the cumulative weights, so O(values) to set
+// up and O(log values) per sample.
+fn zipf(prng: &mut Prng, n: usize, values: usize, exponent: f64) -> Vec<i32> {
+    let mut cumulative = Vec::with_capacity(values.max(1));
+    let mut total = 0.0;
+    for k in 1..=values.max(1) {
+        total += (k as f64).powf(-exponent);
+        cumulative.push(total);
+    }
+
+    (0..n)
+        .map(|_| {
+            let u = prng.next_f64() * total;
+            let k = cumulative
+                .partition_point(|&c| c <= u)
+                .min(cumulative.len() - 1);
+            k as i32 + 1
+        })
+        .collect()
+}
+
+/// Builds an input that makes `sort` do as many comparisons as it can,
+/// with McIlroy's adversary from "A Killer Adversary for Quicksort" (1999).
+///
+/// `sort` is run once on the indexes `0..n` with a comparator that decides
+/// values as it goes: everything starts as "gas", bigger than any value
+/// yet, and an element only gets a fixed value when it's compared against
+/// another gas element. Whichever looks like the pivot stays gas the
+/// longest, so every partition comes out as lopsided as possible. Any
+/// quicksort that picks pivots by comparing elements goes quadratic on the
+/// result (or, for introsort, hits its depth limit).
+///
+/// `sort` must be deterministic and sort only by the comparator it's given.
+pub fn antiqsort<F>(n: usize, sort: F) -> Vec<i32>
+where
+    F: FnOnce(&mut [usize], &mut dyn FnMut(&usize, &usize) -> Ordering),
+{
+    struct State {
+        vals: Vec<usize>,
+        gas: usize,
+        solid: usize,
+        candidate: usize,
+    }
+
+    impl State {
+        fn freeze(&mut self, i: usize) {
+            self.vals[i] = self.solid;
+            self.solid += 1;
+        }
+    }
+
+    let state = RefCell::new(State {
+        vals: vec![n; n],
+        gas: n,
+        solid: 0,
+        candidate: 0,
+    });
+
+    let mut cmp = |&x: &usize, &y: &usize| {
+        let mut st = state.borrow_mut();
+        let gas = st.gas;
+        if st.vals[x] == gas && st.vals[y] == gas {
+            if x == st.candidate {
+                st.freeze(x);
+            } else {
+                st.freeze(y);
+            }
+        }
+
+        if st.vals[x] == gas {
+            st.candidate = x;
+        } else if st.vals[y] == gas {
+            st.candidate = y;
+        }
+
+        st.vals[x].cmp(&st.vals[y])
+    };
+
+    let mut idx: Vec<usize> = (0..n).collect();
+    sort(&mut idx, &mut cmp);
+
+    // Whatever is still gas never mattered; give it the biggest values.
+    let mut st = state.into_inner();
+    for i in 0..n {
+        if st.vals[i] == st.gas {
+            st.freeze(i);
+        }
+    }
+
+    st.vals.into_iter().map(|v| v as i32).collect()
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+    use crate::{check_sorted, introsort, quicksort_by};
+
+    #[test]
+    #[should_panic(expected = "can't generate 2147483647 values")]
+    fn test_too_long() {
+        // Values go up to n, so i32::MAX is one too many.
+        Distribution::Sorted.generate(i32::MAX as usize, 0);
+    }
+
+    #[test]
+    fn test_generate() {
+        let n = 1000;
+        for dist in Distribution::all() {
+            let v = dist.generate(n, 7);
+            assert_eq!(v.len(), n, "{dist}");
+            assert_eq!(v, dist.generate(n, 7), "{dist} isn't repeatable");
+            assert!(dist.generate(0, 7).is_empty(), "{dist}");
+            assert_eq!(dist.generate(1, 7).len(), 1, "{dist}");
+
+            let mut sorted = v.clone();
+            introsort(&mut sorted);
+            assert!(check_sorted(&sorted), "{dist}");
+        }
+
+        let random = Distribution::Random;
+        assert_ne!(random.generate(n, 1), random.generate(n, 2));
+    }
+
+    #[test]
+    fn test_shapes() {
+        let n = 100;
+        let sorted: Vec<i32> = (0..n).collect();
+        assert_eq!(Distribution::Sorted.generate(100, 0), sorted);
+        let reversed: Vec<i32> = (0..n).rev().collect();
+        assert_eq!(Distribution::Reversed.generate(100, 0), reversed);
+        assert_eq!(Distribution::AllEqual.generate(100, 0), vec![0; 100]);
+
+        let nearly = Distribution::NearlySorted { swaps: 3 }.generate(100, 0);
+        let moved = nearly.iter().zip(&sorted).filter(|(a, b)| a != b).count();
+        assert!(moved <= 6, "{moved}");
+
+        let saw = Distribution::Sawtooth { teeth: 4 }.generate(100, 0);
+        assert_eq!(saw.windows(2).filter(|w| w[0] > w[1]).count(), 3);
+        assert_eq!(saw[..25], sorted[..25]);
+
+        let pipe = Distribution::OrganPipe.generate(9, 0);
+        assert_eq!(pipe, [0, 1, 2, 3, 4, 3, 2, 1, 0]);
+
+        let few = Distribution::FewUnique { values: 5 }.generate(1000, 0);
+        assert!(few.iter().all(|x| (0..5).contains(x)));
+        assert!((0..5).all(|x| few.contains(&x)));
+    }
+
+    #[test]
+    fn test_zipf() {
+        let v = Distribution::Zipf {
+            values: 100,
+            exponent: 1.0,
+        }
+        .generate(100_000, 3);
+        assert!(v.iter().all(|x| (1..=100).contains(x)));
+
+        // P(1) = 1 / H(100), about 0.193, and P(2) is half that.
+        let ones = v.iter().filter(|&&x| x == 1).count() as f64 / v.len() as f64;
+        let twos = v.iter().filter(|&&x| x == 2).count() as f64 / v.len() as f64;
+        assert!((ones - 0.193).abs() < 0.01, "{ones}");
+        assert!((ones / twos - 2.0).abs() < 0.1, "{}", ones / twos);
+    }
+
+    fn count_quicksort(v: &[i32]) -> usize {
+        let mut v = v.to_vec();
+        let mut count = 0;
+        quicksort_by(&mut v, |a, b| {
+            count += 1;
+            a.cmp(b)
+        });
+        count
+    }
+
+    #[test]
+    fn test_antiqsort() {
+        // Against the crate's quicksort, the killer is about as bad as it
+        // gets: nearly every partition peels off one element.
+        let n = 2000;
+        let killer = antiqsort(n, |s, cmp| quicksort_by(s, cmp));
+        let mut check = killer.clone();
+        check.sort();
+        assert_eq!(check, (0..n as i32).collect::<Vec<_>>());
+        let count = count_quicksort(&killer);
+        assert!(count > n * (n - 1) / 2 * 99 / 100, "{count}");
+
+        // Against introsort it takes several times the comparisons of a
+        // random input, as the depth limit keeps kicking in.
+        let count_introsort = |v: &[i32]| {
+            let mut v = v.to_vec();
+            let mut count = 0;
+            introsort_by(&mut v, |a, b| {
+                count += 1;
+                a.cmp(b)
+            });
+            count
+        };
+        let n = 20_000;
+        let killer = Distribution::Killer.generate(n, 0);
+        let random = Distribution::Random.generate(n, 0);
+        let (bad, good) = (count_introsort(&killer), count_introsort(&random));
+        assert!(bad > 2 * good, "killer {bad}, random {good}");
+    }
+}
@@ -0,0 +1,21 @@
+use std::fmt::{Display, Error, Formatter};
+
+/// Errors from the sorts that can fail rather than panic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SortError {
+    /// Counting sort would need this many counters, more than can be
+    /// allocated.
+    KeyRangeTooLarge(u128),
+}
+
+impl Display for SortError {
+    fn fmt(&self, f: &mut Formatter) -> std::result::Result<(), Error> {
+        match self {
+            SortError::KeyRangeTooLarge(n) => {
+                write!(f, "a key range of {n} values is too large to count")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SortError {}
@@ -0,0 +1,472 @@
+//! External merge sort, for files bigger than memory.
+//!
+//! The input is read in chunks that fit in [`ExternalSortConfig::memory`],
+//! each chunk is sorted and spilled to a temporary run file, and then the
+//! runs are k-way merged through a min [`Heap`] into the output. When there
+//! are more runs than [`ExternalSortConfig::fan_in`] they're merged in
+//! several passes so only that many files are open at once.
+//!
+//! Every failure is an I/O error. Malformed input, like invalid UTF-8 in a
+//! text file or a binary file that isn't a whole number of records, comes
+//! back as `io::ErrorKind::InvalidData`.
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{introsort, Heap, HeapKind};
+
+/// A record type that can be read from and written to a sort file.
+pub trait Record: Ord + Sized {
+    /// Reads the next record, or `None` at a clean end of file.
+    fn read_from<R: BufRead>(r: &mut R) -> io::Result<Option<Self>>;
+
+    fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()>;
+
+    /// Heap memory the record owns on top of `size_of::<Self>()`, for the
+    /// memory budget.
+    fn heap_size(&self) -> usize {
+        0
+    }
+}
+
+/// Newline delimited text. A `\n` or `\r\n` line ending is stripped on read
+/// and `\n` written back, so CRLF input comes out with LF endings and a
+/// last line without one gains one. Lines compare by bytes.
+impl Record for String {
+    fn read_from<R: BufRead>(r: &mut R) -> io::Result<Option<Self>> {
+        let mut line = String::new();
+        if r.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+
+        Ok(Some(line))
+    }
+
+    fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(self.as_bytes())?;
+        w.write_all(b"\n")
+    }
+
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+// Fixed width little-endian binary records.
+macro_rules! impl_binary_record {
+    ($($t:ty),*) => {$(
+        impl Record for $t {
+            fn read_from<R: BufRead>(r: &mut R) -> io::Result<Option<Self>> {
+                let mut bytes = [0u8; std::mem::size_of::<$t>()];
+                let mut filled = 0;
+                while filled < bytes.len() {
+                    match r.read(&mut bytes[filled..])? {
+                        0 if filled == 0 => return Ok(None),
+                        0 => {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                format!("file ends partway through a {}", stringify!($t)),
+                            ))
+                        }
+                        n => filled += n,
+                    }
+                }
+
+                Ok(Some(<$t>::from_le_bytes(bytes)))
+            }
+
+            fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+                w.write_all(&self.to_le_bytes())
+            }
+        }
+    )*};
+}
+
+impl_binary_record!(i32, u32, i64, u64);
+
+/// Limits for an external sort.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExternalSortConfig {
+    /// Roughly how many bytes of records to hold in memory per run.
+    pub memory: usize,
+    /// The most runs to merge at once, which is also the most run files
+    /// open at once. At least 2.
+    pub fan_in: usize,
+    /// Where run files go. They're removed when the sort finishes or fails.
+    pub temp_dir: PathBuf,
+}
+
+impl Default for ExternalSortConfig {
+    /// 64 MiB runs, merged 64 at a time, in the system temp directory.
+    fn default() -> Self {
+        Self {
+            memory: 64 << 20,
+            fan_in: 64,
+            temp_dir: std::env::temp_dir(),
+        }
+    }
+}
+
+/// What an external sort did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExternalSortSummary {
+    pub records: u64,
+    /// Sorted runs spilled to disk. 0 when everything fit in one run.
+    pub runs: usize,
+    /// Times the data was read back and merged.
+    pub merge_passes: usize,
+}
+
+/// Sorts the records in the file `input` into the file `output`, which may
+/// be the same path.
+pub fn external_sort<R: Record>(
+    input: &Path,
+    output: &Path,
+    cfg: &ExternalSortConfig,
+) -> io::Result<ExternalSortSummary> {
+    let reader = BufReader::new(File::open(input)?);
+    external_sort_reader::<R, _>(reader, output, cfg)
+}
+
+/// Like [`external_sort`], but reading from any `BufRead`, e.g. stdin.
+pub fn external_sort_reader<R: Record, B: BufRead>(
+    mut reader: B,
+    output: &Path,
+    cfg: &ExternalSortConfig,
+) -> io::Result<ExternalSortSummary> {
+    if cfg.fan_in < 2 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "fan_in must be at least 2",
+        ));
+    }
+
+    let temp = TempDir::new(&cfg.temp_dir)?;
+    let mut summary = ExternalSortSummary::default();
+    let mut runs: Vec<PathBuf> = Vec::new();
+
+    loop {
+        let (mut chunk, done) = read_chunk::<R, _>(&mut reader, cfg.memory)?;
+        summary.records += chunk.len() as u64;
+        introsort(&mut chunk);
+
+        // The whole input fit in one chunk, so skip the disk.
+        if done && runs.is_empty() {
+            write_records(output, &chunk)?;
+            return Ok(summary);
+        }
+
+        if !chunk.is_empty() {
+            let path = temp.file();
+            write_records(&path, &chunk)?;
+            runs.push(path);
+        }
+        if done {
+            break;
+        }
+    }
+    summary.runs = runs.len();
+
+    // Merge groups of fan_in runs into longer runs until one pass is enough.
+    while runs.len() > cfg.fan_in {
+        let mut merged = Vec::with_capacity(runs.len().div_ceil(cfg.fan_in));
+        for group in runs.chunks(cfg.fan_in) {
+            let path = temp.file();
+            merge_files::<R>(group, &path)?;
+            group.iter().try_for_each(fs::remove_file)?;
+            merged.push(path);
+        }
+        runs = merged;
+        summary.merge_passes += 1;
+    }
+
+    merge_files::<R>(&runs, output)?;
+    summary.merge_passes += 1;
+
+    Ok(summary)
+}
+
+// Reads records until they'd pass `memory` bytes, growing the chunk by
+// hand so its capacity stays within that too. Always takes at least one
+// record so a huge line can't stall the sort. The flag is true at end of
+// input.
+fn read_chunk<R: Record, B: BufRead>(reader: &mut B, memory: usize) -> io::Result<(Vec<R>, bool)> {
+    let size = std::mem::size_of::<R>().max(1);
+    let mut chunk: Vec<R> = Vec::new();
+    let mut heap = 0;
+
+    while chunk.is_empty() || chunk.len() * size + heap < memory {
+        let Some(rec) = R::read_from(reader)? else {
+            return Ok((chunk, true));
+        };
+        heap += rec.heap_size();
+
+        // Doubling could take the chunk to twice the budget.
+        if chunk.len() == chunk.capacity() {
+            let room = memory.saturating_sub(chunk.capacity() * size + heap) / size;
+            chunk.reserve_exact(chunk.len().min(room).max(1));
+        }
+        chunk.push(rec);
+    }
+
+    Ok((chunk, false))
+}
+
+fn write_records<R: Record>(path: &Path, records: &[R]) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    records.iter().try_for_each(|r| r.write_to(&mut w))?;
+    w.flush()
+}
+
+// K-way merges sorted files into `output`. The heap holds the next record
+// of each file, tagged with the file's index so ties come out in file
+// order and the merge is stable.
+fn merge_files<R: Record>(inputs: &[PathBuf], output: &Path) -> io::Result<()> {
+    let mut readers = inputs
+        .iter()
+        .map(|p| File::open(p).map(BufReader::new))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let mut heap = Heap::with_arity(4, HeapKind::Min);
+    for (i, r) in readers.iter_mut().enumerate() {
+        if let Some(rec) = R::read_from(r)? {
+            heap.push((rec, i));
+        }
+    }
+
+    let mut w = BufWriter::new(File::create(output)?);
+    while let Some((rec, i)) = heap.pop() {
+        rec.write_to(&mut w)?;
+        if let Some(next) = R::read_from(&mut readers[i])? {
+            heap.push((next, i));
+        }
+    }
+
+    w.flush()
+}
+
+// A uniquely named directory that's removed, contents and all, on drop.
+struct TempDir {
+    path: PathBuf,
+    files: AtomicUsize,
+}
+
+impl TempDir {
+    fn new(parent: &Path) -> io::Result<Self> {
+        static DIRS: AtomicUsize = AtomicUsize::new(0);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.subsec_nanos());
+
+        loop {
+            let name = format!(
+                "sorting-{}-{}-{}",
+                std::process::id(),
+                nanos,
+                DIRS.fetch_add(1, Ordering::Relaxed)
+            );
+            let path = parent.join(name);
+            match fs::create_dir(&path) {
+                Ok(()) => {
+                    return Ok(Self {
+                        path,
+                        files: AtomicUsize::new(0),
+                    })
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // A fresh path inside the directory.
+    fn file(&self) -> PathBuf {
+        let n = self.files.fetch_add(1, Ordering::Relaxed);
+        self.path.join(format!("run-{n}"))
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+    use crate::{make_one, Prng};
+
+    fn read_all<R: Record>(path: &Path) -> Vec<R> {
+        let mut r = BufReader::new(File::open(path).expect("failed to open output"));
+        let mut out = vec![];
+        while let Some(rec) = R::read_from(&mut r).expect("failed to read output") {
+            out.push(rec);
+        }
+        out
+    }
+
+    fn config(dir: &TempDir, memory: usize, fan_in: usize) -> ExternalSortConfig {
+        ExternalSortConfig {
+            memory,
+            fan_in,
+            temp_dir: dir.path.clone(),
+        }
+    }
+
+    #[test]
+    fn test_text_lines() {
+        let dir = TempDir::new(&std::env::temp_dir()).expect("failed to make temp dir");
+        let input = dir.file();
+        let output = dir.file();
+
+        let mut prng = Prng::new();
+        let mut lines: Vec<String> = (0..5_000)
+            .map(|_| {
+                let len = prng.next_i32(0, 30) as usize;
+                (0..len)
+                    .map(|_| (b'a' + prng.next_i32(0, 26) as u8) as char)
+                    .collect()
+            })
+            .collect();
+        write_records(&input, &lines).expect("failed to write input");
+
+        // Tiny runs and a fan-in of 4 force several merge passes.
+        let got = external_sort::<String>(&input, &output, &config(&dir, 4096, 4))
+            .expect("failed to sort");
+        assert_eq!(got.records, 5_000);
+        assert!(got.runs > 16, "{got:?}");
+        assert!(got.merge_passes > 1, "{got:?}");
+
+        lines.sort();
+        assert_eq!(read_all::<String>(&output), lines);
+    }
+
+    #[test]
+    fn test_read_chunk() {
+        // The chunk's capacity stays in the budget rather than doubling
+        // past it.
+        let bytes: Vec<u8> = (0..1000u32).flat_map(|x| x.to_le_bytes()).collect();
+        let mut r = io::Cursor::new(bytes);
+        let (chunk, done) = read_chunk::<u32, _>(&mut r, 1000).expect("failed to read");
+        assert!(!done);
+        assert_eq!(chunk.len(), 250);
+        assert_eq!(chunk.capacity(), 250);
+
+        let mut r = io::Cursor::new("b\r\na\r\n\r\nc\n\rd");
+        let (lines, done) = read_chunk::<String, _>(&mut r, 1 << 20).expect("failed to read");
+        assert!(done);
+        assert_eq!(lines, ["b", "a", "", "c", "\rd"]);
+        let mut r = io::Cursor::new("x\n".repeat(1000));
+        let (lines, _) = read_chunk::<String, _>(&mut r, 4096).expect("failed to read");
+        let used = lines.capacity() * std::mem::size_of::<String>()
+            + lines.iter().map(|l| l.capacity()).sum::<usize>();
+        assert!(used <= 4096 + 24, "{used}");
+    }
+
+    #[test]
+    fn test_binary_records() {
+        let dir = TempDir::new(&std::env::temp_dir()).expect("failed to make temp dir");
+        let input = dir.file();
+
+        let mut ints: Vec<i32> = make_one(20_000, 1_000_000)
+            .into_iter()
+            .map(|x| x - 500_000)
+            .collect();
+        write_records(&input, &ints).expect("failed to write input");
+
+        // Sorting a file onto itself is fine, the input is read first.
+        let got =
+            external_sort::<i32>(&input, &input, &config(&dir, 10_000, 8)).expect("failed to sort");
+        assert_eq!(got.runs, 8);
+        assert_eq!(got.merge_passes, 1);
+
+        ints.sort();
+        assert_eq!(read_all::<i32>(&input), ints);
+
+        let mut big: Vec<u64> = (0..10_000u64)
+            .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+            .collect();
+        write_records(&input, &big).expect("failed to write input");
+        external_sort::<u64>(&input, &input, &config(&dir, 8 * 1000, 3)).expect("failed to sort");
+
+        big.sort();
+        assert_eq!(read_all::<u64>(&input), big);
+    }
+
+    #[test]
+    fn test_fits_in_memory() {
+        let dir = TempDir::new(&std::env::temp_dir()).expect("failed to make temp dir");
+        let input = dir.file();
+        let output = dir.file();
+
+        write_records(&input, &[3u64, 1, 2]).expect("failed to write input");
+        let got = external_sort::<u64>(&input, &output, &ExternalSortConfig::default())
+            .expect("failed to sort");
+        assert_eq!(
+            got,
+            ExternalSortSummary {
+                records: 3,
+                runs: 0,
+                merge_passes: 0
+            }
+        );
+        assert_eq!(read_all::<u64>(&output), [1, 2, 3]);
+
+        let got = external_sort_reader::<String, _>(&b""[..], &output, &config(&dir, 1, 2))
+            .expect("failed to sort");
+        assert_eq!(got.records, 0);
+        assert!(read_all::<String>(&output).is_empty());
+    }
+
+    #[test]
+    fn test_bad_input() {
+        let dir = TempDir::new(&std::env::temp_dir()).expect("failed to make temp dir");
+        let input = dir.file();
+        let output = dir.file();
+
+        // Seven bytes isn't a whole number of u32s.
+        fs::write(&input, [0u8; 7]).expect("failed to write input");
+        let err = external_sort::<u32>(&input, &output, &config(&dir, 4, 2))
+            .expect_err("sorted a truncated file");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        fs::write(&input, b"ok\n\xff\xfe\n").expect("failed to write input");
+        let err = external_sort::<String>(&input, &output, &config(&dir, 4, 2))
+            .expect_err("sorted invalid utf-8");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let err = external_sort::<String>(&input, &output, &config(&dir, 4, 1))
+            .expect_err("sorted with a fan-in of 1");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let missing = dir.path.join("missing");
+        let err = external_sort::<String>(&missing, &output, &ExternalSortConfig::default())
+            .expect_err("sorted a missing file");
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_temp_files_removed() {
+        let dir = TempDir::new(&std::env::temp_dir()).expect("failed to make temp dir");
+        let input = dir.file();
+        let output = dir.path.join("out");
+        write_records(&input, &make_one(1_000, 100)).expect("failed to write input");
+
+        external_sort::<i32>(&input, &output, &config(&dir, 400, 2)).expect("failed to sort");
+
+        let left: Vec<_> = fs::read_dir(&dir.path)
+            .expect("failed to list temp dir")
+            .map(|e| e.expect("failed to read entry").file_name())
+            .collect();
+        assert_eq!(left.len(), 2, "{left:?}");
+    }
+}
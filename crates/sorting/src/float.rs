@@ -0,0 +1,191 @@
+//! Sorting floats, which have no `Ord` because NaN isn't equal to itself.
+//!
+//! [`Total`] wraps a float in the IEEE 754 total order, so every sort in
+//! the crate takes it: -NaN < -inf < ... < -0.0 < 0.0 < ... < inf < NaN.
+//! [`sort_floats`] radix sorts in that order and then moves NaNs to
+//! whichever end [`NanPlacement`] asks for, and [`float_cmp`] compares the
+//! same way for the `_by` sorts.
+use std::cmp::Ordering;
+
+use crate::{radix_sort, RadixKey};
+
+/// `f32` or `f64`.
+pub trait Float: RadixKey + PartialOrd {
+    fn is_nan(self) -> bool;
+
+    /// The IEEE 754 total order, as `f64::total_cmp`.
+    fn total_cmp(&self, other: &Self) -> Ordering;
+}
+
+macro_rules! impl_float {
+    ($($t:ty),*) => {$(
+        impl Float for $t {
+            fn is_nan(self) -> bool {
+                <$t>::is_nan(self)
+            }
+
+            fn total_cmp(&self, other: &Self) -> Ordering {
+                <$t>::total_cmp(self, other)
+            }
+        }
+    )*};
+}
+
+impl_float!(f32, f64);
+
+/// A float ordered by the IEEE 754 total order, so it can go through any
+/// sort. -0.0 sorts before 0.0, and NaNs with the sign bit set come first
+/// and the rest last.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(transparent)]
+pub struct Total<F>(pub F);
+
+impl<F: Float> PartialEq for Total<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<F: Float> Eq for Total<F> {}
+
+impl<F: Float> PartialOrd for Total<F> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<F: Float> Ord for Total<F> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl<F: Float> RadixKey for Total<F> {
+    const BYTES: usize = F::BYTES;
+
+    fn radix_byte(self, i: usize) -> u8 {
+        self.0.radix_byte(i)
+    }
+}
+
+/// Where NaNs go in a sorted slice of floats.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NanPlacement {
+    /// Before everything else.
+    First,
+    /// After everything else.
+    #[default]
+    Last,
+    /// Wherever the total order puts them: NaNs with the sign bit set
+    /// first, the rest last.
+    BySign,
+}
+
+/// Compares floats in the IEEE 754 total order, except that NaNs go where
+/// `nans` says. NaNs among themselves stay in the total order.
+pub fn float_cmp<F: Float>(a: &F, b: &F, nans: NanPlacement) -> Ordering {
+    match (nans, a.is_nan(), b.is_nan()) {
+        (NanPlacement::First, true, false) => Ordering::Less,
+        (NanPlacement::First, false, true) => Ordering::Greater,
+        (NanPlacement::Last, true, false) => Ordering::Greater,
+        (NanPlacement::Last, false, true) => Ordering::Less,
+        _ => a.total_cmp(b),
+    }
+}
+
+/// Sorts floats in the order of [`float_cmp`]. Radix sorts, so it's O(n)
+/// and stable.
+pub fn sort_floats<F: Float>(s: &mut [F], nans: NanPlacement) {
+    radix_sort(s);
+
+    // The total order leaves negative NaNs at the front and the rest at
+    // the back.
+    let front = s.iter().take_while(|x| x.is_nan()).count();
+    let back = s[front..].iter().rev().take_while(|x| x.is_nan()).count();
+    let end = s.len() - back;
+    match nans {
+        NanPlacement::First => s[front..].rotate_right(back),
+        NanPlacement::Last => s[..end].rotate_left(front),
+        NanPlacement::BySign => {}
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+    use crate::{introsort, merge_sort_by, Prng};
+
+    fn bits(s: &[f64]) -> Vec<u64> {
+        s.iter().map(|x| x.to_bits()).collect()
+    }
+
+    #[test]
+    fn test_sort_floats() {
+        let nan = f64::NAN;
+        let v = [
+            1.5,
+            -nan,
+            f64::INFINITY,
+            0.0,
+            nan,
+            -0.0,
+            f64::NEG_INFINITY,
+            -2.0,
+            f64::MIN_POSITIVE,
+        ];
+        let numbers = [
+            f64::NEG_INFINITY,
+            -2.0,
+            -0.0,
+            0.0,
+            f64::MIN_POSITIVE,
+            1.5,
+            f64::INFINITY,
+        ];
+
+        for (nans, want) in [
+            (NanPlacement::First, [&[-nan, nan][..], &numbers].concat()),
+            (NanPlacement::Last, [&numbers[..], &[-nan, nan]].concat()),
+            (
+                NanPlacement::BySign,
+                [&[-nan][..], &numbers, &[nan]].concat(),
+            ),
+        ] {
+            let mut got = v;
+            sort_floats(&mut got, nans);
+            // Compare bits, since NaN != NaN and -0.0 == 0.0.
+            assert_eq!(bits(&got), bits(&want), "{nans:?}");
+
+            let mut by_cmp = v;
+            merge_sort_by(&mut by_cmp, |a, b| float_cmp(a, b, nans));
+            assert_eq!(bits(&by_cmp), bits(&want), "{nans:?}");
+        }
+
+        let mut v = [2.5f32, f32::NAN, -1.0];
+        sort_floats(&mut v, NanPlacement::First);
+        assert!(v[0].is_nan());
+        assert_eq!(v[1..], [-1.0, 2.5]);
+    }
+
+    #[test]
+    fn test_total() {
+        let mut prng = Prng::from_seed(50);
+        let v: Vec<Total<f64>> = (0..1000)
+            .map(|_| Total(f64::from_bits(prng.next_u64())))
+            .collect();
+
+        let mut compared = v.clone();
+        introsort(&mut compared);
+        let mut radix = v.clone();
+        radix_sort(&mut radix);
+        let mut want = v.clone();
+        want.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let raw = |s: &[Total<f64>]| s.iter().map(|x| x.0.to_bits()).collect::<Vec<u64>>();
+        assert_eq!(raw(&compared), raw(&want));
+        assert_eq!(raw(&radix), raw(&want));
+
+        assert!(Total(f64::NAN) == Total(f64::NAN));
+        assert!(Total(-0.0) < Total(0.0));
+    }
+}
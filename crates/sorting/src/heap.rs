@@ -0,0 +1,565 @@
+//! A d-ary heap / priority queue with handles, and heapsort built on the
+//! same sift routines.
+//!
+//! Unlike `std::collections::BinaryHeap`, [`Heap`] can be a min or a max
+//! heap, can have any number of children per node, and hands out a
+//! [`Handle`] from every push so an entry can be re-prioritized or removed
+//! later, which is what Dijkstra or branch and bound need.
+use std::cmp::Ordering;
+
+use crate::{stats, Sorter};
+
+/// Which end of the order comes out of the heap first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeapKind {
+    Min,
+    Max,
+}
+
+impl HeapKind {
+    // True if `a` belongs above `b`.
+    fn before<T: Ord>(self, a: &T, b: &T) -> bool {
+        match self {
+            HeapKind::Min => a < b,
+            HeapKind::Max => a > b,
+        }
+    }
+}
+
+/// Refers to one pushed entry for as long as it's in the heap.
+///
+/// `Heap::from_vec` gives element `i` the handle `Handle(i)`, so dense ids
+/// like graph vertices can be used directly, and pushes number on from
+/// there. Like slab keys, a handle is reused once its entry is popped or
+/// removed, which keeps a long-lived heap from growing without bound; don't
+/// hold on to handles of entries that are gone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Handle(pub usize);
+
+// pos[] value for handles that are no longer in the heap.
+const GONE: usize = usize::MAX;
+
+/// A d-ary min or max heap. With an arity of 2 this is a binary heap.
+#[derive(Clone, Debug)]
+pub struct Heap<T> {
+    data: Vec<T>,
+    // ids[i] is the handle of data[i].
+    ids: Vec<usize>,
+    // pos[h] is the index of handle h in data, or GONE.
+    pos: Vec<usize>,
+    // Handles that are GONE, for reuse.
+    free: Vec<usize>,
+    arity: usize,
+    kind: HeapKind,
+}
+
+impl<T: Ord> Heap<T> {
+    /// An empty binary heap.
+    pub fn new(kind: HeapKind) -> Self {
+        Self::with_arity(2, kind)
+    }
+
+    /// An empty heap where each node has `arity` children. Wider heaps are
+    /// shallower, so pushes and `decrease_key` get cheaper while pops cost
+    /// more comparisons.
+    ///
+    /// Panics if `arity < 2`.
+    pub fn with_arity(arity: usize, kind: HeapKind) -> Self {
+        assert!(arity >= 2, "a heap needs at least two children per node");
+
+        Self {
+            data: Vec::new(),
+            ids: Vec::new(),
+            pos: Vec::new(),
+            free: Vec::new(),
+            arity,
+            kind,
+        }
+    }
+
+    /// Builds a heap from `v` in O(n). Element `i` gets `Handle(i)`.
+    ///
+    /// Panics if `arity < 2`.
+    pub fn from_vec(v: Vec<T>, arity: usize, kind: HeapKind) -> Self {
+        let mut heap = Self::with_arity(arity, kind);
+        heap.ids = (0..v.len()).collect();
+        heap.pos = (0..v.len()).collect();
+        heap.data = v;
+
+        if heap.data.len() > 1 {
+            for i in (0..=(heap.data.len() - 2) / arity).rev() {
+                heap.sift_down(i);
+            }
+        }
+
+        heap
+    }
+
+    /// Builds a heap from a copy of `s`, see [`Heap::from_vec`].
+    pub fn heapify(s: &[T], arity: usize, kind: HeapKind) -> Self
+    where
+        T: Clone,
+    {
+        Self::from_vec(s.to_vec(), arity, kind)
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    pub fn kind(&self) -> HeapKind {
+        self.kind
+    }
+
+    /// Adds `item`, returning a handle to it.
+    pub fn push(&mut self, item: T) -> Handle {
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.pos[id] = self.data.len();
+                id
+            }
+            None => {
+                self.pos.push(self.data.len());
+                self.pos.len() - 1
+            }
+        };
+        self.ids.push(id);
+        self.data.push(item);
+        self.sift_up(self.data.len() - 1);
+
+        Handle(id)
+    }
+
+    /// The smallest (min heap) or largest (max heap) item.
+    pub fn peek(&self) -> Option<&T> {
+        self.data.first()
+    }
+
+    /// Removes and returns the top item.
+    pub fn pop(&mut self) -> Option<T> {
+        self.pop_with_handle().map(|(_, item)| item)
+    }
+
+    /// Removes the top item and returns it along with its handle.
+    pub fn pop_with_handle(&mut self) -> Option<(Handle, T)> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        self.remove_at(0)
+    }
+
+    pub fn contains(&self, h: Handle) -> bool {
+        self.index_of(h).is_some()
+    }
+
+    /// The item behind `h`, if it's still in the heap.
+    pub fn get(&self, h: Handle) -> Option<&T> {
+        self.index_of(h).map(|i| &self.data[i])
+    }
+
+    /// Replaces the item behind `h`, moving it up or down as needed.
+    /// Returns the old item, or `None` if `h` is no longer in the heap.
+    pub fn update(&mut self, h: Handle, item: T) -> Option<T> {
+        let i = self.index_of(h)?;
+        let old = std::mem::replace(&mut self.data[i], item);
+        self.sift_up(i);
+        self.sift_down(self.pos[h.0]);
+
+        Some(old)
+    }
+
+    /// Moves `h` toward the top by giving it `item`, which has to sort
+    /// before or equal to its current item: a smaller key in a min heap,
+    /// a larger one in a max heap. Returns false and leaves the heap
+    /// alone if `h` is gone or `item` would move it the other way.
+    pub fn decrease_key(&mut self, h: Handle, item: T) -> bool {
+        let Some(i) = self.index_of(h) else {
+            return false;
+        };
+        if self.kind.before(&self.data[i], &item) {
+            return false;
+        }
+
+        self.data[i] = item;
+        self.sift_up(i);
+
+        true
+    }
+
+    /// Removes the item behind `h` from anywhere in the heap.
+    pub fn remove(&mut self, h: Handle) -> Option<T> {
+        let i = self.index_of(h)?;
+        self.remove_at(i).map(|(_, item)| item)
+    }
+
+    /// Drains the heap in pop order.
+    pub fn into_sorted_vec(mut self) -> Vec<T> {
+        let mut out = Vec::with_capacity(self.len());
+        while let Some(item) = self.pop() {
+            out.push(item);
+        }
+
+        out
+    }
+
+    fn index_of(&self, h: Handle) -> Option<usize> {
+        self.pos.get(h.0).copied().filter(|&i| i != GONE)
+    }
+
+    fn remove_at(&mut self, i: usize) -> Option<(Handle, T)> {
+        let last = self.data.len() - 1;
+        self.swap(i, last);
+
+        let item = self.data.pop()?;
+        let id = self.ids.pop()?;
+        self.pos[id] = GONE;
+        self.free.push(id);
+
+        if i < self.data.len() {
+            self.sift_up(i);
+            self.sift_down(self.pos[self.ids[i]]);
+        }
+
+        Some((Handle(id), item))
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        self.data.swap(i, j);
+        self.ids.swap(i, j);
+        self.pos[self.ids[i]] = i;
+        self.pos[self.ids[j]] = j;
+    }
+
+    fn sift_up(&mut self, i: usize) {
+        let kind = self.kind;
+        let (ids, pos) = (&mut self.ids, &mut self.pos);
+        sift_up(
+            &mut self.data,
+            i,
+            self.arity,
+            &mut |a, b| kind.before(a, b),
+            &mut |i, j| {
+                ids.swap(i, j);
+                pos[ids[i]] = i;
+                pos[ids[j]] = j;
+            },
+        );
+    }
+
+    fn sift_down(&mut self, i: usize) {
+        let kind = self.kind;
+        let (ids, pos) = (&mut self.ids, &mut self.pos);
+        sift_down(
+            &mut self.data,
+            i,
+            self.arity,
+            &mut |a, b| kind.before(a, b),
+            &mut |i, j| {
+                ids.swap(i, j);
+                pos[ids[i]] = i;
+                pos[ids[j]] = j;
+            },
+        );
+    }
+}
+
+// Moves s[i] up while it belongs before its parent. `on_swap` sees every
+// swap so the heap can keep its handle positions current.
+fn sift_up<T>(
+    s: &mut [T],
+    mut i: usize,
+    arity: usize,
+    before: &mut impl FnMut(&T, &T) -> bool,
+    on_swap: &mut impl FnMut(usize, usize),
+) {
+    while i > 0 {
+        let parent = (i - 1) / arity;
+        if !before(&s[i], &s[parent]) {
+            return;
+        }
+        stats::swap(s, i, parent);
+        on_swap(i, parent);
+        i = parent;
+    }
+}
+
+// Moves s[i] down while one of its children belongs before it.
+fn sift_down<T>(
+    s: &mut [T],
+    mut i: usize,
+    arity: usize,
+    before: &mut impl FnMut(&T, &T) -> bool,
+    on_swap: &mut impl FnMut(usize, usize),
+) {
+    loop {
+        let first = arity * i + 1;
+        if first >= s.len() {
+            return;
+        }
+
+        let mut best = first;
+        for child in first + 1..(first + arity).min(s.len()) {
+            if before(&s[child], &s[best]) {
+                best = child;
+            }
+        }
+        if !before(&s[best], &s[i]) {
+            return;
+        }
+
+        stats::swap(s, i, best);
+        on_swap(i, best);
+        i = best;
+    }
+}
+
+/// In-place heapsort. O(n log n) worst case, O(1) extra space, not stable.
+pub fn heapsort<T: Ord>(s: &mut [T]) {
+    heapsort_by(s, T::cmp);
+}
+
+/// Heapsort with a custom comparator.
+pub fn heapsort_by<T, F>(s: &mut [T], cmp: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    dary_heapsort_by(s, 2, cmp);
+}
+
+/// Heapsort on a heap with `arity` children per node. Four is often faster
+/// than two since the tree is half as deep and the children share a cache
+/// line.
+///
+/// Panics if `arity < 2`.
+pub fn dary_heapsort_by<T, F>(s: &mut [T], arity: usize, mut cmp: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    assert!(arity >= 2, "a heap needs at least two children per node");
+    if s.len() < 2 {
+        return;
+    }
+
+    // A max heap, so the largest element is swapped to the end each round.
+    let before = &mut |a: &T, b: &T| cmp(a, b) == Ordering::Greater;
+    let on_swap = &mut |_, _| {};
+
+    for i in (0..=(s.len() - 2) / arity).rev() {
+        sift_down(s, i, arity, before, on_swap);
+    }
+    for end in (1..s.len()).rev() {
+        stats::swap(s, 0, end);
+        sift_down(&mut s[..end], 0, arity, before, on_swap);
+    }
+}
+
+/// Binary heapsort, see [`heapsort`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Heapsort;
+
+impl<T: Ord> Sorter<T> for Heapsort {
+    fn name(&self) -> &'static str {
+        "heapsort"
+    }
+
+    fn sort(&self, v: &mut [T]) {
+        heapsort(v);
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+    use crate::make_one;
+
+    #[test]
+    fn test_push_pop() {
+        for arity in 2..6 {
+            for kind in [HeapKind::Min, HeapKind::Max] {
+                let v = make_one(1000, 200);
+                let mut want = v.clone();
+                want.sort();
+                if kind == HeapKind::Max {
+                    want.reverse();
+                }
+
+                let mut heap = Heap::with_arity(arity, kind);
+                v.into_iter().for_each(|x| {
+                    heap.push(x);
+                });
+                assert_eq!(heap.len(), 1000);
+                assert_eq!(heap.peek(), want.first());
+                assert_eq!(heap.into_sorted_vec(), want, "{arity} {kind:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_heapify() {
+        for arity in 2..6 {
+            let v = make_one(777, 1000);
+            let mut want = v.clone();
+            want.sort();
+
+            let heap = Heap::heapify(&v, arity, HeapKind::Min);
+            assert_eq!(heap.into_sorted_vec(), want);
+        }
+
+        let mut empty = Heap::<i32>::from_vec(vec![], 2, HeapKind::Max);
+        assert!(empty.is_empty());
+        assert_eq!(empty.pop(), None);
+        assert_eq!(empty.peek(), None);
+    }
+
+    #[test]
+    fn test_handles() {
+        let mut heap = Heap::new(HeapKind::Min);
+        let a = heap.push(50);
+        let b = heap.push(40);
+        let c = heap.push(30);
+        assert_eq!(heap.peek(), Some(&30));
+
+        assert!(heap.decrease_key(a, 10));
+        assert_eq!(heap.peek(), Some(&10));
+        // Moving a key away from the top isn't a decrease.
+        assert!(!heap.decrease_key(a, 60));
+        assert_eq!(heap.get(a), Some(&10));
+
+        assert_eq!(heap.update(a, 60), Some(10));
+        assert_eq!(heap.peek(), Some(&30));
+
+        assert_eq!(heap.remove(b), Some(40));
+        assert!(!heap.contains(b));
+        assert_eq!(heap.remove(b), None);
+        assert_eq!(heap.update(b, 1), None);
+
+        assert_eq!(heap.pop_with_handle(), Some((c, 30)));
+        assert_eq!(heap.pop_with_handle(), Some((a, 60)));
+        assert_eq!(heap.pop(), None);
+        assert!(!heap.decrease_key(c, 0));
+    }
+
+    #[test]
+    fn test_handles_reused() {
+        let mut heap = Heap::new(HeapKind::Min);
+        for i in 0..10_000 {
+            let h = heap.push(i);
+            assert_eq!(heap.pop_with_handle(), Some((h, i)));
+        }
+        assert_eq!(heap.pos.len(), 1);
+
+        let a = heap.push(1);
+        let b = heap.push(2);
+        heap.remove(a);
+        assert_eq!(heap.push(3), a);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_max_decrease_key() {
+        let mut heap = Heap::from_vec(vec![3, 1, 2], 3, HeapKind::Max);
+        assert!(heap.decrease_key(Handle(1), 5));
+        assert_eq!(heap.pop_with_handle(), Some((Handle(1), 5)));
+        assert_eq!(heap.pop(), Some(3));
+    }
+
+    #[test]
+    fn test_random_updates() {
+        // Mirror every operation on a plain vec and compare.
+        let mut prng = crate::Prng::new();
+        let mut heap = Heap::with_arity(3, HeapKind::Min);
+        let mut model: Vec<Option<i32>> = vec![];
+
+        for _ in 0..5_000 {
+            match prng.next_i32(0, 4) {
+                0 | 1 => {
+                    let x = prng.next_i32(0, 1000);
+                    let h = heap.push(x);
+                    if h.0 == model.len() {
+                        model.push(Some(x));
+                    } else {
+                        // Only handles of popped entries come back.
+                        assert_eq!(model[h.0], None);
+                        model[h.0] = Some(x);
+                    }
+                }
+                2 if !model.is_empty() => {
+                    let h = prng.next_i32(0, model.len() as i32) as usize;
+                    let x = prng.next_i32(0, 1000);
+                    assert_eq!(heap.update(Handle(h), x), model[h]);
+                    if model[h].is_some() {
+                        model[h] = Some(x);
+                    }
+                }
+                _ => {
+                    let want = model.iter().flatten().min().copied();
+                    let got = heap.pop_with_handle();
+                    assert_eq!(got.map(|(_, x)| x), want);
+                    if let Some((h, _)) = got {
+                        model[h.0] = None;
+                    }
+                }
+            }
+            assert_eq!(heap.len(), model.iter().flatten().count());
+        }
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        // 0 -> 1 (4), 0 -> 2 (1), 2 -> 1 (2), 1 -> 3 (1), 2 -> 3 (5)
+        let edges = [
+            vec![(1, 4), (2, 1)],
+            vec![(3, 1)],
+            vec![(1, 2), (3, 5)],
+            vec![],
+        ];
+        let mut dist = vec![u32::MAX; 4];
+        dist[0] = 0;
+
+        // Pushing in vertex order makes vertex v's handle Handle(v).
+        let mut heap = Heap::from_vec(dist.clone(), 4, HeapKind::Min);
+        while let Some((Handle(u), d)) = heap.pop_with_handle() {
+            for &(v, w) in &edges[u] {
+                if d + w < dist[v] {
+                    dist[v] = d + w;
+                    heap.decrease_key(Handle(v), dist[v]);
+                }
+            }
+        }
+
+        assert_eq!(dist, [0, 3, 1, 4]);
+    }
+
+    #[test]
+    fn test_heapsort() {
+        for i in 0..500 {
+            let v = make_one(i, i * 2 + 1);
+            let mut want = v.clone();
+            want.sort();
+
+            let mut got = v.clone();
+            heapsort(&mut got);
+            assert_eq!(got, want);
+
+            for arity in 3..6 {
+                let mut got = v.clone();
+                dary_heapsort_by(&mut got, arity, i32::cmp);
+                assert_eq!(got, want);
+            }
+
+            let mut got = v.clone();
+            heapsort_by(&mut got, |a, b| b.cmp(a));
+            want.reverse();
+            assert_eq!(got, want);
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! [Introsort](https://en.wikipedia.org/wiki/Introsort): quicksort that
+//! can't go quadratic.
+//!
+//! Plain `quicksort` picks the last element as its pivot, so sorted or
+//! all-equal input gives n deep recursion. This one
+//! - picks a median-of-three (ninther on big slices) pivot,
+//! - partitions three ways, so runs of equal keys are done in one pass,
+//! - insertion sorts short slices,
+//! - falls back to heapsort once the recursion gets deeper than 2 log2(n),
+//! - and only recurses into the smaller side, looping on the larger one, so
+//!   the stack stays O(log n).
+use std::cmp::Ordering;
+
+use crate::{heapsort_by, stats, Sorter};
+
+// Slices this short are faster to insertion sort.
+const INSERTION_CUTOFF: usize = 16;
+// Slices at least this long use Tukey's ninther instead of median-of-three.
+const NINTHER_CUTOFF: usize = 128;
+
+/// Sorts in place in O(n log n) worst case. Not stable.
+pub fn introsort<T: Ord>(s: &mut [T]) {
+    introsort_by(s, T::cmp);
+}
+
+/// Introsort with a custom comparator.
+pub fn introsort_by<T, F>(s: &mut [T], mut cmp: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let depth = 2 * (usize::BITS - s.len().leading_zeros());
+    introsort_loop(s, depth, &mut cmp);
+}
+
+/// Introsort on a key pulled out of each element.
+pub fn introsort_by_key<T, K, F>(s: &mut [T], mut key: F)
+where
+    K: Ord,
+    F: FnMut(&T) -> K,
+{
+    introsort_by(s, |a, b| key(a).cmp(&key(b)));
+}
+
+fn introsort_loop<T, F>(mut s: &mut [T], mut depth: u32, cmp: &mut F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let _depth = stats::enter();
+    loop {
+        if s.len() <= INSERTION_CUTOFF {
+            insertion_sort(s, cmp);
+            return;
+        }
+        if depth == 0 {
+            heapsort_by(s, &mut *cmp);
+            return;
+        }
+        depth -= 1;
+
+        let p = choose_pivot(s, cmp);
+        stats::swap(s, 0, p);
+        let (lt, gt) = partition3(s, cmp);
+
+        // Recurse into the smaller side and loop on the larger one.
+        let (left, rest) = s.split_at_mut(lt);
+        let right = &mut rest[gt - lt..];
+        if left.len() < right.len() {
+            introsort_loop(left, depth, cmp);
+            s = right;
+        } else {
+            introsort_loop(right, depth, cmp);
+            s = left;
+        }
+    }
+}
+
+// Index of the median of s[a], s[b] and s[c].
+pub(crate) fn median3<T, F>(s: &[T], a: usize, b: usize, c: usize, cmp: &mut F) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let ab = cmp(&s[a], &s[b]) == Ordering::Less;
+    let bc = cmp(&s[b], &s[c]) == Ordering::Less;
+    if ab == bc {
+        return b;
+    }
+
+    let ac = cmp(&s[a], &s[c]) == Ordering::Less;
+    if ab == ac {
+        c
+    } else {
+        a
+    }
+}
+
+pub(crate) fn choose_pivot<T, F>(s: &[T], cmp: &mut F) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let n = s.len();
+    let mid = n / 2;
+    if n < NINTHER_CUTOFF {
+        return median3(s, 0, mid, n - 1, cmp);
+    }
+
+    // Median of the medians of three groups of three.
+    let step = n / 8;
+    let a = median3(s, 0, step, 2 * step, cmp);
+    let b = median3(s, mid - step, mid, mid + step, cmp);
+    let c = median3(s, n - 1 - 2 * step, n - 1 - step, n - 1, cmp);
+
+    median3(s, a, b, c, cmp)
+}
+
+// Dijkstra's three-way partition around the pivot in s[0].
+// Returns (lt, gt) with s[..lt] < pivot, s[lt..gt] == pivot and
+// s[gt..] > pivot.
+pub(crate) fn partition3<T, F>(s: &mut [T], cmp: &mut F) -> (usize, usize)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let (pivot, rest) = s.split_first_mut().expect("partition of an empty slice");
+    let mut lt = 0;
+    let mut i = 0;
+    let mut gt = rest.len();
+
+    while i < gt {
+        match cmp(&rest[i], pivot) {
+            Ordering::Less => {
+                stats::swap(rest, lt, i);
+                lt += 1;
+                i += 1;
+            }
+            Ordering::Greater => {
+                gt -= 1;
+                stats::swap(rest, i, gt);
+            }
+            Ordering::Equal => i += 1,
+        }
+    }
+
+    // Move the pivot from the front to the end of the smaller elements.
+    stats::swap(s, 0, lt);
+
+    (lt, gt + 1)
+}
+
+pub(crate) fn insertion_sort<T, F>(s: &mut [T], cmp: &mut F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    for i in 1..s.len() {
+        let mut j = i;
+        while j > 0 && cmp(&s[j - 1], &s[j]) == Ordering::Greater {
+            stats::swap(s, j - 1, j);
+            j -= 1;
+        }
+    }
+}
+
+/// Introsort, see [`introsort`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Introsort;
+
+impl<T: Ord> Sorter<T> for Introsort {
+    fn name(&self) -> &'static str {
+        "introsort"
+    }
+
+    fn sort(&self, v: &mut [T]) {
+        introsort(v);
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+    use crate::{check_sorted, make_one};
+
+    const BIG: usize = 1_000_000;
+
+    #[test]
+    fn test_introsort() {
+        for i in 0..1000 {
+            let mut v = make_one(i, i * 2 + 1);
+            let mut want = v.clone();
+            want.sort();
+
+            introsort(&mut v);
+            assert_eq!(v, want);
+        }
+    }
+
+    #[test]
+    fn test_introsort_sorted() {
+        let mut v: Vec<i32> = (0..BIG as i32).collect();
+        introsort(&mut v);
+        assert!(check_sorted(&v));
+        assert_eq!(v.len(), BIG);
+    }
+
+    #[test]
+    fn test_introsort_reversed() {
+        let mut v: Vec<i32> = (0..BIG as i32).rev().collect();
+        introsort(&mut v);
+        assert!(v.iter().enumerate().all(|(i, &x)| x == i as i32));
+    }
+
+    #[test]
+    fn test_introsort_constant() {
+        let mut v = vec![7; BIG];
+        introsort(&mut v);
+        assert!(v.iter().all(|&x| x == 7));
+    }
+
+    #[test]
+    fn test_introsort_few_unique() {
+        let mut v = make_one(BIG, 4);
+        introsort(&mut v);
+        assert!(check_sorted(&v));
+    }
+
+    #[test]
+    fn test_introsort_by() {
+        let mut v = make_one(10_000, 50);
+        introsort_by(&mut v, |a, b| b.cmp(a));
+        assert!(v.windows(2).all(|w| w[0] >= w[1]));
+
+        let mut people = vec![("carol", 41), ("alice", 30), ("bob", 25)];
+        introsort_by_key(&mut people, |p| p.1);
+        assert_eq!(people, [("bob", 25), ("alice", 30), ("carol", 41)]);
+    }
+
+    #[test]
+    fn test_heapsort_fallback() {
+        // A depth of zero goes straight to heapsort.
+        let mut v = make_one(5_000, 1_000);
+        let mut want = v.clone();
+        want.sort();
+
+        introsort_loop(&mut v, 0, &mut i32::cmp);
+        assert_eq!(v, want);
+    }
+
+    #[test]
+    fn test_median3() {
+        let s = [1, 2, 3];
+        for (a, b, c) in [
+            (0, 1, 2),
+            (0, 2, 1),
+            (1, 0, 2),
+            (1, 2, 0),
+            (2, 0, 1),
+            (2, 1, 0),
+        ] {
+            assert_eq!(s[median3(&s, a, b, c, &mut i32::cmp)], 2);
+        }
+    }
+}
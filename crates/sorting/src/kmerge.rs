@@ -0,0 +1,266 @@
+//! Lazily merging any number of sorted iterators into one sorted iterator,
+//! e.g. runs sorted separately, without collecting and sorting them again.
+//!
+//! The merge runs a loser tree: a tournament with one leaf per input,
+//! where each internal node remembers the loser of the match played there
+//! and the overall winner sits on top. Taking the winner only replays the
+//! matches on its leaf's path, so each element costs ⌈log₂ k⌉ comparisons,
+//! about half what a binary heap needs to sift down.
+use std::cmp::Ordering;
+use std::iter::FusedIterator;
+
+// What kmerge compares with.
+type ByOrd<T> = fn(&T, &T) -> Ordering;
+
+/// Merges sorted iterators into one sorted iterator. Equal elements come
+/// out in the order of the iterators they came from, so the merge is
+/// stable.
+///
+/// ```
+/// use sorting::kmerge;
+///
+/// let merged: Vec<i32> = kmerge([vec![1, 4, 7], vec![2, 5], vec![3, 6, 9]]).collect();
+/// assert_eq!(merged, [1, 2, 3, 4, 5, 6, 7, 9]);
+/// ```
+pub fn kmerge<I, T>(iters: I) -> KMerge<<I::Item as IntoIterator>::IntoIter, ByOrd<T>>
+where
+    I: IntoIterator,
+    I::Item: IntoIterator<Item = T>,
+    T: Ord,
+{
+    kmerge_by(iters, T::cmp as ByOrd<T>)
+}
+
+/// [`kmerge`] with a custom comparator, which the inputs must already be
+/// sorted by.
+pub fn kmerge_by<I, F>(iters: I, cmp: F) -> KMerge<<I::Item as IntoIterator>::IntoIter, F>
+where
+    I: IntoIterator,
+    I::Item: IntoIterator,
+    F: FnMut(&<I::Item as IntoIterator>::Item, &<I::Item as IntoIterator>::Item) -> Ordering,
+{
+    KMerge {
+        sources: iters.into_iter().map(IntoIterator::into_iter).collect(),
+        heads: vec![],
+        tree: vec![],
+        cmp,
+    }
+}
+
+/// The iterator from [`kmerge`] and [`kmerge_by`].
+#[derive(Clone, Debug)]
+pub struct KMerge<I: Iterator, F> {
+    sources: Vec<I>,
+    // The next element of each source, or None once it's run out.
+    heads: Vec<Option<I::Item>>,
+    // tree[0] is the winning source; tree[1..] the loser at each internal
+    // node, where node n's children are 2n and 2n + 1 and source i's leaf
+    // is k + i. Empty until the first call to next().
+    tree: Vec<usize>,
+    cmp: F,
+}
+
+impl<I, F> KMerge<I, F>
+where
+    I: Iterator,
+    F: FnMut(&I::Item, &I::Item) -> Ordering,
+{
+    // True if source a's head goes out before source b's. Sources that have
+    // run out lose to everything, and ties go to the earlier source.
+    fn beats(&mut self, a: usize, b: usize) -> bool {
+        match (&self.heads[a], &self.heads[b]) {
+            (Some(x), Some(y)) => match (self.cmp)(x, y) {
+                Ordering::Less => true,
+                Ordering::Greater => false,
+                Ordering::Equal => a < b,
+            },
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => a < b,
+        }
+    }
+
+    // Pulls every source's first element and plays the first tournament.
+    // Each player climbs until it finds a node nobody has reached yet and
+    // waits there; the second player to reach a node plays the first, and
+    // the winner climbs on.
+    fn start(&mut self) {
+        let k = self.sources.len();
+        self.heads = self.sources.iter_mut().map(Iterator::next).collect();
+        self.tree = vec![usize::MAX; k];
+
+        for i in 0..k {
+            let mut winner = i;
+            let mut node = (k + i) / 2;
+            while node > 0 {
+                if self.tree[node] == usize::MAX {
+                    self.tree[node] = winner;
+                    break;
+                }
+                if self.beats(self.tree[node], winner) {
+                    std::mem::swap(&mut self.tree[node], &mut winner);
+                }
+                node /= 2;
+            }
+            if node == 0 {
+                self.tree[0] = winner;
+            }
+        }
+    }
+
+    // Replays the matches from source i's leaf up after its head changed.
+    fn replay(&mut self, i: usize) {
+        let mut winner = i;
+        let mut node = (self.sources.len() + i) / 2;
+        while node > 0 {
+            if self.beats(self.tree[node], winner) {
+                std::mem::swap(&mut self.tree[node], &mut winner);
+            }
+            node /= 2;
+        }
+        self.tree[0] = winner;
+    }
+}
+
+impl<I, F> Iterator for KMerge<I, F>
+where
+    I: Iterator,
+    F: FnMut(&I::Item, &I::Item) -> Ordering,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        if self.sources.is_empty() {
+            return None;
+        }
+        if self.tree.is_empty() {
+            self.start();
+        }
+
+        // Once the winner has run out, every source has, and they're not
+        // pulled again in case they aren't fused.
+        let winner = self.tree[0];
+        self.heads[winner].as_ref()?;
+        let next = self.sources[winner].next();
+        let item = std::mem::replace(&mut self.heads[winner], next)?;
+        self.replay(winner);
+
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let held = self.heads.iter().filter(|h| h.is_some()).count();
+        self.sources
+            .iter()
+            .map(Iterator::size_hint)
+            .fold((held, Some(held)), |(lo, hi), (l, h)| {
+                (
+                    lo.saturating_add(l),
+                    hi.zip(h).and_then(|(a, b)| a.checked_add(b)),
+                )
+            })
+    }
+}
+
+impl<I, F> FusedIterator for KMerge<I, F>
+where
+    I: Iterator,
+    F: FnMut(&I::Item, &I::Item) -> Ordering,
+{
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+    use crate::{check_sort, Distribution, Prng};
+
+    #[test]
+    fn test_kmerge() {
+        let mut prng = Prng::from_seed(49);
+        for k in [0, 1, 2, 3, 5, 8, 13] {
+            let runs: Vec<Vec<i32>> = (0..k)
+                .map(|_| {
+                    let n = prng.next_u64() as usize % 50;
+                    let mut run =
+                        Distribution::FewUnique { values: 8 }.generate(n, prng.next_u64());
+                    run.sort();
+                    run
+                })
+                .collect();
+            let all: Vec<i32> = runs.concat();
+
+            let merged = kmerge(runs.clone());
+            assert_eq!(merged.size_hint(), (all.len(), Some(all.len())));
+            let merged: Vec<i32> = merged.collect();
+            assert_eq!(check_sort(&all, &merged), Ok(()), "k = {k}");
+
+            let mut desc = runs;
+            for run in &mut desc {
+                run.reverse();
+            }
+            let merged: Vec<i32> = kmerge_by(desc, |a, b| b.cmp(a)).collect();
+            assert!(merged.windows(2).all(|w| w[0] >= w[1]));
+        }
+    }
+
+    #[test]
+    fn test_kmerge_stable() {
+        // Equal keys come out in source order, then in order within a source.
+        let runs = [
+            vec![(1, 'a'), (2, 'a'), (2, 'b')],
+            vec![(0, 'c'), (2, 'c')],
+            vec![(2, 'd'), (3, 'd')],
+        ];
+        let merged: Vec<(i32, char)> = kmerge_by(runs, |a, b| a.0.cmp(&b.0)).collect();
+        assert_eq!(
+            merged,
+            [
+                (0, 'c'),
+                (1, 'a'),
+                (2, 'a'),
+                (2, 'b'),
+                (2, 'c'),
+                (2, 'd'),
+                (3, 'd')
+            ]
+        );
+    }
+
+    #[test]
+    fn test_kmerge_lazy() {
+        // Endless inputs are fine as long as only a prefix is taken.
+        let evens = (0..).step_by(2);
+        let odds = (1..).step_by(2);
+        let squares = (0..).map(|x: i32| x * x);
+        let merged: Vec<i32> = kmerge([
+            Box::new(evens) as Box<dyn Iterator<Item = i32>>,
+            Box::new(odds),
+            Box::new(squares),
+        ])
+        .take(8)
+        .collect();
+        assert_eq!(merged, [0, 0, 1, 1, 2, 3, 4, 4]);
+
+        let empty: Vec<Vec<i32>> = vec![vec![], vec![]];
+        assert_eq!(kmerge(empty).next(), None);
+    }
+
+    #[test]
+    fn test_kmerge_fused() {
+        // Yields 0, then None, then 1, 2, ... on every other call.
+        struct Flaky(i32);
+        impl Iterator for Flaky {
+            type Item = i32;
+            fn next(&mut self) -> Option<i32> {
+                self.0 += 1;
+                (self.0 % 2 == 1).then_some(self.0 / 2)
+            }
+        }
+
+        let mut merged = kmerge([Flaky(0), Flaky(0)]);
+        assert_eq!(merged.by_ref().take(2).collect::<Vec<_>>(), [0, 0]);
+        for _ in 0..4 {
+            assert_eq!(merged.next(), None);
+        }
+    }
+}
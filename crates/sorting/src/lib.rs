@@ -3,77 +3,86 @@ use std::{io, str::FromStr};
 
 use anyhow::{anyhow, Result};
 
+mod distribution;
+pub use distribution::{antiqsort, Distribution};
+mod error;
+pub use error::SortError;
+
+mod check;
+pub use check::{
+    cases, check_sort, check_sorter, check_stable_sort, check_stable_sorter, Case, CheckError,
+    CheckFailure, Tagged,
+};
+mod collate;
+pub use collate::{ascii_caseless_cmp, natural_cmp, natural_cmp_with, Collation};
+mod counting_sort;
+pub use counting_sort::{counting_sort, counting_sort_by_key, counting_sort_by_signed_key};
+mod external;
+pub use external::{
+    external_sort, external_sort_reader, ExternalSortConfig, ExternalSortSummary, Record,
+};
+mod float;
+pub use float::{float_cmp, sort_floats, Float, NanPlacement, Total};
+mod heap;
+pub use heap::{dary_heapsort_by, heapsort, heapsort_by, Handle, Heap, HeapKind, Heapsort};
+mod introsort;
+pub use introsort::{introsort, introsort_by, introsort_by_key, Introsort};
+mod kmerge;
+pub use kmerge::{kmerge, kmerge_by, KMerge};
+mod merge_sort;
+pub use merge_sort::{
+    bottom_up_merge_sort, bottom_up_merge_sort_by, merge_sort, merge_sort_by, merge_sort_by_key,
+    timsort, timsort_by, timsort_by_key, BottomUpMergeSort, MergeSort, Timsort,
+};
+mod multikey;
+pub use multikey::{multikey_quicksort, multikey_quicksort_with, MultikeyQuicksort};
+mod network;
+pub use network::{network_sort, Network, OPTIMAL_MAX};
+mod parallel;
+pub use parallel::{
+    par_merge_sort, par_merge_sort_by, par_quicksort, par_quicksort_by, par_radix_sort,
+    ParMergeSort, ParQuicksort, ParRadixSort, ParallelConfig,
+};
 mod prng;
-pub use prng::Prng;
-
-// Traditionally this takes an in put array and a max element.
-// counting_sort(input: Vec<i32>, k: usize)
-// We find the max as a first step in the fuction.
-pub fn counting_sort(input: Vec<i32>) -> Vec<i32> {
-    // Get the max value
-    let max = input.iter().fold(0, |a, b| a.max(*b)) as usize;
-
-    let mut counts = vec![0i32; max + 1];
-    let mut output = vec![0i32; input.len()];
-
-    // Build the counts vec first.
-    input.iter().for_each(|elem| counts[*elem as usize] += 1);
-
-    // Accumulate positions in the vec for populating the output vec.
-    // We start at one and add any zeros in the zero place.
-    // Spent an embarassing amount of time after setting 0 to 0 and starting at one.
-    // 🤦‍
-    for idx in 1..counts.len() {
-        counts[idx] += counts[idx - 1]
-    }
-
-    // Place the elements in order in the output array.
-    input.into_iter().rev().for_each(|k| {
-        counts[k as usize] -= 1;
-        output[counts[k as usize] as usize] = k;
-    });
-
-    output
-}
-
-// Divides the array into two particions using the
-// [Luomo Partition Scheme](https://en.wikipedia.org/wiki/Quicksort#Lomuto_partition_scheme).
-fn partition(s: &mut [i32]) -> usize {
-    let lo = 0;
-    // The last element is the pivot.
-    let pvt = s.len() - 1;
-
-    // Temporary pivot index
-    let mut i = lo;
-
-    for j in 0..pvt {
-        // If the current element is less than or equal to the pivot
-        if s[j] <= s[pvt] {
-            // swap it
-            s.swap(j, i);
-            // next indext to compare.
-            i += 1;
-        }
-    }
-
-    s.swap(i, pvt);
-
-    i
-}
-
-// Sorts a (portion of an) array, divides it into partitions,
-// then sorts those.
-pub fn quicksort(s: &mut [i32]) {
-    // Ensure valid indices.
-    if s.len() > 1 {
-        // Partition array and get the pivot index
-        let p = partition(s);
-
-        // Sort two partitions
-        quicksort(&mut s[0..p]); // Left side of pivot
-        quicksort(&mut s[(p + 1)..]); // Right side of pivot
-    }
-}
+pub use prng::{Pcg32, Prng, SplitMix64, Xorshift128Plus};
+mod quicksort;
+pub use quicksort::{quicksort, quicksort_by, quicksort_by_key};
+mod radix_sort;
+pub use radix_sort::{
+    msd_radix_sort, msd_radix_sort_by_key, radix_sort, radix_sort_by_key, MsdRadixSort, RadixKey,
+    RadixSort,
+};
+mod randtest;
+pub use randtest::{
+    birthday_spacings_test, chi_square_test, gap_test, ks_test, run_suite, runs_test,
+    serial_correlation_test, TestResult,
+};
+mod render;
+pub use render::{animate, bar_chart, frames, html_page, svg_frame, write_svg_frames, Frame, Mark};
+mod search;
+pub use search::{
+    binary_search, binary_search_by, equal_range, equal_range_by, exponential_search,
+    exponential_search_by, interpolation_search, interpolation_search_counted, linear_search,
+    linear_search_by, lower_bound, lower_bound_by, ternary_search, ternary_search_f64, upper_bound,
+    upper_bound_by, Comparisons,
+};
+mod select;
+pub use select::{
+    partial_sort, partial_sort_by, select_nth, select_nth_by, top_k, top_k_partition,
+    StreamingPercentile,
+};
+mod sorted_set;
+pub use sorted_set::{
+    dedup_sorted, dedup_sorted_by, merge_join, sorted_difference, sorted_difference_by,
+    sorted_intersection, sorted_intersection_by, sorted_symmetric_difference,
+    sorted_symmetric_difference_by, sorted_union, sorted_union_by,
+};
+mod stats;
+pub use stats::{measure, sort_stats, Counted, SortStats};
+mod sorter;
+pub use sorter::{CountingSort, Quicksort, Sorter};
+mod trace;
+pub use trace::{trace, trace_sorter, Event, Slot, Trace};
 
 /// Get's a number of elements to sort from the user.
 pub fn get_count<T>(prompt: &str) -> Result<T>
@@ -91,20 +100,22 @@ where
         .map_err(|_| anyhow!(format!("failed to parse input: {prompt}")))
 }
 
-/// Makes a vec of len `num_items` wiith a max value of max.
+/// Makes a vec of len `num_items` with values from 0 to `max`, which is
+/// capped at `i32::MAX - 1`.
 pub fn make_one(num_items: usize, max: usize) -> Vec<i32> {
     let mut prng = Prng::new();
+    let end = max.min(i32::MAX as usize - 1) as i32 + 1;
 
     let mut v = Vec::with_capacity(num_items);
     for _ in 0..num_items {
-        v.push(prng.next_i32(0, max as i32));
+        v.push(prng.next_i32(0, end));
     }
 
     v
 }
 
 /// Check that a vec is sorted.
-pub fn check_sorted(v: &Vec<i32>) -> bool {
+pub fn check_sorted<T: PartialOrd>(v: &[T]) -> bool {
     if v.is_empty() {
         return true;
     }
@@ -112,7 +123,7 @@ pub fn check_sorted(v: &Vec<i32>) -> bool {
     v.windows(2).all(|w| w[0] <= w[1])
 }
 
-/// Print at most `num` items.
+// Print at most `num` items.
 // pub fn print_vec(v: &Vec<i32>, num: usize) {
 //     println!("{:#?}", v.iter().take(num).collect::<Vec<&i32>>());
 // }
@@ -127,8 +138,18 @@ mod unit {
     fn test_make_one() {
         let got = make_one(3, 100);
         assert!(got.len() == 3);
-        assert!(got.iter().max() < Some(&100));
-        assert!(got.iter().min() > Some(&0));
+        assert!(got.iter().all(|x| (0..=100).contains(x)));
+
+        // Both ends are drawn. Missing one in 1000 draws is a 1 in 10^124
+        // chance.
+        let got = make_one(1000, 3);
+        assert_eq!(got.iter().min(), Some(&0));
+        assert_eq!(got.iter().max(), Some(&3));
+
+        // Too big for an i32, so capped rather than overflowing.
+        let got = make_one(100, usize::MAX);
+        assert!(got.iter().all(|&x| x >= 0));
+        make_one(1, i32::MAX as usize);
     }
 
     #[test]
@@ -151,21 +172,4 @@ mod unit {
             assert_eq!(check_sorted(&tc.0), tc.1);
         }
     }
-
-    #[test]
-    fn test_counting_sort() {
-        for i in 5..1000 {
-            let v = make_one(i, i * 2);
-            let got = counting_sort(v);
-            assert!(check_sorted(&got));
-        }
-    }
-    #[test]
-    fn test_quicksort() {
-        for i in 50..1000 {
-            let mut v = make_one(i, i * 2);
-            quicksort(&mut v[..]);
-            assert!(check_sorted(&v));
-        }
-    }
 }
@@ -0,0 +1,475 @@
+//! Stable merge sorts.
+//!
+//! All three keep equal elements in their original order, and all of them
+//! need `T: Clone` for the merge buffer, which holds at most half the slice.
+//! - [`merge_sort`] is the textbook top-down recursion.
+//! - [`bottom_up_merge_sort`] merges runs of doubling width without
+//!   recursing.
+//! - [`timsort`] finds the runs already in the input and merges them with
+//!   galloping, so sorted, reversed and partly sorted data is close to O(n).
+use std::cmp::Ordering;
+
+use crate::introsort::insertion_sort;
+use crate::search::gallop;
+use crate::stats;
+use crate::Sorter;
+
+// Slices this short are insertion sorted, which is stable too.
+const INSERTION_CUTOFF: usize = 16;
+// A side has to win this many times in a row before a merge starts galloping.
+const MIN_GALLOP: usize = 7;
+
+/// Top-down merge sort. Stable, O(n log n).
+pub fn merge_sort<T: Ord + Clone>(s: &mut [T]) {
+    merge_sort_by(s, T::cmp);
+}
+
+/// Top-down merge sort with a custom comparator.
+pub fn merge_sort_by<T, F>(s: &mut [T], mut cmp: F)
+where
+    T: Clone,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let mut buf = Vec::with_capacity(s.len() / 2);
+    merge_sort_rec(s, &mut buf, &mut cmp);
+}
+
+/// Top-down merge sort on a key pulled out of each element.
+pub fn merge_sort_by_key<T, K, F>(s: &mut [T], mut key: F)
+where
+    T: Clone,
+    K: Ord,
+    F: FnMut(&T) -> K,
+{
+    merge_sort_by(s, |a, b| key(a).cmp(&key(b)));
+}
+
+fn merge_sort_rec<T, F>(s: &mut [T], buf: &mut Vec<T>, cmp: &mut F)
+where
+    T: Clone,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    if s.len() <= INSERTION_CUTOFF {
+        insertion_sort(s, cmp);
+        return;
+    }
+
+    let _depth = stats::enter();
+    let mid = s.len() / 2;
+    merge_sort_rec(&mut s[..mid], buf, cmp);
+    merge_sort_rec(&mut s[mid..], buf, cmp);
+    merge(s, mid, buf, cmp);
+}
+
+/// Bottom-up merge sort. Stable, O(n log n), no recursion.
+pub fn bottom_up_merge_sort<T: Ord + Clone>(s: &mut [T]) {
+    bottom_up_merge_sort_by(s, T::cmp);
+}
+
+/// Bottom-up merge sort with a custom comparator.
+pub fn bottom_up_merge_sort_by<T, F>(s: &mut [T], mut cmp: F)
+where
+    T: Clone,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let n = s.len();
+    for chunk in s.chunks_mut(INSERTION_CUTOFF) {
+        insertion_sort(chunk, &mut cmp);
+    }
+
+    let mut buf = Vec::with_capacity(n / 2);
+    let mut width = INSERTION_CUTOFF;
+    while width < n {
+        for lo in (0..n - width).step_by(2 * width) {
+            let hi = (lo + 2 * width).min(n);
+            merge(&mut s[lo..hi], width, &mut buf, &mut cmp);
+        }
+        width *= 2;
+    }
+}
+
+// Merges the sorted halves s[..mid] and s[mid..], copying the left half out
+// to `buf`. Ties go to the left half, which keeps the merge stable.
+pub(crate) fn merge<T, F>(s: &mut [T], mid: usize, buf: &mut Vec<T>, cmp: &mut F)
+where
+    T: Clone,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    // Already in order, nothing to do.
+    if mid == 0 || mid == s.len() || cmp(&s[mid - 1], &s[mid]) != Ordering::Greater {
+        return;
+    }
+
+    buf.clear();
+    buf.extend_from_slice(&s[..mid]);
+    stats::copied(&s[..mid], buf);
+    let buf = &buf[..];
+
+    // s[k] is always either copied into buf or already written, so it's free.
+    let (mut i, mut j, mut k) = (0, mid, 0);
+    while i < buf.len() && j < s.len() {
+        if cmp(&s[j], &buf[i]) == Ordering::Less {
+            stats::swap(s, k, j);
+            j += 1;
+        } else {
+            s[k] = buf[i].clone();
+            stats::copied_one(&buf[i], &s[k]);
+            i += 1;
+        }
+        k += 1;
+    }
+
+    // Anything left on the right is already in place.
+    s[k..k + buf.len() - i].clone_from_slice(&buf[i..]);
+    stats::copied(&buf[i..], &s[k..k + buf.len() - i]);
+}
+
+/// Natural merge sort in the style of Python's Timsort. Stable,
+/// O(n log n) worst case and O(n) on input that's already in runs.
+pub fn timsort<T: Ord + Clone>(s: &mut [T]) {
+    timsort_by(s, T::cmp);
+}
+
+/// Timsort with a custom comparator.
+pub fn timsort_by<T, F>(s: &mut [T], mut cmp: F)
+where
+    T: Clone,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let n = s.len();
+    if n < 2 {
+        return;
+    }
+
+    let min_run = min_run(n);
+    let mut buf = Vec::new();
+    // (start, len) of each pending run.
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+
+    let mut start = 0;
+    while start < n {
+        let mut len = find_run(&mut s[start..], &mut cmp);
+
+        // Short runs get topped up to min_run with insertion sort.
+        if len < min_run {
+            len = min_run.min(n - start);
+            insertion_sort(&mut s[start..start + len], &mut cmp);
+        }
+
+        runs.push((start, len));
+        collapse(s, &mut runs, &mut buf, &mut cmp, false);
+        start += len;
+    }
+
+    collapse(s, &mut runs, &mut buf, &mut cmp, true);
+}
+
+/// Timsort on a key pulled out of each element.
+pub fn timsort_by_key<T, K, F>(s: &mut [T], mut key: F)
+where
+    T: Clone,
+    K: Ord,
+    F: FnMut(&T) -> K,
+{
+    timsort_by(s, |a, b| key(a).cmp(&key(b)));
+}
+
+// Picks a run length in 32..=64 so that n / min_run is a power of two, or
+// just under one, which keeps the final merges balanced.
+fn min_run(mut n: usize) -> usize {
+    let mut r = 0;
+    while n >= 64 {
+        r |= n & 1;
+        n >>= 1;
+    }
+
+    n + r
+}
+
+// Length of the run at the start of `s`. A strictly descending run is
+// reversed in place; strict so that reversing can't reorder equal elements.
+fn find_run<T, F>(s: &mut [T], cmp: &mut F) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    if s.len() < 2 {
+        return s.len();
+    }
+
+    let mut end = 2;
+    if cmp(&s[1], &s[0]) == Ordering::Less {
+        while end < s.len() && cmp(&s[end], &s[end - 1]) == Ordering::Less {
+            end += 1;
+        }
+        stats::reverse(&mut s[..end]);
+    } else {
+        while end < s.len() && cmp(&s[end], &s[end - 1]) != Ordering::Less {
+            end += 1;
+        }
+    }
+
+    end
+}
+
+// Merges pending runs until the run lengths on the stack satisfy
+// A > B + C and B > C (for the top three, A deepest), or down to a single
+// run when `force` is set. Checking the fourth run down as well fixes the
+// invariant bug found in the original Timsort.
+fn collapse<T, F>(
+    s: &mut [T],
+    runs: &mut Vec<(usize, usize)>,
+    buf: &mut Vec<T>,
+    cmp: &mut F,
+    force: bool,
+) where
+    T: Clone,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    while runs.len() > 1 {
+        let mut n = runs.len() - 2;
+        let len = |i: usize| runs[i].1;
+
+        if force
+            || (n > 0 && len(n - 1) <= len(n) + len(n + 1))
+            || (n > 1 && len(n - 2) <= len(n - 1) + len(n))
+        {
+            if n > 0 && len(n - 1) < len(n + 1) {
+                n -= 1;
+            }
+        } else if len(n) > len(n + 1) {
+            return;
+        }
+
+        let (start, left) = runs[n];
+        let (_, right) = runs.remove(n + 1);
+        runs[n].1 = left + right;
+        merge_runs(&mut s[start..start + left + right], left, buf, cmp);
+    }
+}
+
+// Merges two adjacent runs. Elements of the left run that are no bigger
+// than the right run's first element, and elements of the right run that
+// are no smaller than the left run's last, are already in place and skipped.
+fn merge_runs<T, F>(s: &mut [T], mid: usize, buf: &mut Vec<T>, cmp: &mut F)
+where
+    T: Clone,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let lo = gallop(&s[..mid], |x| cmp(x, &s[mid]) != Ordering::Greater);
+    if lo == mid {
+        return;
+    }
+    let hi = mid + gallop(&s[mid..], |x| cmp(x, &s[mid - 1]) == Ordering::Less);
+
+    gallop_merge(&mut s[lo..hi], mid - lo, buf, cmp);
+}
+
+// Like `merge`, but once one side wins MIN_GALLOP times in a row it
+// switches to galloping: finding how far that side keeps winning with an
+// exponential search and moving the whole stretch at once.
+fn gallop_merge<T, F>(s: &mut [T], mid: usize, buf: &mut Vec<T>, cmp: &mut F)
+where
+    T: Clone,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    buf.clear();
+    buf.extend_from_slice(&s[..mid]);
+    stats::copied(&s[..mid], buf);
+    let buf = &buf[..];
+
+    let (mut i, mut j, mut k) = (0, mid, 0);
+    let (mut left_wins, mut right_wins) = (0, 0);
+
+    while i < buf.len() && j < s.len() {
+        if cmp(&s[j], &buf[i]) == Ordering::Less {
+            stats::swap(s, k, j);
+            j += 1;
+            right_wins += 1;
+            left_wins = 0;
+        } else {
+            s[k] = buf[i].clone();
+            stats::copied_one(&buf[i], &s[k]);
+            i += 1;
+            left_wins += 1;
+            right_wins = 0;
+        }
+        k += 1;
+
+        if left_wins >= MIN_GALLOP && j < s.len() {
+            // Every left element up to and including ties with s[j] goes next.
+            let count = gallop(&buf[i..], |x| cmp(x, &s[j]) != Ordering::Greater);
+            s[k..k + count].clone_from_slice(&buf[i..i + count]);
+            stats::copied(&buf[i..i + count], &s[k..k + count]);
+            i += count;
+            k += count;
+            left_wins = 0;
+        } else if right_wins >= MIN_GALLOP && i < buf.len() {
+            // Every right element strictly less than buf[i] goes next.
+            let count = gallop(&s[j..], |x| cmp(x, &buf[i]) == Ordering::Less);
+            for _ in 0..count {
+                stats::swap(s, k, j);
+                j += 1;
+                k += 1;
+            }
+            right_wins = 0;
+        }
+    }
+
+    s[k..k + buf.len() - i].clone_from_slice(&buf[i..]);
+    stats::copied(&buf[i..], &s[k..k + buf.len() - i]);
+}
+
+/// Top-down merge sort, see [`merge_sort`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MergeSort;
+
+impl<T: Ord + Clone> Sorter<T> for MergeSort {
+    fn name(&self) -> &'static str {
+        "merge_sort"
+    }
+
+    fn sort(&self, v: &mut [T]) {
+        merge_sort(v);
+    }
+}
+
+/// Bottom-up merge sort, see [`bottom_up_merge_sort`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BottomUpMergeSort;
+
+impl<T: Ord + Clone> Sorter<T> for BottomUpMergeSort {
+    fn name(&self) -> &'static str {
+        "bottom_up_merge_sort"
+    }
+
+    fn sort(&self, v: &mut [T]) {
+        bottom_up_merge_sort(v);
+    }
+}
+
+/// Timsort, see [`timsort`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Timsort;
+
+impl<T: Ord + Clone> Sorter<T> for Timsort {
+    fn name(&self) -> &'static str {
+        "timsort"
+    }
+
+    fn sort(&self, v: &mut [T]) {
+        timsort(v);
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+    use crate::make_one;
+
+    type SortFn = fn(&mut [(i32, usize)], fn(&(i32, usize)) -> i32);
+
+    const SORTS: [(&str, SortFn); 3] = [
+        ("merge_sort", |s, k| merge_sort_by_key(s, k)),
+        ("bottom_up_merge_sort", |s, k| {
+            bottom_up_merge_sort_by(s, |a, b| k(a).cmp(&k(b)))
+        }),
+        ("timsort", |s, k| timsort_by_key(s, k)),
+    ];
+
+    // Pairs each value with its original index, so a stable sort by value
+    // leaves the indices ascending within each run of equal values.
+    fn indexed(v: Vec<i32>) -> Vec<(i32, usize)> {
+        v.into_iter().enumerate().map(|(i, x)| (x, i)).collect()
+    }
+
+    fn check_stable(name: &str, input: Vec<i32>) {
+        for (sort_name, sort) in SORTS {
+            let mut got = indexed(input.clone());
+            let mut want = got.clone();
+            want.sort_by_key(|p| p.0);
+
+            sort(&mut got, |p| p.0);
+            assert_eq!(got, want, "{sort_name} on {name}");
+        }
+    }
+
+    #[test]
+    fn test_merge_sorts() {
+        for i in 0..500 {
+            let v = make_one(i, i * 2 + 1);
+            let mut want = v.clone();
+            want.sort();
+
+            for sort in [merge_sort, bottom_up_merge_sort, timsort] {
+                let mut got = v.clone();
+                sort(&mut got);
+                assert_eq!(got, want);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stable_random() {
+        for i in [0, 1, 2, 17, 100, 1000, 10_000] {
+            check_stable("few unique", make_one(i, 10));
+            check_stable("random", make_one(i, i * 4 + 1));
+        }
+    }
+
+    #[test]
+    fn test_stable_structured() {
+        let n = 10_000;
+        check_stable("sorted", (0..n).map(|x| x / 3).collect());
+        check_stable("reversed", (0..n).rev().map(|x| x / 3).collect());
+        check_stable("constant", vec![5; n as usize]);
+        check_stable(
+            "sawtooth",
+            (0..n).map(|x| x % 300 + x % 7).collect::<Vec<_>>(),
+        );
+        check_stable(
+            "organ pipe",
+            (0..n).map(|x| x.min(n - x) / 2).collect::<Vec<_>>(),
+        );
+
+        // Long runs on each side make the merges gallop.
+        let mut runs: Vec<i32> = (0..2_000).collect();
+        runs.extend(1_000..3_000);
+        runs.extend((0..2_000).map(|x| x * 2));
+        check_stable("galloping", runs);
+    }
+
+    #[test]
+    fn test_by_key() {
+        let mut people = vec![("carol", 30), ("alice", 41), ("bob", 30), ("dave", 25)];
+        timsort_by_key(&mut people, |p| p.1);
+        assert_eq!(
+            people,
+            [("dave", 25), ("carol", 30), ("bob", 30), ("alice", 41)]
+        );
+
+        merge_sort_by(&mut people, |a, b| b.1.cmp(&a.1));
+        assert_eq!(
+            people,
+            [("alice", 41), ("carol", 30), ("bob", 30), ("dave", 25)]
+        );
+    }
+
+    #[test]
+    fn test_min_run() {
+        assert_eq!(min_run(10), 10);
+        assert_eq!(min_run(64), 32);
+        assert_eq!(min_run(65), 33);
+        for n in 64..100_000 {
+            assert!((32..=64).contains(&min_run(n)));
+        }
+    }
+
+    #[test]
+    fn test_gallop() {
+        let s: Vec<i32> = (0..100).collect();
+        for k in 0..=100 {
+            assert_eq!(gallop(&s, |&x| x < k), k as usize);
+        }
+        assert_eq!(gallop(&[] as &[i32], |_| true), 0);
+    }
+}
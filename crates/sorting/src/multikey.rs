@@ -0,0 +1,169 @@
+//! Multikey quicksort (Bentley and Sedgewick's three-way radix quicksort)
+//! for strings.
+//!
+//! Partitions on one byte at a time into less than, equal to and greater
+//! than a pivot byte, and only the equal part moves on to the next byte.
+//! Common prefixes are never compared twice, which makes it much faster
+//! than a comparison sort on strings like URLs or paths that share long
+//! prefixes.
+use std::cmp::Ordering;
+
+use crate::{introsort_by, stats, Collation, Sorter};
+
+// Partitions this small are finished with a comparison sort.
+const CUTOFF: usize = 16;
+
+/// Sorts byte strings, e.g. `String`s, `&str`s or `Vec<u8>`s, in byte
+/// order. Not stable.
+pub fn multikey_quicksort<T: AsRef<[u8]>>(s: &mut [T]) {
+    multikey_quicksort_with(s, Collation::Bytes);
+}
+
+/// [`multikey_quicksort`] in the order of `collation`, e.g. ignoring ASCII
+/// case. Strings that tie under the collation are ordered by their bytes,
+/// as [`Collation::cmp`] does.
+pub fn multikey_quicksort_with<T: AsRef<[u8]>>(s: &mut [T], collation: Collation) {
+    sort(s, 0, collation);
+}
+
+// The byte at `depth` under the collation, or -1 past the end so shorter
+// strings come first.
+fn byte_at<T: AsRef<[u8]>>(t: &T, depth: usize, collation: Collation) -> i16 {
+    t.as_ref()
+        .get(depth)
+        .map_or(-1, |&b| collation.fold(b) as i16)
+}
+
+// Sorts `s`, whose strings all agree on their first `depth` bytes under
+// the collation. Only the parts less and greater than the pivot recurse;
+// the equal part loops on to the next byte, so a long shared prefix
+// doesn't grow the stack.
+fn sort<T: AsRef<[u8]>>(mut s: &mut [T], mut depth: usize, collation: Collation) {
+    loop {
+        if s.len() <= CUTOFF {
+            // Compare from depth on, then whole strings to break ties.
+            introsort_by(s, |a, b| {
+                let (a, b) = (a.as_ref(), b.as_ref());
+                collation
+                    .cmp_folded(a.get(depth..).unwrap_or(&[]), b.get(depth..).unwrap_or(&[]))
+                    .then_with(|| a.cmp(b))
+            });
+            return;
+        }
+        let _depth = stats::enter();
+
+        let byte = |t: &T| byte_at(t, depth, collation);
+        let mid = s.len() / 2;
+        let pivot = median3(byte(&s[0]), byte(&s[mid]), byte(&s[s.len() - 1]));
+
+        // Dijkstra's three-way partition: [..lt] < pivot, [lt..i] == pivot,
+        // [gt..] > pivot.
+        let (mut lt, mut i, mut gt) = (0, 0, s.len());
+        while i < gt {
+            match byte(&s[i]).cmp(&pivot) {
+                Ordering::Less => {
+                    stats::swap(s, lt, i);
+                    lt += 1;
+                    i += 1;
+                }
+                Ordering::Greater => {
+                    gt -= 1;
+                    stats::swap(s, i, gt);
+                }
+                Ordering::Equal => i += 1,
+            }
+        }
+
+        sort(&mut s[..lt], depth, collation);
+        sort(&mut s[gt..], depth, collation);
+        if pivot < 0 {
+            if collation != Collation::Bytes {
+                // All ended here, so they tie under the collation; order by
+                // bytes.
+                introsort_by(&mut s[lt..gt], |a, b| a.as_ref().cmp(b.as_ref()));
+            }
+            return;
+        }
+        s = &mut std::mem::take(&mut s)[lt..gt];
+        depth += 1;
+    }
+}
+
+fn median3(a: i16, b: i16, c: i16) -> i16 {
+    a.max(b).min(a.min(b).max(c))
+}
+
+/// Multikey quicksort, see [`multikey_quicksort`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MultikeyQuicksort;
+
+impl<T: AsRef<[u8]>> Sorter<T> for MultikeyQuicksort {
+    fn name(&self) -> &'static str {
+        "multikey_quicksort"
+    }
+
+    fn sort(&self, v: &mut [T]) {
+        multikey_quicksort(v);
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+    use crate::{ascii_caseless_cmp, Prng};
+
+    // Random strings over a small alphabet, so they share prefixes and
+    // repeat, of lengths 0 to 12.
+    fn words(n: usize, seed: u64) -> Vec<String> {
+        let alphabet = b"aAbB_z0";
+        let mut prng = Prng::from_seed(seed);
+        (0..n)
+            .map(|_| {
+                let len = prng.next_u64() as usize % 13;
+                (0..len)
+                    .map(|_| alphabet[prng.next_u64() as usize % alphabet.len()] as char)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_multikey_quicksort() {
+        for n in [0, 1, 2, 15, 16, 17, 100, 5000] {
+            let v = words(n, n as u64);
+            let mut want = v.clone();
+            want.sort();
+
+            let mut got = v.clone();
+            multikey_quicksort(&mut got);
+            assert_eq!(got, want, "n = {n}");
+
+            let mut caseless = v.clone();
+            multikey_quicksort_with(&mut caseless, Collation::IgnoreAsciiCase);
+            want.sort_by(|a, b| ascii_caseless_cmp(a, b));
+            assert_eq!(caseless, want, "n = {n}");
+        }
+
+        // Long shared prefixes, and byte slices rather than strings.
+        let mut v: Vec<Vec<u8>> = (0..500u32)
+            .rev()
+            .map(|i| [&[7u8; 100][..], &i.to_be_bytes()].concat())
+            .collect();
+        MultikeyQuicksort.sort(&mut v);
+        assert!(v.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_long_shared_prefix() {
+        // A byte per level would need a stack frame per byte of prefix.
+        let prefix = "x".repeat(100_000);
+        let mut v: Vec<String> = (0..40).rev().map(|i| format!("{prefix}{i:02}")).collect();
+        let mut want = v.clone();
+        want.sort();
+
+        multikey_quicksort(&mut v);
+        assert_eq!(v, want);
+        multikey_quicksort_with(&mut v, Collation::IgnoreAsciiCase);
+        assert_eq!(v, want);
+    }
+}
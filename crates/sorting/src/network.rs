@@ -0,0 +1,578 @@
+//! Sorting networks: a fixed list of comparators, each of which swaps two
+//! positions if they're out of order. The comparisons don't depend on the
+//! data, so a network runs without data-dependent branches, and
+//! comparators on separate wires can run side by side in layers.
+//!
+//! [`Network::bitonic`] and [`Network::odd_even_merge`] build Batcher's
+//! networks for any n, [`Network::optimal`] gives the smallest known for n
+//! up to 16, [`Network::verify`] checks a network by the 0-1 principle,
+//! and `Display` draws one.
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::{introsort, stats};
+
+/// Slices up to this long have a hard-coded network in [`Network::optimal`].
+pub const OPTIMAL_MAX: usize = 16;
+
+// The smallest known networks, one row per layer. Sizes 0 to 12 are proven
+// optimal; 13 to 16 are the best known. 15 is 16 with its last wire cut off.
+#[rustfmt::skip]
+const OPTIMAL: [&[(u8, u8)]; OPTIMAL_MAX + 1] = [
+    &[],
+    &[],
+    &[(0, 1)],
+    &[
+        (0, 2),
+        (0, 1),
+        (1, 2),
+    ],
+    &[
+        (0, 2), (1, 3),
+        (0, 1), (2, 3),
+        (1, 2),
+    ],
+    &[
+        (0, 3), (1, 4),
+        (0, 2), (1, 3),
+        (0, 1), (2, 4),
+        (1, 2), (3, 4),
+        (2, 3),
+    ],
+    &[
+        (0, 5), (1, 3), (2, 4),
+        (1, 2), (3, 4),
+        (0, 3), (2, 5),
+        (0, 1), (2, 3), (4, 5),
+        (1, 2), (3, 4),
+    ],
+    &[
+        (0, 6), (2, 3), (4, 5),
+        (0, 2), (1, 4), (3, 6),
+        (0, 1), (2, 5), (3, 4),
+        (1, 2), (4, 6),
+        (2, 3), (4, 5),
+        (1, 2), (3, 4), (5, 6),
+    ],
+    &[
+        (0, 2), (1, 3), (4, 6), (5, 7),
+        (0, 4), (1, 5), (2, 6), (3, 7),
+        (0, 1), (2, 3), (4, 5), (6, 7),
+        (2, 4), (3, 5),
+        (1, 4), (3, 6),
+        (1, 2), (3, 4), (5, 6),
+    ],
+    &[
+        (0, 3), (1, 7), (2, 5), (4, 8),
+        (0, 7), (2, 4), (3, 8), (5, 6),
+        (0, 2), (1, 3), (4, 5), (7, 8),
+        (1, 4), (3, 6), (5, 7),
+        (0, 1), (2, 4), (3, 5), (6, 8),
+        (2, 3), (4, 5), (6, 7),
+        (1, 2), (3, 4), (5, 6),
+    ],
+    &[
+        (0, 8), (1, 9), (2, 7), (3, 5), (4, 6),
+        (0, 2), (1, 4), (5, 8), (7, 9),
+        (0, 3), (2, 4), (5, 7), (6, 9),
+        (0, 1), (3, 6), (8, 9),
+        (1, 5), (2, 3), (4, 8), (6, 7),
+        (1, 2), (3, 5), (4, 6), (7, 8),
+        (2, 3), (4, 5), (6, 7),
+        (3, 4), (5, 6),
+    ],
+    &[
+        (0, 9), (1, 6), (2, 4), (3, 7), (5, 8),
+        (0, 1), (3, 5), (4, 10), (6, 9), (7, 8),
+        (1, 3), (2, 5), (4, 7), (8, 10),
+        (0, 4), (1, 2), (3, 7), (5, 9), (6, 8),
+        (0, 1), (2, 6), (4, 5), (7, 8), (9, 10),
+        (2, 4), (3, 6), (5, 7), (8, 9),
+        (1, 2), (3, 4), (5, 6), (7, 8),
+        (2, 3), (4, 5), (6, 7),
+    ],
+    &[
+        (0, 8), (1, 7), (2, 6), (3, 11), (4, 10), (5, 9),
+        (0, 1), (2, 5), (3, 4), (6, 9), (7, 8), (10, 11),
+        (0, 2), (1, 6), (5, 10), (9, 11),
+        (0, 3), (1, 2), (4, 6), (5, 7), (8, 11), (9, 10),
+        (1, 4), (3, 5), (6, 8), (7, 10),
+        (1, 3), (2, 5), (6, 9), (8, 10),
+        (2, 3), (4, 5), (6, 7), (8, 9),
+        (4, 6), (5, 7),
+        (3, 4), (5, 6), (7, 8),
+    ],
+    &[
+        (0, 12), (1, 10), (2, 9), (3, 7), (5, 11), (6, 8),
+        (1, 6), (2, 3), (4, 11), (7, 9), (8, 10),
+        (0, 4), (1, 2), (3, 6), (7, 8), (9, 10), (11, 12),
+        (4, 6), (5, 9), (8, 11), (10, 12),
+        (0, 5), (3, 8), (4, 7), (6, 11), (9, 10),
+        (0, 1), (2, 5), (6, 9), (7, 8), (10, 11),
+        (1, 3), (2, 4), (5, 6), (9, 10),
+        (1, 2), (3, 4), (5, 7), (6, 8),
+        (2, 3), (4, 5), (6, 7), (8, 9),
+        (3, 4), (5, 6),
+    ],
+    &[
+        (0, 1), (2, 3), (4, 5), (6, 7), (8, 9), (10, 11), (12, 13),
+        (0, 2), (1, 3), (4, 8), (5, 9), (10, 12), (11, 13),
+        (0, 4), (1, 2), (3, 7), (5, 8), (6, 10), (9, 13), (11, 12),
+        (0, 6), (1, 5), (3, 9), (4, 10), (7, 13), (8, 12),
+        (2, 10), (3, 11), (4, 6), (7, 9),
+        (1, 3), (2, 8), (5, 11), (6, 7), (10, 12),
+        (1, 4), (2, 6), (3, 5), (7, 11), (8, 10), (9, 12),
+        (2, 4), (3, 6), (5, 8), (7, 10), (9, 11),
+        (3, 4), (5, 6), (7, 8), (9, 10),
+        (6, 7),
+    ],
+    &[
+        (0, 13), (1, 12), (3, 14), (4, 8), (5, 6), (7, 11), (9, 10),
+        (0, 5), (1, 7), (2, 9), (3, 4), (6, 13), (8, 14), (11, 12),
+        (0, 1), (2, 3), (4, 5), (6, 8), (7, 9), (10, 11), (12, 13),
+        (0, 2), (1, 3), (4, 10), (5, 11), (6, 7), (8, 9), (12, 14),
+        (1, 2), (3, 12), (4, 6), (5, 7), (8, 10), (9, 11), (13, 14),
+        (1, 4), (2, 6), (5, 8), (7, 10), (9, 13), (11, 14),
+        (2, 4), (3, 6), (9, 12), (11, 13),
+        (3, 5), (6, 8), (7, 9), (10, 12),
+        (3, 4), (5, 6), (7, 8), (9, 10), (11, 12),
+        (6, 7), (8, 9),
+    ],
+    &[
+        (0, 13), (1, 12), (2, 15), (3, 14), (4, 8), (5, 6), (7, 11), (9, 10),
+        (0, 5), (1, 7), (2, 9), (3, 4), (6, 13), (8, 14), (10, 15), (11, 12),
+        (0, 1), (2, 3), (4, 5), (6, 8), (7, 9), (10, 11), (12, 13), (14, 15),
+        (0, 2), (1, 3), (4, 10), (5, 11), (6, 7), (8, 9), (12, 14), (13, 15),
+        (1, 2), (3, 12), (4, 6), (5, 7), (8, 10), (9, 11), (13, 14),
+        (1, 4), (2, 6), (5, 8), (7, 10), (9, 13), (11, 14),
+        (2, 4), (3, 6), (9, 12), (11, 13),
+        (3, 5), (6, 8), (7, 9), (10, 12),
+        (3, 4), (5, 6), (7, 8), (9, 10), (11, 12),
+        (6, 7), (8, 9),
+    ],
+];
+
+/// A comparator network on `n` wires. Each comparator `(a, b)` has
+/// `a < b` and leaves the smaller value on `a`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Network {
+    n: usize,
+    comparators: Vec<(usize, usize)>,
+}
+
+impl Network {
+    /// A network from its comparators, applied in order.
+    ///
+    /// Panics unless every comparator is `(a, b)` with `a < b < n`.
+    pub fn new(n: usize, comparators: Vec<(usize, usize)>) -> Self {
+        for &(a, b) in &comparators {
+            assert!(a < b && b < n, "bad comparator ({a}, {b}) on {n} wires");
+        }
+
+        Self { n, comparators }
+    }
+
+    /// Batcher's bitonic sorter: sorts both halves in opposite directions,
+    /// then merges the bitonic result with half-cleaners. n log² n / 4
+    /// comparators for a power of two.
+    pub fn bitonic(n: usize) -> Self {
+        let size = n.next_power_of_two();
+        let mut comparators = vec![];
+
+        let mut k = 2;
+        while k <= size {
+            // Comparing each run of k mirrored merges two ascending runs of
+            // k / 2 without a descending comparator.
+            for start in (0..size).step_by(k) {
+                for i in 0..k / 2 {
+                    comparators.push((start + i, start + k - 1 - i));
+                }
+            }
+            let mut j = k / 4;
+            while j > 0 {
+                for i in (0..size).filter(|i| i & j == 0) {
+                    comparators.push((i, i + j));
+                }
+                j /= 2;
+            }
+            k *= 2;
+        }
+
+        Self::cut(n, comparators)
+    }
+
+    /// Batcher's odd-even merge sort: sorts both halves, then merges the
+    /// even and odd positions separately and fixes up neighbours. A few
+    /// comparators fewer than [`Network::bitonic`].
+    pub fn odd_even_merge(n: usize) -> Self {
+        let size = n.next_power_of_two();
+        let mut comparators = vec![];
+
+        let mut p = 1;
+        while p < size {
+            let mut k = p;
+            while k > 0 {
+                for j in (k % p..size - k).step_by(2 * k) {
+                    for i in 0..k.min(size - j - k) {
+                        // Only within the 2p block being merged.
+                        if (i + j) / (2 * p) == (i + j + k) / (2 * p) {
+                            comparators.push((i + j, i + j + k));
+                        }
+                    }
+                }
+                k /= 2;
+            }
+            p *= 2;
+        }
+
+        Self::cut(n, comparators)
+    }
+
+    // A network for `n` wires from one for the next power of two. The
+    // missing inputs act as +infinity, which every comparator leaves on
+    // the higher wire, so comparators touching them never swap and can go.
+    fn cut(n: usize, comparators: Vec<(usize, usize)>) -> Self {
+        Self::new(n, comparators.into_iter().filter(|&(_, b)| b < n).collect())
+    }
+
+    /// The smallest known network for `n` wires, or `None` past
+    /// [`OPTIMAL_MAX`].
+    pub fn optimal(n: usize) -> Option<Self> {
+        let comparators = OPTIMAL.get(n)?;
+        Some(Self::new(
+            n,
+            comparators
+                .iter()
+                .map(|&(a, b)| (a as usize, b as usize))
+                .collect(),
+        ))
+    }
+
+    /// Number of wires.
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    pub fn comparators(&self) -> &[(usize, usize)] {
+        &self.comparators
+    }
+
+    /// Number of comparators.
+    pub fn size(&self) -> usize {
+        self.comparators.len()
+    }
+
+    /// The comparators grouped into layers, each put in the earliest layer
+    /// after every comparator before it on the same wires. Comparators in a
+    /// layer touch separate wires, so they can run at once.
+    pub fn layers(&self) -> Vec<Vec<(usize, usize)>> {
+        let mut layers: Vec<Vec<(usize, usize)>> = vec![];
+        // The number of layers each wire is already used in.
+        let mut used = vec![0; self.n];
+        for &(a, b) in &self.comparators {
+            let layer = used[a].max(used[b]);
+            if layer == layers.len() {
+                layers.push(vec![]);
+            }
+            layers[layer].push((a, b));
+            used[a] = layer + 1;
+            used[b] = layer + 1;
+        }
+
+        layers
+    }
+
+    /// Number of layers, the time it takes with every layer in parallel.
+    pub fn depth(&self) -> usize {
+        self.layers().len()
+    }
+
+    /// Runs the network on `s`.
+    ///
+    /// Panics if `s` isn't `n` long.
+    pub fn apply<T: Ord>(&self, s: &mut [T]) {
+        self.apply_by(s, T::cmp);
+    }
+
+    /// Runs the network on `s` with a custom comparator.
+    ///
+    /// Panics if `s` isn't `n` long.
+    pub fn apply_by<T, F>(&self, s: &mut [T], mut cmp: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        assert_eq!(s.len(), self.n, "slice length doesn't match the network");
+        for &(a, b) in &self.comparators {
+            compare_exchange(s, a, b, &mut cmp);
+        }
+    }
+
+    /// Checks that the network sorts every input, by the 0-1 principle: a
+    /// network that sorts every sequence of 0s and 1s sorts everything.
+    /// That's 2^n inputs rather than n!, run 64 at a time as the bits of a
+    /// `u64` per wire, where a comparator is an `and` and an `or`.
+    ///
+    /// Returns the first 0-1 input it doesn't sort. Panics past 32 wires,
+    /// which would take far too long anyway.
+    pub fn verify(&self) -> Result<(), Vec<u8>> {
+        // Bit i of each is input i's value on that wire, for the first six
+        // wires; later wires are constant across a block of 64 inputs.
+        const LOW: [u64; 6] = [
+            0xaaaa_aaaa_aaaa_aaaa,
+            0xcccc_cccc_cccc_cccc,
+            0xf0f0_f0f0_f0f0_f0f0,
+            0xff00_ff00_ff00_ff00,
+            0xffff_0000_ffff_0000,
+            0xffff_ffff_0000_0000,
+        ];
+        assert!(self.n <= 32, "too many wires to check every 0-1 input");
+
+        let inputs = 1u64 << self.n;
+        let live = if inputs < 64 { (1 << inputs) - 1 } else { !0 };
+        let mut wires = vec![0u64; self.n];
+        for block in 0..inputs.div_ceil(64) {
+            for (i, w) in wires.iter_mut().enumerate() {
+                *w = match i {
+                    0..6 => LOW[i],
+                    _ if block >> (i - 6) & 1 == 1 => !0,
+                    _ => 0,
+                };
+            }
+            for &(a, b) in &self.comparators {
+                let (x, y) = (wires[a], wires[b]);
+                wires[a] = x & y;
+                wires[b] = x | y;
+            }
+
+            // A 1 above a 0 is out of order.
+            let unsorted = wires.windows(2).fold(0, |bad, w| bad | (w[0] & !w[1])) & live;
+            if unsorted != 0 {
+                let input = block * 64 + unsorted.trailing_zeros() as u64;
+                return Err((0..self.n).map(|i| (input >> i & 1) as u8).collect());
+            }
+        }
+
+        Ok(())
+    }
+
+    // The comparators in drawing order: each layer split into columns
+    // whose comparators don't overlap, so their lines don't cross.
+    fn columns(&self) -> Vec<Vec<(usize, usize)>> {
+        let mut columns = vec![];
+        for layer in self.layers() {
+            let start = columns.len();
+            for (a, b) in layer {
+                let free = columns[start..]
+                    .iter()
+                    .position(|c: &Vec<(usize, usize)>| c.iter().all(|&(x, y)| b < x || y < a));
+                match free {
+                    Some(i) => columns[start + i].push((a, b)),
+                    None => columns.push(vec![(a, b)]),
+                }
+            }
+        }
+
+        columns
+    }
+}
+
+/// Draws the network in Knuth's style: a row per wire, top to bottom, and
+/// each comparator a vertical line joining two dots, left to right in the
+/// order they run.
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.n == 0 {
+            return Ok(());
+        }
+
+        let columns = self.columns();
+        // Wires on even rows, with gaps between them.
+        let mut grid = vec![vec![' '; 3 * columns.len() + 2]; 2 * self.n - 1];
+        for row in grid.iter_mut().step_by(2) {
+            row.fill('─');
+        }
+        for (c, column) in columns.iter().enumerate() {
+            let x = 3 * c + 2;
+            for &(a, b) in column {
+                grid[2 * a][x] = '●';
+                grid[2 * b][x] = '●';
+                for (r, row) in grid.iter_mut().enumerate().take(2 * b).skip(2 * a + 1) {
+                    row[x] = if r % 2 == 0 { '┼' } else { '│' };
+                }
+            }
+        }
+
+        let width = (self.n - 1).to_string().len();
+        for (r, row) in grid.iter().enumerate() {
+            let line: String = row.iter().collect();
+            if r % 2 == 0 {
+                writeln!(f, "{:>width$} {line}", r / 2)?;
+            } else {
+                writeln!(f, "{:width$} {}", "", line.trim_end())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn compare_exchange<T, F>(s: &mut [T], a: usize, b: usize, cmp: &mut F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    if cmp(&s[a], &s[b]) == Ordering::Greater {
+        stats::swap(s, a, b);
+    }
+}
+
+/// Sorts a short slice with its [`Network::optimal`] network, for
+/// quicksort's small partitions.
+pub(crate) fn small_sort_by<T, F>(s: &mut [T], cmp: &mut F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    for &(a, b) in OPTIMAL[s.len()] {
+        compare_exchange(s, a as usize, b as usize, cmp);
+    }
+}
+
+/// Sorts up to [`OPTIMAL_MAX`] elements with the smallest known network,
+/// branch-free: each comparator is a `min` and a `max`, which compile to
+/// conditional moves for integers. Longer slices go to
+/// [`introsort`](crate::introsort).
+pub fn network_sort<T: Copy + Ord>(s: &mut [T]) {
+    // A copy per length, so the comparators are constants and the loop
+    // unrolls into straight-line code.
+    match s.len() {
+        0 | 1 => {}
+        2 => fixed::<T, 2>(s),
+        3 => fixed::<T, 3>(s),
+        4 => fixed::<T, 4>(s),
+        5 => fixed::<T, 5>(s),
+        6 => fixed::<T, 6>(s),
+        7 => fixed::<T, 7>(s),
+        8 => fixed::<T, 8>(s),
+        9 => fixed::<T, 9>(s),
+        10 => fixed::<T, 10>(s),
+        11 => fixed::<T, 11>(s),
+        12 => fixed::<T, 12>(s),
+        13 => fixed::<T, 13>(s),
+        14 => fixed::<T, 14>(s),
+        15 => fixed::<T, 15>(s),
+        16 => fixed::<T, 16>(s),
+        _ => introsort(s),
+    }
+}
+
+#[inline(always)]
+fn fixed<T: Copy + Ord, const N: usize>(s: &mut [T]) {
+    // On a local copy the values can stay in registers.
+    let mut v: [T; N] = s.try_into().expect("slice length doesn't match");
+    for &(a, b) in OPTIMAL[N] {
+        let (a, b) = (a as usize, b as usize);
+        let (x, y) = (v[a], v[b]);
+        v[a] = x.min(y);
+        v[b] = x.max(y);
+    }
+    s.copy_from_slice(&v);
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+    use crate::{check_sort, Distribution};
+
+    // The smallest known sizes and depths for 0 to 16 wires.
+    const SIZES: [usize; 17] = [0, 0, 1, 3, 5, 9, 12, 16, 19, 25, 29, 35, 39, 45, 51, 56, 60];
+    const DEPTHS: [usize; 17] = [0, 0, 1, 3, 3, 5, 5, 6, 6, 7, 8, 8, 9, 10, 10, 10, 10];
+
+    #[test]
+    fn test_optimal() {
+        for n in 0..=OPTIMAL_MAX {
+            let net = Network::optimal(n).expect("missing network");
+            assert_eq!(net.verify(), Ok(()), "n = {n}");
+            assert_eq!(net.size(), SIZES[n], "n = {n}");
+            assert_eq!(net.depth(), DEPTHS[n], "n = {n}");
+        }
+        assert!(Network::optimal(OPTIMAL_MAX + 1).is_none());
+    }
+
+    #[test]
+    fn test_batcher() {
+        for n in 0..=20 {
+            assert_eq!(Network::bitonic(n).verify(), Ok(()), "bitonic n = {n}");
+            assert_eq!(
+                Network::odd_even_merge(n).verify(),
+                Ok(()),
+                "odd-even n = {n}"
+            );
+        }
+
+        // Knuth's table: n log² n / 4 for bitonic, fewer for odd-even.
+        for (n, bitonic, odd_even, depth) in [(4, 6, 5, 3), (8, 24, 19, 6), (16, 80, 63, 10)] {
+            assert_eq!(Network::bitonic(n).size(), bitonic);
+            assert_eq!(Network::odd_even_merge(n).size(), odd_even);
+            assert_eq!(Network::bitonic(n).depth(), depth);
+            assert_eq!(Network::odd_even_merge(n).depth(), depth);
+        }
+    }
+
+    #[test]
+    fn test_verify() {
+        // Missing its last comparator, the 4-wire network can't sort 0101.
+        let mut comparators = Network::optimal(4).expect("missing network").comparators;
+        comparators.pop();
+        let broken = Network::new(4, comparators);
+        let input = broken.verify().expect_err("verified a broken network");
+        let mut v = input.clone();
+        broken.apply(&mut v);
+        assert!(v.windows(2).any(|w| w[0] > w[1]), "{input:?}");
+
+        // Far too few comparators.
+        assert!(Network::new(7, vec![(0, 1), (1, 2)]).verify().is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        for n in 0..=20 {
+            let nets = [
+                Network::bitonic(n),
+                Network::odd_even_merge(n),
+                Network::optimal(n).unwrap_or_else(|| Network::bitonic(n)),
+            ];
+            for dist in Distribution::all() {
+                let v = dist.generate(n, 7);
+                for net in &nets {
+                    let mut got = v.clone();
+                    net.apply(&mut got);
+                    assert_eq!(check_sort(&v, &got), Ok(()), "{dist} n = {n}");
+                }
+
+                let mut got = v.clone();
+                network_sort(&mut got);
+                assert_eq!(check_sort(&v, &got), Ok(()), "{dist} n = {n}");
+
+                let mut desc = v.clone();
+                nets[0].apply_by(&mut desc, |a, b| b.cmp(a));
+                assert!(desc.windows(2).all(|w| w[0] >= w[1]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        let net = Network::optimal(4).expect("missing network");
+        assert_eq!(
+            net.to_string(),
+            "\
+0 ──●─────●─────
+    │     │
+1 ──┼──●──●──●──
+    │  │     │
+2 ──●──┼──●──●──
+       │  │
+3 ─────●──●─────
+"
+        );
+    }
+}
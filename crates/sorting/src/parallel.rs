@@ -0,0 +1,348 @@
+//! Parallel sorts on `std::thread::scope`, so they can borrow the slice
+//! without `Arc` or `unsafe`.
+//!
+//! Each one splits the work until it runs out of threads or the pieces get
+//! shorter than [`ParallelConfig::cutoff`], then hands the pieces to the
+//! sequential version. A [`crate::trace`] follows them onto the threads they
+//! spawn.
+use std::cmp::Ordering;
+use std::thread;
+
+use crate::introsort::{choose_pivot, partition3};
+use crate::merge_sort::merge;
+use crate::trace::tracer;
+use crate::{introsort_by, merge_sort_by, radix_sort, stats, RadixKey, Sorter};
+
+/// How far to split a parallel sort.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParallelConfig {
+    /// The most threads to run at once, including the caller's.
+    pub threads: usize,
+    /// Slices shorter than this are sorted sequentially, since spawning a
+    /// thread costs more than it saves.
+    pub cutoff: usize,
+}
+
+impl Default for ParallelConfig {
+    /// One thread per core and a cutoff of 16K elements.
+    fn default() -> Self {
+        Self {
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            cutoff: 1 << 14,
+        }
+    }
+}
+
+/// Parallel introsort. Not stable.
+pub fn par_quicksort<T: Ord + Send>(s: &mut [T], cfg: ParallelConfig) {
+    par_quicksort_by(s, cfg, T::cmp);
+}
+
+/// Parallel introsort with a custom comparator, which is shared between
+/// threads so it has to be `Fn + Sync`.
+pub fn par_quicksort_by<T, F>(s: &mut [T], cfg: ParallelConfig, cmp: F)
+where
+    T: Send,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    let depth = 2 * (usize::BITS - s.len().leading_zeros());
+    par_quicksort_rec(s, cfg.threads, depth, cfg.cutoff, &cmp);
+}
+
+fn par_quicksort_rec<T, F>(s: &mut [T], threads: usize, depth: u32, cutoff: usize, cmp: &F)
+where
+    T: Send,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    // Out of threads, too small to be worth it, or the pivots keep going
+    // bad: introsort has its own heapsort fallback for the last one.
+    if threads <= 1 || s.len() <= cutoff || depth == 0 {
+        introsort_by(s, cmp);
+        return;
+    }
+
+    let mut c = cmp;
+    let p = choose_pivot(s, &mut c);
+    stats::swap(s, 0, p);
+    let (lt, gt) = partition3(s, &mut c);
+
+    let (left, rest) = s.split_at_mut(lt);
+    let right = &mut rest[gt - lt..];
+    let half = threads / 2;
+    let tracer = tracer();
+    thread::scope(|scope| {
+        scope.spawn(|| tracer.run(|| par_quicksort_rec(left, half, depth - 1, cutoff, cmp)));
+        par_quicksort_rec(right, threads - half, depth - 1, cutoff, cmp);
+    });
+}
+
+/// Parallel merge sort. Stable.
+pub fn par_merge_sort<T: Ord + Clone + Send>(s: &mut [T], cfg: ParallelConfig) {
+    par_merge_sort_by(s, cfg, T::cmp);
+}
+
+/// Parallel merge sort with a custom comparator.
+pub fn par_merge_sort_by<T, F>(s: &mut [T], cfg: ParallelConfig, cmp: F)
+where
+    T: Clone + Send,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    par_merge_sort_rec(s, cfg.threads, cfg.cutoff, &cmp);
+}
+
+fn par_merge_sort_rec<T, F>(s: &mut [T], threads: usize, cutoff: usize, cmp: &F)
+where
+    T: Clone + Send,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    if threads <= 1 || s.len() <= cutoff {
+        merge_sort_by(s, cmp);
+        return;
+    }
+
+    let mid = s.len() / 2;
+    let (left, right) = s.split_at_mut(mid);
+    let half = threads / 2;
+    let tracer = tracer();
+    thread::scope(|scope| {
+        scope.spawn(|| tracer.run(|| par_merge_sort_rec(left, half, cutoff, cmp)));
+        par_merge_sort_rec(right, threads - half, cutoff, cmp);
+    });
+
+    let mut buf = Vec::with_capacity(mid);
+    merge(s, mid, &mut buf, &mut &cmp);
+}
+
+/// Parallel LSD radix sort. Each pass has every thread build a histogram of
+/// its own chunk, then scatter that chunk into the slots the combined
+/// histograms give it. Stable.
+pub fn par_radix_sort<K: RadixKey + Send + Sync>(s: &mut [K], cfg: ParallelConfig) {
+    if cfg.threads <= 1 || s.len() <= cfg.cutoff {
+        radix_sort(s);
+        return;
+    }
+
+    let n = s.len();
+    let chunk_len = n.div_ceil(cfg.threads);
+    let mut buf = s.to_vec();
+    let mut from_buf = false;
+
+    for byte in 0..K::BYTES {
+        let (src, dst) = if from_buf {
+            (&*buf, &mut *s)
+        } else {
+            (&*s, &mut *buf)
+        };
+
+        let hists: Vec<[usize; 256]> = thread::scope(|scope| {
+            let handles: Vec<_> = src
+                .chunks(chunk_len)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        let mut hist = [0usize; 256];
+                        chunk
+                            .iter()
+                            .for_each(|k| hist[k.radix_byte(byte) as usize] += 1);
+                        hist
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|h| h.join().expect("histogram thread panicked"))
+                .collect()
+        });
+
+        // Every key has the same byte here, so the pass wouldn't move anything.
+        if (0..256).any(|b| hists.iter().map(|h| h[b]).sum::<usize>() == n) {
+            continue;
+        }
+
+        // Cut dst into a piece per (byte value, thread), in that order, so
+        // each thread owns exactly the slots its chunk scatters into.
+        let mut pieces: Vec<Vec<&mut [K]>> =
+            hists.iter().map(|_| Vec::with_capacity(256)).collect();
+        let mut rest = dst;
+        for b in 0..256 {
+            for (t, hist) in hists.iter().enumerate() {
+                let (piece, tail) = std::mem::take(&mut rest).split_at_mut(hist[b]);
+                pieces[t].push(piece);
+                rest = tail;
+            }
+        }
+
+        let tracer = tracer();
+        thread::scope(|scope| {
+            for (chunk, mut piece) in src.chunks(chunk_len).zip(pieces) {
+                let tracer = tracer.clone();
+                scope.spawn(move || {
+                    tracer.run(|| {
+                        let mut next = [0usize; 256];
+                        for k in chunk {
+                            let b = k.radix_byte(byte) as usize;
+                            piece[b][next[b]] = *k;
+                            stats::copied_one(k, &piece[b][next[b]]);
+                            next[b] += 1;
+                        }
+                    })
+                });
+            }
+        });
+
+        from_buf = !from_buf;
+    }
+
+    if from_buf {
+        s.copy_from_slice(&buf);
+        stats::copied(&buf, s);
+    }
+}
+
+/// Parallel quicksort, see [`par_quicksort`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ParQuicksort(pub ParallelConfig);
+
+impl<T: Ord + Send> Sorter<T> for ParQuicksort {
+    fn name(&self) -> &'static str {
+        "par_quicksort"
+    }
+
+    fn sort(&self, v: &mut [T]) {
+        par_quicksort(v, self.0);
+    }
+}
+
+/// Parallel merge sort, see [`par_merge_sort`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ParMergeSort(pub ParallelConfig);
+
+impl<T: Ord + Clone + Send> Sorter<T> for ParMergeSort {
+    fn name(&self) -> &'static str {
+        "par_merge_sort"
+    }
+
+    fn sort(&self, v: &mut [T]) {
+        par_merge_sort(v, self.0);
+    }
+}
+
+/// Parallel LSD radix sort, see [`par_radix_sort`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ParRadixSort(pub ParallelConfig);
+
+impl<K: RadixKey + Send + Sync> Sorter<K> for ParRadixSort {
+    fn name(&self) -> &'static str {
+        "par_radix_sort"
+    }
+
+    fn sort(&self, v: &mut [K]) {
+        par_radix_sort(v, self.0);
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+    use crate::make_one;
+
+    // Small cutoffs so the tests actually split, whatever the machine.
+    const CONFIGS: [ParallelConfig; 4] = [
+        ParallelConfig {
+            threads: 1,
+            cutoff: 64,
+        },
+        ParallelConfig {
+            threads: 2,
+            cutoff: 64,
+        },
+        ParallelConfig {
+            threads: 5,
+            cutoff: 100,
+        },
+        ParallelConfig {
+            threads: 8,
+            cutoff: 1,
+        },
+    ];
+
+    fn inputs() -> Vec<Vec<i32>> {
+        let n = 50_000;
+        vec![
+            vec![],
+            vec![1],
+            make_one(n, n),
+            make_one(n, 10),
+            (0..n as i32).collect(),
+            (0..n as i32).rev().collect(),
+            vec![3; n],
+            make_one(n, n)
+                .into_iter()
+                .map(|x| x - n as i32 / 2)
+                .collect(),
+        ]
+    }
+
+    #[test]
+    fn test_par_sorts() {
+        for cfg in CONFIGS {
+            for v in inputs() {
+                let mut want = v.clone();
+                want.sort();
+
+                let sorters: [Box<dyn Sorter<i32>>; 3] = [
+                    Box::new(ParQuicksort(cfg)),
+                    Box::new(ParMergeSort(cfg)),
+                    Box::new(ParRadixSort(cfg)),
+                ];
+                for s in sorters {
+                    let mut got = v.clone();
+                    s.sort(&mut got);
+                    assert_eq!(got, want, "{} {cfg:?}", s.name());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_par_stable() {
+        let v: Vec<(u16, usize)> = make_one(20_000, 50)
+            .into_iter()
+            .enumerate()
+            .map(|(i, k)| (k as u16, i))
+            .collect();
+        let mut want = v.clone();
+        want.sort_by_key(|p| p.0);
+
+        for cfg in CONFIGS {
+            let mut got = v.clone();
+            par_merge_sort_by(&mut got, cfg, |a, b| a.0.cmp(&b.0));
+            assert_eq!(got, want);
+        }
+    }
+
+    #[test]
+    fn test_par_by() {
+        let cfg = CONFIGS[2];
+        let mut v = make_one(10_000, 1000);
+        par_quicksort_by(&mut v, cfg, |a, b| b.cmp(a));
+        assert!(v.windows(2).all(|w| w[0] >= w[1]));
+
+        let mut words: Vec<String> = (0..5_000)
+            .map(|i| format!("w{}", i * 7919 % 5000))
+            .collect();
+        let mut want = words.clone();
+        want.sort();
+        par_quicksort(&mut words, cfg);
+        assert_eq!(words, want);
+
+        let mut f: Vec<f64> = make_one(10_000, 1000)
+            .into_iter()
+            .map(|x| x as f64 / -7.0)
+            .collect();
+        let mut want = f.clone();
+        want.sort_by(f64::total_cmp);
+        par_radix_sort(&mut f, cfg);
+        assert_eq!(f, want);
+    }
+}
@@ -1,55 +1,280 @@
+//! Small, fast, seedable pseudorandom number generators. None of them are
+//! fit for cryptography.
+//!
+//! Each one has a `from_seed` constructor for repeatable runs, a `new`
+//! that seeds from the clock, and the same set of helpers: unbiased
+//! bounded integers, floats, [`shuffle`](Pcg32::shuffle) and
+//! [`sample`](Pcg32::sample). They also implement `rand::RngCore`, so
+//! anything that takes a `rand::Rng` takes them too.
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-pub struct Prng {
-    seed: u32,
+use rand::RngCore;
+
+/// The generator the rest of the crate uses.
+pub type Prng = Pcg32;
+
+/// SplitMix64, Vigna's mixer for a Weyl sequence. Very fast, passes
+/// BigCrush, and any seed is fine, which makes it the usual way to seed
+/// the other generators from one `u64`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SplitMix64 {
+    state: u64,
 }
 
-impl Default for Prng {
-    fn default() -> Self {
-        Self::new()
+impl SplitMix64 {
+    pub fn from_seed(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
     }
 }
 
-impl Prng {
-    pub fn new() -> Self {
-        let mut prng = Self { seed: 0 };
-        prng.randomize();
+/// xorshift128+, with 128 bits of state and a period of 2^128 - 1. The
+/// low bits are weaker than the high ones, so `next_u32` takes the top
+/// half.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Xorshift128Plus {
+    s: [u64; 2],
+}
 
-        prng
+impl Xorshift128Plus {
+    /// Fills the state from `seed` with [`SplitMix64`], which never gives
+    /// the all-zero state xorshift can't leave.
+    pub fn from_seed(seed: u64) -> Self {
+        let mut sm = SplitMix64::from_seed(seed);
+
+        Self {
+            s: [sm.next_u64(), sm.next_u64()],
+        }
     }
 
-    pub fn randomize(&mut self) {
-        self.seed = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("time went backwards")
-            .as_millis() as u32;
+    pub fn next_u64(&mut self) -> u64 {
+        let mut s1 = self.s[0];
+        let s0 = self.s[1];
+        let result = s0.wrapping_add(s1);
+
+        self.s[0] = s0;
+        s1 ^= s1 << 23;
+        self.s[1] = s1 ^ s0 ^ (s1 >> 18) ^ (s0 >> 5);
+
+        result
     }
 
     pub fn next_u32(&mut self) -> u32 {
-        self.seed = self.seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
-        self.seed %= 1 << 31;
+        (self.next_u64() >> 32) as u32
+    }
+}
+
+/// PCG32 (PCG-XSH-RR): a 64-bit LCG with a permuted 32-bit output, from
+/// O'Neill's paper. Generators with different streams give independent
+/// sequences from the same seed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pcg32 {
+    state: u64,
+    inc: u64,
+}
+
+impl Pcg32 {
+    const MULTIPLIER: u64 = 6_364_136_223_846_793_005;
 
-        self.seed
+    pub fn from_seed(seed: u64) -> Self {
+        Self::with_stream(seed, 0xda3e_39cb_94b9_5bdb)
     }
 
-    pub fn next_f64(&mut self) -> f64 {
-        self.next_u32() as f64 / (2147483647.0 + 1.0)
+    /// Seeds on one of 2^63 streams.
+    pub fn with_stream(seed: u64, stream: u64) -> Self {
+        let mut pcg = Self {
+            state: 0,
+            inc: (stream << 1) | 1,
+        };
+        pcg.step();
+        pcg.state = pcg.state.wrapping_add(seed);
+        pcg.step();
+
+        pcg
     }
 
-    pub fn next_i32(&mut self, min: i32, max: i32) -> i32 {
-        let range = (max - min) as f64;
+    fn step(&mut self) {
+        self.state = self
+            .state
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(self.inc);
+    }
 
-        (min as f64 + range * self.next_f64()) as i32
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.step();
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        ((self.next_u32() as u64) << 32) | self.next_u32() as u64
     }
 }
 
+// A different seed on every call, even within the same clock tick.
+fn clock_seed() -> u64 {
+    static CALLS: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("time went backwards")
+        .as_nanos() as u64;
+    let call = CALLS.fetch_add(1, Ordering::Relaxed);
+
+    SplitMix64::from_seed(nanos ^ call.rotate_left(32)).next_u64()
+}
+
+// The helpers every generator shares, built on its `from_seed`,
+// `next_u32` and `next_u64`.
+macro_rules! impl_prng {
+    ($($t:ty),*) => {$(
+        impl $t {
+            /// Seeded from the clock, so different on every call.
+            pub fn new() -> Self {
+                Self::from_seed(clock_seed())
+            }
+
+            /// Reseeds from the clock.
+            pub fn randomize(&mut self) {
+                *self = Self::new();
+            }
+
+            /// A uniform `u32` below `n`, without the bias of `% n`, by
+            /// Lemire's multiply-and-reject method.
+            ///
+            /// Panics if `n` is 0.
+            pub fn below_u32(&mut self, n: u32) -> u32 {
+                assert!(n > 0, "empty range");
+                let mut m = self.next_u32() as u64 * n as u64;
+                if (m as u32) < n {
+                    // Reject the 2^32 % n low values that would make some
+                    // results one more likely than the others.
+                    let threshold = n.wrapping_neg() % n;
+                    while (m as u32) < threshold {
+                        m = self.next_u32() as u64 * n as u64;
+                    }
+                }
+
+                (m >> 32) as u32
+            }
+
+            /// A uniform `u64` below `n`, see [`below_u32`](Self::below_u32).
+            pub fn below_u64(&mut self, n: u64) -> u64 {
+                assert!(n > 0, "empty range");
+                let mut m = self.next_u64() as u128 * n as u128;
+                if (m as u64) < n {
+                    let threshold = n.wrapping_neg() % n;
+                    while (m as u64) < threshold {
+                        m = self.next_u64() as u128 * n as u128;
+                    }
+                }
+
+                (m >> 64) as u64
+            }
+
+            /// A uniform index below `n`.
+            pub fn below(&mut self, n: usize) -> usize {
+                self.below_u64(n as u64) as usize
+            }
+
+            /// A uniform `i32` in `min..max`, or `min` if that's empty.
+            pub fn next_i32(&mut self, min: i32, max: i32) -> i32 {
+                if max <= min {
+                    return min;
+                }
+                let span = (max as i64 - min as i64) as u64;
+
+                (min as i64 + self.below_u64(span) as i64) as i32
+            }
+
+            /// A uniform `f64` in `[0, 1)`, with all 53 bits of precision.
+            pub fn next_f64(&mut self) -> f64 {
+                (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+            }
+
+            /// Shuffles `s` in place with Fisher-Yates, every order equally
+            /// likely.
+            pub fn shuffle<T>(&mut self, s: &mut [T]) {
+                for i in (1..s.len()).rev() {
+                    s.swap(i, self.below(i + 1));
+                }
+            }
+
+            /// `k` distinct elements of `s` picked uniformly at random, in
+            /// the order they appear in `s`, or all of them if `k` is
+            /// bigger. Knuth's selection sampling, so one pass and no
+            /// memory beyond the result.
+            pub fn sample<T: Clone>(&mut self, s: &[T], k: usize) -> Vec<T> {
+                let mut out = Vec::with_capacity(k.min(s.len()));
+                for (i, x) in s.iter().enumerate() {
+                    let (needed, left) = (k.saturating_sub(out.len()), s.len() - i);
+                    if needed > 0 && self.below(left) < needed {
+                        out.push(x.clone());
+                    }
+                }
+
+                out
+            }
+        }
+
+        impl Default for $t {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl RngCore for $t {
+            fn next_u32(&mut self) -> u32 {
+                <$t>::next_u32(self)
+            }
+
+            fn next_u64(&mut self) -> u64 {
+                <$t>::next_u64(self)
+            }
+
+            fn fill_bytes(&mut self, dest: &mut [u8]) {
+                for chunk in dest.chunks_mut(8) {
+                    let bytes = <$t>::next_u64(self).to_le_bytes();
+                    chunk.copy_from_slice(&bytes[..chunk.len()]);
+                }
+            }
+
+            fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+                self.fill_bytes(dest);
+                Ok(())
+            }
+        }
+    )*};
+}
+
+impl_prng!(SplitMix64, Xorshift128Plus, Pcg32);
+
 #[cfg(test)]
 mod common {
+    use rand::Rng;
+
     use super::*;
 
     #[test]
     fn test_new() {
-        let mut tut = Prng::new();
+        // Seeded: even a perfect generator draws the same value twice in a
+        // row now and then, which with a clock seed is a flaky failure.
+        let mut tut = Prng::from_seed(41);
 
         let mut prev: i32 = 0;
         for _ in 0..=100 {
@@ -71,12 +296,14 @@ mod common {
             assert_ne!(prev, got);
             prev = got;
         }
+
+        assert_ne!(Prng::new(), Prng::new());
     }
 
     #[test]
     fn test_prng() {
-        let mut rng1 = Prng { seed: 3 };
-        let mut rng2 = Prng { seed: 3 };
+        let mut rng1 = Prng::from_seed(3);
+        let mut rng2 = Prng::from_seed(3);
 
         for _ in 1..=100 {
             let v1 = rng1.next_i32(1, 1000);
@@ -84,4 +311,130 @@ mod common {
             assert_eq!(v1, v2);
         }
     }
+
+    #[test]
+    fn test_known_outputs() {
+        // From the reference implementations.
+        let mut pcg = Pcg32::with_stream(42, 54);
+        let got: Vec<u32> = (0..6).map(|_| pcg.next_u32()).collect();
+        assert_eq!(
+            got,
+            [0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e]
+        );
+
+        let mut sm = SplitMix64::from_seed(1234567);
+        let got: Vec<u64> = (0..3).map(|_| sm.next_u64()).collect();
+        assert_eq!(
+            got,
+            [
+                6457827717110365317,
+                3203168211198807973,
+                9817491932198370423
+            ]
+        );
+
+        let mut xs = Xorshift128Plus { s: [1, 2] };
+        assert_eq!(xs.next_u64(), 3);
+        // s1 = 1 ^ 1 << 23, then s1 ^ 2 ^ s1 >> 18 ^ 2 >> 5.
+        assert_eq!(xs.s, [2, 8388643]);
+    }
+
+    #[test]
+    fn test_bounded() {
+        let mut rng = Xorshift128Plus::from_seed(7);
+        let mut counts = [0usize; 6];
+        for _ in 0..60_000 {
+            counts[rng.below_u32(6) as usize] += 1;
+        }
+        assert!(
+            counts.iter().all(|&c| (9_000..11_000).contains(&c)),
+            "{counts:?}"
+        );
+
+        // Near the top of the range, where % n would be badly biased.
+        let n = (1 << 31) + (1 << 30);
+        let low = (0..10_000).filter(|_| rng.below_u32(n) < 1 << 30).count();
+        assert!((3_000..3_700).contains(&low), "{low}");
+
+        assert!((0..1000).all(|_| rng.below_u64(u64::MAX) < u64::MAX));
+        assert!((0..1000).all(|_| rng.below(1) == 0));
+
+        let got: Vec<i32> = (0..1000).map(|_| rng.next_i32(-3, 3)).collect();
+        assert_eq!(got.iter().min(), Some(&-3));
+        assert_eq!(got.iter().max(), Some(&2));
+        assert_eq!(rng.next_i32(5, 5), 5);
+        assert_eq!(
+            (0..100)
+                .map(|_| rng.next_i32(i32::MIN, i32::MAX))
+                .min()
+                .map(|x| x < 0),
+            Some(true)
+        );
+
+        assert!((0..1000)
+            .map(|_| rng.next_f64())
+            .all(|f| (0.0..1.0).contains(&f)));
+    }
+
+    #[test]
+    fn test_shuffle_and_sample() {
+        let mut rng = SplitMix64::from_seed(11);
+
+        let mut v: Vec<i32> = (0..100).collect();
+        rng.shuffle(&mut v);
+        assert_ne!(v, (0..100).collect::<Vec<_>>());
+        v.sort();
+        assert_eq!(v, (0..100).collect::<Vec<_>>());
+
+        // Each of the 6 orders of 3 elements turns up about equally often.
+        let mut counts = std::collections::HashMap::new();
+        for _ in 0..6000 {
+            let mut v = [0, 1, 2];
+            rng.shuffle(&mut v);
+            *counts.entry(v).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 6);
+        assert!(
+            counts.values().all(|&c| (850..1150).contains(&c)),
+            "{counts:?}"
+        );
+
+        let s: Vec<i32> = (0..50).collect();
+        for k in [0, 1, 10, 50, 80] {
+            let got = rng.sample(&s, k);
+            assert_eq!(got.len(), k.min(50));
+            assert!(got.windows(2).all(|w| w[0] < w[1]));
+        }
+
+        // Every element is equally likely to be picked.
+        let mut hits = [0usize; 10];
+        for _ in 0..10_000 {
+            rng.sample(&(0..10).collect::<Vec<_>>(), 3)
+                .into_iter()
+                .for_each(|i: usize| hits[i] += 1);
+        }
+        assert!(
+            hits.iter().all(|&h| (2_700..3_300).contains(&h)),
+            "{hits:?}"
+        );
+    }
+
+    #[test]
+    fn test_rng_core() {
+        fn roll(rng: &mut impl Rng) -> u8 {
+            rng.gen_range(1..=6)
+        }
+
+        let mut rng = Pcg32::from_seed(5);
+        assert!((0..100)
+            .map(|_| roll(&mut rng))
+            .all(|d| (1..=6).contains(&d)));
+
+        let mut a = [0u8; 13];
+        let mut b = [0u8; 13];
+        Xorshift128Plus::from_seed(1).fill_bytes(&mut a);
+        Xorshift128Plus::from_seed(1).fill_bytes(&mut b);
+        assert_eq!(a, b);
+        assert_ne!(a, [0; 13]);
+    }
 }
@@ -0,0 +1,128 @@
+use std::cmp::Ordering;
+
+use crate::{network, stats, OPTIMAL_MAX};
+
+// Divides the array into two particions using the
+// [Luomo Partition Scheme](https://en.wikipedia.org/wiki/Quicksort#Lomuto_partition_scheme).
+pub(crate) fn partition<T, F>(s: &mut [T], cmp: &mut F) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let lo = 0;
+    // The last element is the pivot.
+    let pvt = s.len() - 1;
+
+    // Temporary pivot index
+    let mut i = lo;
+
+    for j in 0..pvt {
+        // If the current element is less than or equal to the pivot
+        if cmp(&s[j], &s[pvt]) != Ordering::Greater {
+            // swap it
+            stats::swap(s, j, i);
+            // next indext to compare.
+            i += 1;
+        }
+    }
+
+    stats::swap(s, i, pvt);
+
+    i
+}
+
+/// Sorts a (portion of an) array, divides it into partitions,
+/// then sorts those. Partitions of up to 16 elements are sorted with
+/// the smallest known sorting network for their length.
+///
+/// Sorted or all-equal input goes quadratic and recurses once per element,
+/// which overflows the stack on large vectors; use [`crate::introsort`] for
+/// those.
+pub fn quicksort<T: Ord>(s: &mut [T]) {
+    quicksort_by(s, T::cmp);
+}
+
+/// Quicksort with a custom comparator, e.g. `|a, b| b.cmp(a)` for descending
+/// order.
+pub fn quicksort_by<T, F>(s: &mut [T], mut cmp: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    quicksort_rec(s, &mut cmp);
+}
+
+/// Quicksort on a key pulled out of each element, e.g. a struct field.
+/// The key is recomputed on every comparison, so keep it cheap.
+pub fn quicksort_by_key<T, K, F>(s: &mut [T], mut key: F)
+where
+    K: Ord,
+    F: FnMut(&T) -> K,
+{
+    quicksort_by(s, |a, b| key(a).cmp(&key(b)));
+}
+
+fn quicksort_rec<T, F>(s: &mut [T], cmp: &mut F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    // Short slices go through a fixed sorting network instead.
+    if s.len() <= OPTIMAL_MAX {
+        network::small_sort_by(s, cmp);
+    } else {
+        let _depth = stats::enter();
+        // Partition array and get the pivot index
+        let p = partition(s, cmp);
+
+        // Sort two partitions
+        quicksort_rec(&mut s[0..p], cmp); // Left side of pivot
+        quicksort_rec(&mut s[(p + 1)..], cmp); // Right side of pivot
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+    use crate::{check_sort, make_one};
+
+    #[test]
+    fn test_quicksort() {
+        for i in 50..1000 {
+            let v = make_one(i, i * 2);
+            let mut got = v.clone();
+            quicksort(&mut got[..]);
+            assert_eq!(check_sort(&v, &got), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_quicksort_generic() {
+        let mut words = vec!["pear", "apple", "fig", "banana", "cherry"];
+        quicksort(&mut words);
+        assert_eq!(words, ["apple", "banana", "cherry", "fig", "pear"]);
+
+        let mut empty: Vec<String> = vec![];
+        quicksort(&mut empty);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_quicksort_by() {
+        let mut v = make_one(500, 100);
+        quicksort_by(&mut v, |a, b| b.cmp(a));
+        assert!(v.windows(2).all(|w| w[0] >= w[1]));
+
+        // Floats only have a partial order, so pick how to compare them.
+        let mut f = vec![2.5, -1.0, 0.0, 10.25, 3.0];
+        quicksort_by(&mut f, f64::total_cmp);
+        assert_eq!(f, [-1.0, 0.0, 2.5, 3.0, 10.25]);
+    }
+
+    #[test]
+    fn test_quicksort_by_key() {
+        let mut people = vec![("carol", 41), ("alice", 30), ("bob", 25)];
+        quicksort_by_key(&mut people, |p| p.1);
+        assert_eq!(people, [("bob", 25), ("alice", 30), ("carol", 41)]);
+
+        quicksort_by_key(&mut people, |p| p.0);
+        assert_eq!(people, [("alice", 30), ("bob", 25), ("carol", 41)]);
+    }
+}
@@ -0,0 +1,406 @@
+//! Radix sorts, which never compare two elements.
+//!
+//! [`radix_sort`] is a least-significant-digit sort for every integer width
+//! and for floats, a byte per pass, so it's O(n * size_of::<T>()) however
+//! big or negative the values are. [`msd_radix_sort`] is an in-place
+//! most-significant-digit (American flag) sort for byte strings.
+use crate::{stats, Sorter};
+
+// Buckets this small are insertion sorted instead of split again.
+const MSD_CUTOFF: usize = 32;
+
+/// A fixed-width key that sorts the same way as its bytes do, most
+/// significant first, when read as unsigned.
+pub trait RadixKey: Copy {
+    const BYTES: usize;
+
+    /// Byte `i` of the key, where 0 is the least significant.
+    fn radix_byte(self, i: usize) -> u8;
+}
+
+macro_rules! impl_radix_key_unsigned {
+    ($($t:ty),*) => {$(
+        impl RadixKey for $t {
+            const BYTES: usize = std::mem::size_of::<$t>();
+
+            fn radix_byte(self, i: usize) -> u8 {
+                (self >> (8 * i)) as u8
+            }
+        }
+    )*};
+}
+
+// Flipping the sign bit moves negative numbers below positive ones, with
+// two's complement keeping each half in order.
+macro_rules! impl_radix_key_signed {
+    ($($t:ty => $u:ty),*) => {$(
+        impl RadixKey for $t {
+            const BYTES: usize = std::mem::size_of::<$t>();
+
+            fn radix_byte(self, i: usize) -> u8 {
+                let flipped = (self as $u) ^ (1 << (<$u>::BITS - 1));
+                (flipped >> (8 * i)) as u8
+            }
+        }
+    )*};
+}
+
+// Positive floats already order like their bits. Negative ones order
+// backwards, so flip all their bits; flipping just the sign bit of the
+// positives then puts them above. This is the IEEE 754 total order:
+// -NaN < -inf < ... < -0.0 < 0.0 < ... < inf < NaN.
+macro_rules! impl_radix_key_float {
+    ($($t:ty => $u:ty),*) => {$(
+        impl RadixKey for $t {
+            const BYTES: usize = std::mem::size_of::<$t>();
+
+            fn radix_byte(self, i: usize) -> u8 {
+                let bits = self.to_bits();
+                let sign = 1 << (<$u>::BITS - 1);
+                let flipped = if bits & sign == 0 { bits | sign } else { !bits };
+                (flipped >> (8 * i)) as u8
+            }
+        }
+    )*};
+}
+
+impl_radix_key_unsigned!(u8, u16, u32, u64, u128, usize);
+impl_radix_key_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize);
+impl_radix_key_float!(f32 => u32, f64 => u64);
+
+/// LSD radix sort. Stable, O(n) per key byte.
+pub fn radix_sort<K: RadixKey>(s: &mut [K]) {
+    let mut buf = s.to_vec();
+    lsd(s, &mut buf, |k| *k);
+}
+
+/// LSD radix sort on a key pulled out of each element, e.g. a struct
+/// field. Stable. Each key is computed once.
+pub fn radix_sort_by_key<T, K, F>(s: &mut [T], mut key: F)
+where
+    K: RadixKey,
+    F: FnMut(&T) -> K,
+{
+    // Sort (key, index) pairs, then move the elements into place.
+    let mut pairs: Vec<(K, usize)> = s.iter().enumerate().map(|(i, x)| (key(x), i)).collect();
+    let mut buf = pairs.clone();
+    lsd(&mut pairs, &mut buf, |p| p.0);
+
+    let perm: Vec<usize> = pairs.into_iter().map(|p| p.1).collect();
+    apply_permutation(s, &perm);
+}
+
+// One counting pass per key byte, ping-ponging between `s` and `buf`.
+fn lsd<E, K>(s: &mut [E], buf: &mut [E], key: impl Fn(&E) -> K)
+where
+    E: Copy,
+    K: RadixKey,
+{
+    let n = s.len();
+    let mut from_buf = false;
+
+    for byte in 0..K::BYTES {
+        let (src, dst) = if from_buf {
+            (&*buf, &mut *s)
+        } else {
+            (&*s, &mut *buf)
+        };
+
+        let mut counts = [0usize; 256];
+        src.iter()
+            .for_each(|e| counts[key(e).radix_byte(byte) as usize] += 1);
+
+        // Every key has the same byte here, so the pass wouldn't move anything.
+        if counts.contains(&n) {
+            continue;
+        }
+
+        let mut next = [0usize; 256];
+        for b in 1..256 {
+            next[b] = next[b - 1] + counts[b - 1];
+        }
+        for e in src {
+            let b = key(e).radix_byte(byte) as usize;
+            dst[next[b]] = *e;
+            stats::copied_one(e, &dst[next[b]]);
+            next[b] += 1;
+        }
+
+        from_buf = !from_buf;
+    }
+
+    if from_buf {
+        s.copy_from_slice(buf);
+        stats::copied(buf, s);
+    }
+}
+
+// Rearranges `s` so that s[i] ends up holding the old s[perm[i]], by
+// walking each cycle of the permutation.
+pub(crate) fn apply_permutation<T>(s: &mut [T], perm: &[usize]) {
+    let mut done = vec![false; s.len()];
+    for start in 0..s.len() {
+        let mut cur = start;
+        while !done[cur] {
+            done[cur] = true;
+            let next = perm[cur];
+            if next == start {
+                break;
+            }
+            stats::swap(s, cur, next);
+            cur = next;
+        }
+    }
+}
+
+/// In-place MSD radix sort (American flag sort) for strings and byte
+/// strings, in byte order. Not stable. O(total bytes examined), which is
+/// at most the length of the distinguishing prefixes.
+pub fn msd_radix_sort<T: AsRef<[u8]>>(s: &mut [T]) {
+    msd_radix_sort_by_key(s, |x| x.as_ref());
+}
+
+/// MSD radix sort on a byte string pulled out of each element.
+pub fn msd_radix_sort_by_key<T, F>(s: &mut [T], key: F)
+where
+    F: Fn(&T) -> &[u8],
+{
+    american_flag(s, 0, &key);
+}
+
+// Bucket 0 is for keys that end before `depth`, so "ab" sorts before "abc".
+fn bucket(key: &[u8], depth: usize) -> usize {
+    key.get(depth).map_or(0, |&b| b as usize + 1)
+}
+
+fn american_flag<T, F>(s: &mut [T], depth: usize, key: &F)
+where
+    F: Fn(&T) -> &[u8],
+{
+    if s.len() <= MSD_CUTOFF {
+        for i in 1..s.len() {
+            let mut j = i;
+            while j > 0 && key(&s[j - 1])[depth..] > key(&s[j])[depth..] {
+                stats::swap(s, j - 1, j);
+                j -= 1;
+            }
+        }
+        return;
+    }
+
+    let mut counts = [0usize; 257];
+    s.iter().for_each(|x| counts[bucket(key(x), depth)] += 1);
+
+    let mut starts = [0usize; 257];
+    for b in 1..257 {
+        starts[b] = starts[b - 1] + counts[b - 1];
+    }
+
+    // Swap each element straight into the next free slot of its bucket.
+    let mut next = starts;
+    for b in 0..257 {
+        let end = starts[b] + counts[b];
+        while next[b] < end {
+            let c = bucket(key(&s[next[b]]), depth);
+            if c == b {
+                next[b] += 1;
+            } else {
+                stats::swap(s, next[b], next[c]);
+                next[c] += 1;
+            }
+        }
+    }
+
+    // Bucket 0 only holds equal keys, everything else goes a byte deeper.
+    for b in 1..257 {
+        if counts[b] > 1 {
+            american_flag(&mut s[starts[b]..starts[b] + counts[b]], depth + 1, key);
+        }
+    }
+}
+
+/// LSD radix sort, see [`radix_sort`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RadixSort;
+
+impl<K: RadixKey> Sorter<K> for RadixSort {
+    fn name(&self) -> &'static str {
+        "radix_sort"
+    }
+
+    fn sort(&self, v: &mut [K]) {
+        radix_sort(v);
+    }
+}
+
+/// MSD radix sort, see [`msd_radix_sort`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MsdRadixSort;
+
+impl<T: AsRef<[u8]>> Sorter<T> for MsdRadixSort {
+    fn name(&self) -> &'static str {
+        "msd_radix_sort"
+    }
+
+    fn sort(&self, v: &mut [T]) {
+        msd_radix_sort(v);
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+    use crate::Prng;
+
+    fn random_u128(prng: &mut Prng) -> u128 {
+        (0..5).fold(0, |acc, _| (acc << 31) ^ prng.next_u32() as u128)
+    }
+
+    macro_rules! check_ints {
+        ($prng:expr, $($t:ty),*) => {$(
+            let mut v: Vec<$t> = (0..2000).map(|_| random_u128($prng) as $t).collect();
+            v.extend([<$t>::MIN, <$t>::MAX, 0, 1, <$t>::MIN, <$t>::MAX]);
+            let mut want = v.clone();
+            want.sort();
+
+            radix_sort(&mut v);
+            assert_eq!(v, want, stringify!($t));
+        )*};
+    }
+
+    #[test]
+    fn test_radix_sort_ints() {
+        let mut prng = Prng::new();
+        check_ints!(&mut prng, u8, u16, u32, u64, u128, usize);
+        check_ints!(&mut prng, i8, i16, i32, i64, i128, isize);
+    }
+
+    #[test]
+    fn test_radix_sort_small_values() {
+        // Only the low byte differs, so the other passes get skipped.
+        let mut v: Vec<i64> = crate::make_one(1000, 200)
+            .into_iter()
+            .map(|x| x as i64 - 100)
+            .collect();
+        let mut want = v.clone();
+        want.sort();
+
+        radix_sort(&mut v);
+        assert_eq!(v, want);
+
+        let mut empty: Vec<u32> = vec![];
+        radix_sort(&mut empty);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_radix_sort_floats() {
+        let mut prng = Prng::new();
+        let mut v: Vec<f64> = (0..2000).map(|_| (prng.next_f64() - 0.5) * 1e6).collect();
+        v.extend([
+            0.0,
+            -0.0,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NAN,
+            -f64::NAN,
+            f64::MIN_POSITIVE,
+            -f64::MIN_POSITIVE,
+            f64::MAX,
+            f64::MIN,
+        ]);
+        let mut want = v.clone();
+        want.sort_by(f64::total_cmp);
+
+        radix_sort(&mut v);
+        let bits = |v: &[f64]| v.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+        assert_eq!(bits(&v), bits(&want));
+
+        let mut f: Vec<f32> = vec![3.5, -1.25, 0.0, -0.0, f32::NAN, -7.0];
+        radix_sort(&mut f);
+        assert_eq!(f[..5], [-7.0, -1.25, -0.0, 0.0, 3.5]);
+        assert!(f[5].is_nan());
+    }
+
+    #[test]
+    fn test_radix_sort_by_key_is_stable() {
+        let mut prng = Prng::new();
+        let mut v: Vec<(i16, usize)> = (0..5000)
+            .map(|i| (prng.next_i32(-20, 20) as i16, i))
+            .collect();
+        let mut want = v.clone();
+        want.sort_by_key(|p| p.0);
+
+        radix_sort_by_key(&mut v, |p| p.0);
+        assert_eq!(v, want);
+
+        #[derive(Debug, PartialEq)]
+        struct Reading {
+            sensor: String,
+            celsius: f32,
+        }
+        let mut readings: Vec<Reading> = [("a", 21.5), ("b", -3.0), ("c", 30.25)]
+            .iter()
+            .map(|&(s, c)| Reading {
+                sensor: s.to_string(),
+                celsius: c,
+            })
+            .collect();
+
+        radix_sort_by_key(&mut readings, |r| r.celsius);
+        let order: Vec<&str> = readings.iter().map(|r| r.sensor.as_str()).collect();
+        assert_eq!(order, ["b", "a", "c"]);
+    }
+
+    #[test]
+    fn test_msd_radix_sort() {
+        let mut prng = Prng::new();
+        let alphabet = b"abc\xff";
+        let mut v: Vec<Vec<u8>> = (0..3000)
+            .map(|_| {
+                let len = prng.next_i32(0, 12) as usize;
+                (0..len)
+                    .map(|_| alphabet[prng.next_i32(0, 4) as usize])
+                    .collect()
+            })
+            .collect();
+        let mut want = v.clone();
+        want.sort();
+
+        msd_radix_sort(&mut v);
+        assert_eq!(v, want);
+
+        let mut words: Vec<String> = "the quick brown fox jumps over the lazy dog then thereafter"
+            .split(' ')
+            .map(String::from)
+            .cycle()
+            .take(200)
+            .collect();
+        let mut want = words.clone();
+        want.sort();
+
+        msd_radix_sort(&mut words);
+        assert_eq!(words, want);
+
+        let mut strs = vec!["b", "", "ab", "a", "abc", ""];
+        msd_radix_sort(&mut strs);
+        assert_eq!(strs, ["", "", "a", "ab", "abc", "b"]);
+    }
+
+    #[test]
+    fn test_msd_radix_sort_by_key() {
+        let mut people: Vec<(String, u32)> = (0..100)
+            .map(|i| (format!("user{}", (i * 37) % 100), i))
+            .collect();
+
+        msd_radix_sort_by_key(&mut people, |p| p.0.as_bytes());
+        assert!(people.windows(2).all(|w| w[0].0 <= w[1].0));
+    }
+
+    #[test]
+    fn test_apply_permutation() {
+        let mut s = vec!['a', 'b', 'c', 'd', 'e'];
+        apply_permutation(&mut s, &[3, 0, 4, 1, 2]);
+        assert_eq!(s, ['d', 'a', 'e', 'b', 'c']);
+    }
+}
@@ -0,0 +1,442 @@
+//! Statistical tests for random number generators, after Knuth (TAOCP
+//! vol. 2, 3.3) and Marsaglia's Diehard.
+//!
+//! Each test draws from any `rand::RngCore` and returns a [`TestResult`]
+//! with the statistic and its p-value: the chance a truly random source
+//! would do at least this badly. A good generator gives p-values spread
+//! evenly over (0, 1); a bad one gives p-values stuck near 0 (or near 1,
+//! for a generator that's suspiciously too even).
+use std::fmt;
+
+use rand::RngCore;
+
+/// The outcome of one test.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestResult {
+    pub name: &'static str,
+    pub statistic: f64,
+    pub p_value: f64,
+}
+
+impl TestResult {
+    /// Whether the p-value is inside `(alpha, 1 - alpha)`.
+    pub fn passed(&self, alpha: f64) -> bool {
+        self.p_value > alpha && self.p_value < 1.0 - alpha
+    }
+}
+
+impl fmt::Display for TestResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:<20} statistic {:>12.4}  p = {:.4}",
+            self.name, self.statistic, self.p_value
+        )
+    }
+}
+
+/// Runs every test at sizes that take a fraction of a second.
+pub fn run_suite<R: RngCore + ?Sized>(rng: &mut R) -> Vec<TestResult> {
+    vec![
+        chi_square_test(rng, 1 << 17, 256),
+        ks_test(rng, 1 << 14),
+        runs_test(rng, 1 << 17),
+        serial_correlation_test(rng, 1 << 17),
+        gap_test(rng, 1 << 14),
+        birthday_spacings_test(rng, 200),
+    ]
+}
+
+// A uniform float in [0, 1) from the top 53 bits.
+fn uniform<R: RngCore + ?Sized>(rng: &mut R) -> f64 {
+    (rng.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+}
+
+/// Chi-square test that `samples` draws spread evenly over `buckets`
+/// buckets, using the top bits of `next_u32`.
+pub fn chi_square_test<R: RngCore + ?Sized>(
+    rng: &mut R,
+    samples: usize,
+    buckets: usize,
+) -> TestResult {
+    let mut counts = vec![0usize; buckets];
+    for _ in 0..samples {
+        counts[((rng.next_u32() as u64 * buckets as u64) >> 32) as usize] += 1;
+    }
+
+    let expected = vec![samples as f64 / buckets as f64; buckets];
+    let chi2 = chi_square(&counts, &expected);
+
+    TestResult {
+        name: "chi-square",
+        statistic: chi2,
+        p_value: chi_square_p(chi2, buckets - 1),
+    }
+}
+
+/// Kolmogorov-Smirnov test that `samples` floats come from the uniform
+/// distribution on [0, 1). The statistic is the biggest gap between their
+/// empirical CDF and the straight line.
+pub fn ks_test<R: RngCore + ?Sized>(rng: &mut R, samples: usize) -> TestResult {
+    let mut u: Vec<f64> = (0..samples).map(|_| uniform(rng)).collect();
+    u.sort_by(f64::total_cmp);
+
+    let n = samples as f64;
+    let d = u
+        .iter()
+        .enumerate()
+        .map(|(i, &x)| ((i + 1) as f64 / n - x).max(x - i as f64 / n))
+        .fold(0.0, f64::max);
+
+    // Stephens' correction makes the asymptotic distribution good for
+    // small n too.
+    let sqrt_n = n.sqrt();
+    TestResult {
+        name: "kolmogorov-smirnov",
+        statistic: d,
+        p_value: kolmogorov_q((sqrt_n + 0.12 + 0.11 / sqrt_n) * d),
+    }
+}
+
+/// Wald-Wolfowitz runs test: counts the runs of draws above and below one
+/// half, which is too few if the output is sticky and too many if it
+/// alternates.
+pub fn runs_test<R: RngCore + ?Sized>(rng: &mut R, samples: usize) -> TestResult {
+    let bits: Vec<bool> = (0..samples).map(|_| uniform(rng) < 0.5).collect();
+    let runs = 1 + bits.windows(2).filter(|w| w[0] != w[1]).count();
+
+    let n1 = bits.iter().filter(|&&b| b).count() as f64;
+    let n2 = samples as f64 - n1;
+    let n = samples as f64;
+    let mean = 2.0 * n1 * n2 / n + 1.0;
+    let var = 2.0 * n1 * n2 * (2.0 * n1 * n2 - n) / (n * n * (n - 1.0));
+    let z = (runs as f64 - mean) / var.sqrt();
+
+    TestResult {
+        name: "runs",
+        statistic: z,
+        p_value: normal_two_sided_p(z),
+    }
+}
+
+/// Lag-one serial correlation test: each draw should tell you nothing
+/// about the next. The correlation times `sqrt(n)` is about a standard
+/// normal.
+pub fn serial_correlation_test<R: RngCore + ?Sized>(rng: &mut R, samples: usize) -> TestResult {
+    let u: Vec<f64> = (0..samples).map(|_| uniform(rng)).collect();
+    let mean = u.iter().sum::<f64>() / samples as f64;
+
+    let cov: f64 = u.windows(2).map(|w| (w[0] - mean) * (w[1] - mean)).sum();
+    let var: f64 = u.iter().map(|x| (x - mean) * (x - mean)).sum();
+    let r = cov / var;
+    let z = r * (samples as f64).sqrt();
+
+    TestResult {
+        name: "serial correlation",
+        statistic: r,
+        p_value: normal_two_sided_p(z),
+    }
+}
+
+/// Knuth's gap test: the gaps between draws landing in `[0, 1/4)` should
+/// be geometrically distributed. Collects `gaps` of them and compares
+/// their lengths to that with a chi-square test.
+pub fn gap_test<R: RngCore + ?Sized>(rng: &mut R, gaps: usize) -> TestResult {
+    const P: f64 = 0.25;
+    // Gaps of 0 to MAX_GAP - 1, then one bucket for the longer ones.
+    const MAX_GAP: usize = 16;
+
+    let mut counts = [0usize; MAX_GAP + 1];
+    for _ in 0..gaps {
+        let mut len = 0;
+        while uniform(rng) >= P {
+            len += 1;
+        }
+        counts[len.min(MAX_GAP)] += 1;
+    }
+
+    let mut expected: Vec<f64> = (0..MAX_GAP)
+        .map(|r| gaps as f64 * P * (1.0 - P).powi(r as i32))
+        .collect();
+    expected.push(gaps as f64 * (1.0 - P).powi(MAX_GAP as i32));
+    let chi2 = chi_square(&counts, &expected);
+
+    TestResult {
+        name: "gap",
+        statistic: chi2,
+        p_value: chi_square_p(chi2, MAX_GAP),
+    }
+}
+
+/// Marsaglia's birthday spacings test. Each of `rounds` rounds picks 512
+/// birthdays in a year of 2^24 days from the top 24 bits of `next_u32`,
+/// sorts them and counts repeated gaps between neighbours. The total is
+/// about Poisson with mean 2 per round. Generators with lattice structure,
+/// like LCGs, repeat far too often.
+pub fn birthday_spacings_test<R: RngCore + ?Sized>(rng: &mut R, rounds: usize) -> TestResult {
+    const BIRTHDAYS: usize = 512;
+    const DAY_BITS: u32 = 24;
+
+    let mut repeats = 0;
+    let mut days = vec![0u32; BIRTHDAYS];
+    let mut spacings = vec![0u32; BIRTHDAYS];
+    for _ in 0..rounds {
+        days.iter_mut()
+            .for_each(|d| *d = rng.next_u32() >> (32 - DAY_BITS));
+        days.sort_unstable();
+
+        spacings[0] = days[0];
+        for i in 1..BIRTHDAYS {
+            spacings[i] = days[i] - days[i - 1];
+        }
+        spacings.sort_unstable();
+        repeats += spacings.windows(2).filter(|w| w[0] == w[1]).count();
+    }
+
+    // lambda = m^3 / 4n per round.
+    let lambda = rounds as f64 * (BIRTHDAYS as f64).powi(3) / (4.0 * (1u64 << DAY_BITS) as f64);
+    let k = repeats as f64;
+    // Two-sided: twice the smaller tail.
+    let below = regularized_gamma_q(k + 1.0, lambda);
+    let above = if repeats == 0 {
+        1.0
+    } else {
+        regularized_gamma_p(k, lambda)
+    };
+
+    TestResult {
+        name: "birthday spacings",
+        statistic: k,
+        p_value: (2.0 * below.min(above)).min(1.0),
+    }
+}
+
+fn chi_square(observed: &[usize], expected: &[f64]) -> f64 {
+    observed
+        .iter()
+        .zip(expected)
+        .map(|(&o, &e)| (o as f64 - e) * (o as f64 - e) / e)
+        .sum()
+}
+
+// P(X >= chi2) for X chi-square distributed with `df` degrees of freedom.
+fn chi_square_p(chi2: f64, df: usize) -> f64 {
+    regularized_gamma_q(df as f64 / 2.0, chi2 / 2.0)
+}
+
+// P(|Z| >= |z|) for a standard normal Z.
+fn normal_two_sided_p(z: f64) -> f64 {
+    // erfc(x) = Q(1/2, x^2) for x >= 0.
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    regularized_gamma_q(0.5, x * x)
+}
+
+// The Kolmogorov distribution's tail, P(K >= lambda).
+fn kolmogorov_q(lambda: f64) -> f64 {
+    if lambda < 0.2 {
+        return 1.0;
+    }
+
+    let mut sum = 0.0;
+    let mut sign = 1.0;
+    for k in 1..=100 {
+        let term = (-2.0 * (k * k) as f64 * lambda * lambda).exp();
+        sum += sign * term;
+        if term < 1e-12 {
+            break;
+        }
+        sign = -sign;
+    }
+
+    (2.0 * sum).clamp(0.0, 1.0)
+}
+
+// ln Γ(x) for x > 0, by Lanczos' approximation (g = 7, n = 9), good to
+// about 15 digits.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection, since the series is only good for x >= 1/2.
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let sum = COEFFS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFS[0], |acc, (i, c)| acc + c / (x + i as f64 + 1.0));
+
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+// The regularized lower incomplete gamma function P(a, x).
+fn regularized_gamma_p(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        0.0
+    } else if x < a + 1.0 {
+        gamma_series(a, x)
+    } else {
+        1.0 - gamma_continued_fraction(a, x)
+    }
+}
+
+// The regularized upper incomplete gamma function Q(a, x) = 1 - P(a, x).
+fn regularized_gamma_q(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        1.0
+    } else if x < a + 1.0 {
+        1.0 - gamma_series(a, x)
+    } else {
+        gamma_continued_fraction(a, x)
+    }
+}
+
+// P(a, x) by its power series, which converges quickly for x < a + 1.
+fn gamma_series(a: f64, x: f64) -> f64 {
+    let mut term = 1.0 / a;
+    let mut sum = term;
+    for n in 1..1000 {
+        term *= x / (a + n as f64);
+        sum += term;
+        if term.abs() < sum.abs() * 1e-15 {
+            break;
+        }
+    }
+
+    sum * (-x + a * x.ln() - ln_gamma(a)).exp()
+}
+
+// Q(a, x) by Lentz's method on its continued fraction, which converges
+// quickly for x >= a + 1.
+fn gamma_continued_fraction(a: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / TINY;
+    let mut d = 1.0 / b;
+    let mut h = d;
+    for i in 1..1000 {
+        let an = -(i as f64) * (i as f64 - a);
+        b += 2.0;
+        d = an * d + b;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = b + an / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+
+    (-x + a * x.ln() - ln_gamma(a)).exp() * h
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+    use crate::{Pcg32, Prng, SplitMix64, Xorshift128Plus};
+
+    // A 16-bit LCG from old textbooks, x' = 25173x + 13849 mod 2^16. Only
+    // 65536 distinct outputs, repeating in the same order, scaled up to
+    // fill 32 bits.
+    struct Lcg16(u16);
+
+    impl RngCore for Lcg16 {
+        fn next_u32(&mut self) -> u32 {
+            self.0 = self.0.wrapping_mul(25173).wrapping_add(13849);
+            (self.0 as u32) << 16
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            ((self.next_u32() as u64) << 32) | self.next_u32() as u64
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for chunk in dest.chunks_mut(4) {
+                chunk.copy_from_slice(&self.next_u32().to_le_bytes()[..chunk.len()]);
+            }
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9 * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn test_special_functions() {
+        assert!(close(ln_gamma(1.0), 0.0));
+        assert!(close(ln_gamma(5.0), 24f64.ln()));
+        assert!(close(ln_gamma(0.5), std::f64::consts::PI.sqrt().ln()));
+
+        // Known chi-square and normal tail values.
+        assert!((chi_square_p(3.841_458_820_694_124, 1) - 0.05).abs() < 1e-9);
+        assert!((chi_square_p(18.307_038_053_275_146, 10) - 0.05).abs() < 1e-9);
+        assert!((chi_square_p(2.0, 2) - (-1f64).exp()).abs() < 1e-12);
+        assert!((normal_two_sided_p(1.959_963_984_540_054) - 0.05).abs() < 1e-9);
+        assert_eq!(normal_two_sided_p(0.0), 1.0);
+
+        // P(K >= 1.358) is about 0.05.
+        assert!((kolmogorov_q(1.358) - 0.05).abs() < 1e-3);
+
+        assert!(close(
+            regularized_gamma_p(3.0, 2.0) + regularized_gamma_q(3.0, 2.0),
+            1.0
+        ));
+    }
+
+    #[test]
+    fn test_good_generators() {
+        let alpha = 1e-4;
+        let suites = [
+            ("Prng", run_suite(&mut Prng::from_seed(1))),
+            ("Pcg32", run_suite(&mut Pcg32::with_stream(2, 7))),
+            ("SplitMix64", run_suite(&mut SplitMix64::from_seed(3))),
+            (
+                "Xorshift128Plus",
+                run_suite(&mut Xorshift128Plus::from_seed(4)),
+            ),
+        ];
+
+        for (name, results) in suites {
+            for r in &results {
+                assert!(r.passed(alpha), "{name} failed {r}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_bad_generator() {
+        let results = run_suite(&mut Lcg16(1));
+        let failed: Vec<_> = results.iter().filter(|r| !r.passed(1e-4)).collect();
+        assert!(!failed.is_empty(), "Lcg16 passed everything");
+
+        // Its outputs sit on a coarse lattice, so spacings repeat far too
+        // often.
+        let bday = birthday_spacings_test(&mut Lcg16(1), 200);
+        assert!(bday.p_value < 1e-6, "{bday}");
+    }
+}
@@ -0,0 +1,337 @@
+//! Drawing a [`Trace`] as a bar chart, one bar per element, either
+//! animated in a terminal or as a sequence of SVG frames.
+//!
+//! Bars a step compared are drawn in one colour and bars it wrote to in
+//! another. Scratch space isn't drawn, so a merge shows up as the slice
+//! being written back from the buffer.
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use crate::{Event, Trace};
+
+// Partial blocks for the top of a terminal bar, in eighths.
+const EIGHTHS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+const SVG_WIDTH: usize = 800;
+const SVG_HEIGHT: usize = 400;
+
+/// How a step touched a bar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mark {
+    Untouched,
+    Compared,
+    Written,
+}
+
+impl Mark {
+    fn ansi(self) -> &'static str {
+        match self {
+            Mark::Untouched => "",
+            Mark::Compared => "\x1b[33m",
+            Mark::Written => "\x1b[31m",
+        }
+    }
+
+    fn svg(self) -> &'static str {
+        match self {
+            Mark::Untouched => "#4c72b0",
+            Mark::Compared => "#dd8452",
+            Mark::Written => "#c44e52",
+        }
+    }
+}
+
+/// One picture of the slice: its values after a step, and how that step
+/// touched each of them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub values: Vec<i64>,
+    pub marks: Vec<Mark>,
+    /// What happened, e.g. "step 3 of 10: swap 0 and 4".
+    pub caption: String,
+}
+
+/// The frames to draw for `trace`: the start, every `every`th step, and
+/// the end.
+pub fn frames<T: Clone + Into<i64>>(trace: &Trace<T>, every: usize) -> Vec<Frame> {
+    let every = every.max(1);
+    let total = trace.events.len();
+    let to_i64 = |s: &[T]| s.iter().cloned().map(Into::into).collect::<Vec<i64>>();
+
+    let mut frames = vec![Frame {
+        values: to_i64(&trace.initial),
+        marks: vec![Mark::Untouched; trace.initial.len()],
+        caption: format!("start, {total} steps"),
+    }];
+    let mut step = 0;
+    trace.replay(|e, s| {
+        step += 1;
+        if step % every == 0 || step == total {
+            frames.push(Frame {
+                values: to_i64(s),
+                marks: marks(e, s.len()),
+                caption: format!("step {step} of {total}: {e}"),
+            });
+        }
+    });
+
+    frames
+}
+
+fn marks(e: &Event, n: usize) -> Vec<Mark> {
+    let mut marks = vec![Mark::Untouched; n];
+    let mark = if e.writes() {
+        Mark::Written
+    } else {
+        Mark::Compared
+    };
+    for i in e.main_slots() {
+        marks[i] = mark;
+    }
+
+    marks
+}
+
+// Each value's share of the tallest bar, from 1 / (max - min + 1) for the
+// smallest up to 1 for the largest, so every bar shows.
+fn scale(values: &[i64]) -> impl Fn(i64) -> f64 {
+    let min = values.iter().copied().min().unwrap_or(0);
+    let max = values.iter().copied().max().unwrap_or(0);
+    let span = (max as f64 - min as f64) + 1.0;
+
+    move |v| (v as f64 - min as f64 + 1.0) / span
+}
+
+/// Draws `frame` as `height` rows of text, one column per value, with
+/// ANSI colours for the bars the step touched, then the caption.
+pub fn bar_chart(frame: &Frame, height: usize) -> String {
+    let scale = scale(&frame.values);
+    let eighths: Vec<usize> = frame
+        .values
+        .iter()
+        .map(|&v| (scale(v) * (8 * height) as f64).round() as usize)
+        .collect();
+
+    let mut out = String::new();
+    for row in (0..height).rev() {
+        for (&e, &mark) in eighths.iter().zip(&frame.marks) {
+            let block = EIGHTHS[e.saturating_sub(8 * row).min(8)];
+            if mark == Mark::Untouched {
+                out.push(block);
+            } else {
+                let _ = write!(out, "{}{block}\x1b[0m", mark.ansi());
+            }
+        }
+        out.push('\n');
+    }
+    out.push_str(&frame.caption);
+    out.push('\n');
+
+    out
+}
+
+/// Plays `trace` in a terminal, redrawing in place every `delay`.
+pub fn animate<T, W>(
+    trace: &Trace<T>,
+    out: &mut W,
+    height: usize,
+    every: usize,
+    delay: Duration,
+) -> io::Result<()>
+where
+    T: Clone + Into<i64>,
+    W: Write,
+{
+    // Clear the screen once, then just move the cursor home per frame.
+    write!(out, "\x1b[2J")?;
+    for frame in frames(trace, every) {
+        // Clearing to the end of the screen drops a longer old caption.
+        write!(out, "\x1b[H{}\x1b[J", bar_chart(&frame, height))?;
+        out.flush()?;
+        thread::sleep(delay);
+    }
+
+    Ok(())
+}
+
+/// Draws `frame` as a standalone SVG image.
+pub fn svg_frame(frame: &Frame) -> String {
+    let n = frame.values.len().max(1);
+    let scale = scale(&frame.values);
+    let chart = SVG_HEIGHT - 30;
+    let width = SVG_WIDTH as f64 / n as f64;
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{SVG_WIDTH}\" \
+         height=\"{SVG_HEIGHT}\" viewBox=\"0 0 {SVG_WIDTH} {SVG_HEIGHT}\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n"
+    );
+    for (i, (&v, &mark)) in frame.values.iter().zip(&frame.marks).enumerate() {
+        let h = scale(v) * chart as f64;
+        let _ = writeln!(
+            out,
+            "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{h:.2}\" fill=\"{}\"/>",
+            i as f64 * width,
+            chart as f64 - h,
+            (width - 1.0).max(0.5),
+            mark.svg()
+        );
+    }
+    let _ = writeln!(
+        out,
+        "<text x=\"4\" y=\"{}\" font-family=\"monospace\" font-size=\"14\">{}</text>\n</svg>",
+        SVG_HEIGHT - 10,
+        escape(&frame.caption)
+    );
+
+    out
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Writes every frame of `trace` to `dir` as frame_00000.svg and on, and
+/// an index.html that plays them every `delay`. Returns the number of
+/// frames.
+pub fn write_svg_frames<T: Clone + Into<i64>>(
+    trace: &Trace<T>,
+    dir: &Path,
+    every: usize,
+    delay: Duration,
+) -> io::Result<usize> {
+    fs::create_dir_all(dir)?;
+    let frames = frames(trace, every);
+    let mut names = vec![];
+    for (i, frame) in frames.iter().enumerate() {
+        let name = format!("frame_{i:05}.svg");
+        fs::write(dir.join(&name), svg_frame(frame))?;
+        names.push(format!("\"{name}\""));
+    }
+
+    let page = format!(
+        "<!DOCTYPE html>\n<html>\n<body>\n<img id=\"frame\" src=\"frame_00000.svg\">\n\
+         <script>\nconst frames = [{}];\nlet i = 0;\n\
+         setInterval(() => {{\n  i = (i + 1) % frames.length;\n  \
+         document.getElementById(\"frame\").src = frames[i];\n}}, {});\n\
+         </script>\n</body>\n</html>\n",
+        names.join(", "),
+        delay.as_millis().max(1)
+    );
+    fs::write(dir.join("index.html"), page)?;
+
+    Ok(frames.len())
+}
+
+/// One self-contained HTML page with every frame of `trace` inline, played
+/// in a loop every `delay`.
+pub fn html_page<T: Clone + Into<i64>>(
+    trace: &Trace<T>,
+    title: &str,
+    every: usize,
+    delay: Duration,
+) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<title>{}</title>\n\
+         <style>.frame {{ display: none; }} .frame.shown {{ display: block; }}</style>\n\
+         </head>\n<body>\n<h1>{}</h1>\n",
+        escape(title),
+        escape(title)
+    );
+    for (i, frame) in frames(trace, every).iter().enumerate() {
+        let class = if i == 0 { "frame shown" } else { "frame" };
+        let _ = writeln!(out, "<div class=\"{class}\">\n{}</div>", svg_frame(frame));
+    }
+    let _ = write!(
+        out,
+        "<script>\nconst frames = document.querySelectorAll(\".frame\");\nlet i = 0;\n\
+         setInterval(() => {{\n  frames[i].classList.remove(\"shown\");\n  \
+         i = (i + 1) % frames.length;\n  frames[i].classList.add(\"shown\");\n}}, {});\n\
+         </script>\n</body>\n</html>\n",
+        delay.as_millis().max(1)
+    );
+
+    out
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+    use crate::{trace_sorter, Quicksort, Slot};
+
+    fn example() -> Trace<i32> {
+        trace_sorter(&Quicksort, &[3, 1, 2])
+    }
+
+    #[test]
+    fn test_frames() {
+        let t = example();
+        let frames = frames(&t, 1);
+        assert_eq!(frames.len(), t.events.len() + 1);
+        assert_eq!(frames[0].values, [3, 1, 2]);
+        assert_eq!(frames.last().expect("no frames").values, [1, 2, 3]);
+        assert!(frames[1].caption.starts_with("step 1 of"));
+
+        // Sampling still keeps the start and end.
+        let sampled = super::frames(&t, 100);
+        assert_eq!(sampled.len(), 2);
+        assert_eq!(sampled[1].values, [1, 2, 3]);
+
+        let e = Event::Swap(Slot::Main(0), Slot::Scratch(0));
+        assert_eq!(marks(&e, 2), [Mark::Written, Mark::Untouched]);
+    }
+
+    #[test]
+    fn test_bar_chart() {
+        let frame = Frame {
+            values: vec![1, 2, 3],
+            marks: vec![Mark::Untouched; 3],
+            caption: "start".to_string(),
+        };
+        assert_eq!(bar_chart(&frame, 3), "  █\n ██\n███\nstart\n");
+        assert_eq!(bar_chart(&frame, 1), "▃▅█\nstart\n");
+
+        let frame = Frame {
+            marks: vec![Mark::Untouched, Mark::Compared, Mark::Written],
+            ..frame
+        };
+        let chart = bar_chart(&frame, 1);
+        assert!(chart.contains("\x1b[33m▅\x1b[0m"), "{chart:?}");
+        assert!(chart.contains("\x1b[31m█\x1b[0m"), "{chart:?}");
+
+        let mut out = vec![];
+        animate(&example(), &mut out, 4, 1, Duration::ZERO).expect("failed to animate");
+        let out = String::from_utf8(out).expect("not utf-8");
+        assert_eq!(out.matches("\x1b[H").count(), example().events.len() + 1);
+    }
+
+    #[test]
+    fn test_svg() {
+        let t = example();
+        let frames = frames(&t, 1);
+        let image = svg_frame(&frames[1]);
+        // The background and one bar per element.
+        assert_eq!(image.matches("<rect").count(), 4);
+        assert!(image.contains(Mark::Compared.svg()));
+
+        let page = html_page(&t, "a < b", 1, Duration::from_millis(20));
+        assert_eq!(page.matches("<svg").count(), frames.len());
+        assert!(page.contains("<title>a &lt; b</title>"));
+
+        let dir = std::env::temp_dir().join(format!("render-{}", std::process::id()));
+        let written = write_svg_frames(&t, &dir, 1, Duration::from_millis(120))
+            .expect("failed to write frames");
+        assert_eq!(written, frames.len());
+        assert!(dir.join("frame_00000.svg").exists());
+        let index = fs::read_to_string(dir.join("index.html")).expect("failed to read index");
+        assert!(index.contains(&format!("frame_{:05}.svg", written - 1)));
+        assert!(index.contains("}, 120);"));
+        fs::remove_dir_all(&dir).expect("failed to clean up");
+    }
+}
@@ -0,0 +1,432 @@
+//! Searching sorted slices, and unimodal functions.
+//!
+//! The `_by` versions take a closure comparing an element to the target,
+//! like `slice::binary_search_by`. Wrap it with [`Comparisons::counted`] to
+//! find out how many comparisons a search made.
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::ops::Range;
+
+/// Counts the calls made through the closures it wraps.
+#[derive(Debug, Default)]
+pub struct Comparisons(Cell<usize>);
+
+impl Comparisons {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wraps `f` so each call adds one to the count.
+    pub fn counted<'a, T: ?Sized, R>(
+        &'a self,
+        mut f: impl FnMut(&T) -> R + 'a,
+    ) -> impl FnMut(&T) -> R + 'a {
+        move |x| {
+            self.0.set(self.0.get() + 1);
+            f(x)
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.0.get()
+    }
+
+    pub fn reset(&self) {
+        self.0.set(0);
+    }
+}
+
+/// The index of the first element equal to `x`, checking each in turn.
+/// Doesn't need `s` to be sorted.
+pub fn linear_search<T: PartialEq>(s: &[T], x: &T) -> Option<usize> {
+    linear_search_by(s, |e| e == x)
+}
+
+/// The index of the first element matching `pred`.
+pub fn linear_search_by<T, F>(s: &[T], pred: F) -> Option<usize>
+where
+    F: FnMut(&T) -> bool,
+{
+    s.iter().position(pred)
+}
+
+/// Binary search for `x` in sorted `s`. `Ok` has the index of a match,
+/// `Err` the index where `x` would be inserted, as in
+/// `slice::binary_search`.
+pub fn binary_search<T: Ord>(s: &[T], x: &T) -> Result<usize, usize> {
+    binary_search_by(s, |e| e.cmp(x))
+}
+
+/// [`binary_search`] with `cmp` comparing each element to the target.
+/// Stops at the first match, so with duplicates it may find any of them;
+/// use [`equal_range_by`] to get them all.
+pub fn binary_search_by<T, F>(s: &[T], mut cmp: F) -> Result<usize, usize>
+where
+    F: FnMut(&T) -> Ordering,
+{
+    let (mut lo, mut hi) = (0, s.len());
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        match cmp(&s[mid]) {
+            Ordering::Less => lo = mid + 1,
+            Ordering::Greater => hi = mid,
+            Ordering::Equal => return Ok(mid),
+        }
+    }
+
+    Err(lo)
+}
+
+/// The index of the first element not less than `x`.
+pub fn lower_bound<T: Ord>(s: &[T], x: &T) -> usize {
+    lower_bound_by(s, |e| e.cmp(x))
+}
+
+/// [`lower_bound`] with `cmp` comparing each element to the target.
+pub fn lower_bound_by<T, F>(s: &[T], mut cmp: F) -> usize
+where
+    F: FnMut(&T) -> Ordering,
+{
+    partition_point(s, |e| cmp(e) == Ordering::Less)
+}
+
+/// The index of the first element greater than `x`.
+pub fn upper_bound<T: Ord>(s: &[T], x: &T) -> usize {
+    upper_bound_by(s, |e| e.cmp(x))
+}
+
+/// [`upper_bound`] with `cmp` comparing each element to the target.
+pub fn upper_bound_by<T, F>(s: &[T], mut cmp: F) -> usize
+where
+    F: FnMut(&T) -> Ordering,
+{
+    partition_point(s, |e| cmp(e) != Ordering::Greater)
+}
+
+/// The indexes of every element equal to `x`, empty (at the insertion
+/// point) if there are none.
+pub fn equal_range<T: Ord>(s: &[T], x: &T) -> Range<usize> {
+    equal_range_by(s, |e| e.cmp(x))
+}
+
+/// [`equal_range`] with `cmp` comparing each element to the target.
+pub fn equal_range_by<T, F>(s: &[T], mut cmp: F) -> Range<usize>
+where
+    F: FnMut(&T) -> Ordering,
+{
+    // Narrow down to the first match, then only the bounds either side of
+    // it are left to find.
+    let (mut lo, mut hi) = (0, s.len());
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        match cmp(&s[mid]) {
+            Ordering::Less => lo = mid + 1,
+            Ordering::Greater => hi = mid,
+            Ordering::Equal => {
+                let start = lo + lower_bound_by(&s[lo..mid], &mut cmp);
+                let end = mid + 1 + upper_bound_by(&s[mid + 1..hi], &mut cmp);
+                return start..end;
+            }
+        }
+    }
+
+    lo..lo
+}
+
+/// Exponential (galloping) search for `x` in sorted `s`, with the same
+/// result as [`binary_search`]. Probes indexes 0, 1, 3, 7, ... before
+/// bisecting, so it's O(log i) for a target near index i, which beats a
+/// plain binary search when targets tend to be near the front.
+pub fn exponential_search<T: Ord>(s: &[T], x: &T) -> Result<usize, usize> {
+    exponential_search_by(s, |e| e.cmp(x))
+}
+
+/// [`exponential_search`] with `cmp` comparing each element to the target.
+pub fn exponential_search_by<T, F>(s: &[T], mut cmp: F) -> Result<usize, usize>
+where
+    F: FnMut(&T) -> Ordering,
+{
+    let mut found = false;
+    let i = gallop(s, |e| match cmp(e) {
+        Ordering::Less => true,
+        Ordering::Equal => {
+            found = true;
+            false
+        }
+        Ordering::Greater => false,
+    });
+
+    // The first element not less than the target is a match exactly when
+    // one of the probes saw one.
+    if found {
+        Ok(i)
+    } else {
+        Err(i)
+    }
+}
+
+// The number of leading elements of `s` for which `pred` holds, where
+// `pred` is true for a prefix and false after. Probes 1, 2, 4, ... then
+// binary searches the last gap, so it's O(log k) for an answer of k.
+pub(crate) fn gallop<T>(s: &[T], mut pred: impl FnMut(&T) -> bool) -> usize {
+    let mut hi = 1;
+    while hi <= s.len() && pred(&s[hi - 1]) {
+        hi *= 2;
+    }
+
+    let lo = hi / 2;
+    let end = (hi - 1).min(s.len());
+
+    lo + partition_point(&s[lo..end], pred)
+}
+
+// `slice::partition_point`, spelled out so every probe goes through `pred`
+// and the comparison counts line up with `binary_search_by`.
+fn partition_point<T>(s: &[T], mut pred: impl FnMut(&T) -> bool) -> usize {
+    let (mut lo, mut hi) = (0, s.len());
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if pred(&s[mid]) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+
+    lo
+}
+
+/// Interpolation search for `x` in sorted `s`, with the same result as
+/// [`binary_search`]. Guesses where `x` should be from the values at the
+/// ends of the range, so it's O(log log n) on evenly spread keys, but can
+/// take O(n) probes when they're badly skewed.
+pub fn interpolation_search<T>(s: &[T], x: &T) -> Result<usize, usize>
+where
+    T: Ord + Copy + Into<i128>,
+{
+    interpolation_search_counted(s, x, &Comparisons::new())
+}
+
+/// [`interpolation_search`], counting its guesses in `probes`.
+pub fn interpolation_search_counted<T>(s: &[T], x: &T, probes: &Comparisons) -> Result<usize, usize>
+where
+    T: Ord + Copy + Into<i128>,
+{
+    let mut probe = probes.counted(|e: &T| e.cmp(x));
+    let target: i128 = (*x).into();
+    let (mut lo, mut hi) = (0, s.len());
+
+    // Invariant: everything before lo is less than x, everything from hi on
+    // is greater.
+    while lo < hi {
+        let (first, last): (i128, i128) = (s[lo].into(), s[hi - 1].into());
+        if target < first {
+            return Err(lo);
+        }
+        if target > last {
+            return Err(hi);
+        }
+
+        // Position in proportion to where x falls between the ends. Done in
+        // floating point since the product can overflow even an i128.
+        let mid = if first == last {
+            lo
+        } else {
+            let frac = (target - first) as f64 / (last - first) as f64;
+            lo + ((frac * (hi - lo - 1) as f64) as usize).min(hi - lo - 1)
+        };
+
+        match probe(&s[mid]) {
+            Ordering::Less => lo = mid + 1,
+            Ordering::Greater => hi = mid,
+            Ordering::Equal => return Ok(mid),
+        }
+    }
+
+    Err(lo)
+}
+
+/// The index in `range` where `f` is largest, for `f` that strictly rises
+/// then strictly falls (either part may be empty). Returns `None` for an
+/// empty range. Negate `f` to find a minimum.
+///
+/// Ternary search: each step compares `f` at two points a third of the way
+/// in from either end and drops the third that can't hold the peak.
+pub fn ternary_search<T, F>(range: Range<usize>, mut f: F) -> Option<usize>
+where
+    T: PartialOrd,
+    F: FnMut(usize) -> T,
+{
+    let (mut lo, mut hi) = (range.start, range.end);
+    if lo >= hi {
+        return None;
+    }
+
+    // With four or more left m1 < m2, so every step drops something.
+    while hi - lo > 3 {
+        let third = (hi - lo) / 3;
+        let (m1, m2) = (lo + third, hi - 1 - third);
+        if f(m1) < f(m2) {
+            lo = m1 + 1;
+        } else {
+            hi = m2;
+        }
+    }
+
+    let mut best = lo;
+    let mut best_val = f(lo);
+    for i in lo + 1..hi {
+        let val = f(i);
+        if val > best_val {
+            (best, best_val) = (i, val);
+        }
+    }
+
+    Some(best)
+}
+
+/// The `x` in `[lo, hi]` where a continuous unimodal `f` is largest, to
+/// within `tol`. Negate `f` to find a minimum.
+pub fn ternary_search_f64<F>(mut lo: f64, mut hi: f64, tol: f64, mut f: F) -> f64
+where
+    F: FnMut(f64) -> f64,
+{
+    // Each step keeps two thirds, so this many steps always reach tol even
+    // if the floats can't get that close.
+    let steps = ((hi - lo).abs() / tol.max(f64::MIN_POSITIVE))
+        .log(1.5)
+        .ceil()
+        .clamp(0.0, 200.0) as usize;
+
+    for _ in 0..steps {
+        let third = (hi - lo) / 3.0;
+        let (m1, m2) = (lo + third, hi - third);
+        if f(m1) < f(m2) {
+            lo = m1;
+        } else {
+            hi = m2;
+        }
+    }
+
+    (lo + hi) / 2.0
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+    use crate::{make_one, quicksort};
+
+    fn table() -> Vec<Vec<i32>> {
+        let mut tests = vec![vec![], vec![7], vec![1, 1, 1], vec![1, 3, 5, 7, 9, 11]];
+        for (n, max) in [(100, 20), (1000, 5000), (4000, 4)] {
+            let mut v = make_one(n, max);
+            quicksort(&mut v);
+            tests.push(v);
+        }
+
+        tests
+    }
+
+    #[test]
+    fn test_bounds() {
+        for v in table() {
+            for x in -1..=v.last().map_or(1, |m| m + 1) {
+                let lower = v.partition_point(|e| *e < x);
+                let upper = v.partition_point(|e| *e <= x);
+                assert_eq!(lower_bound(&v, &x), lower, "x = {x}");
+                assert_eq!(upper_bound(&v, &x), upper, "x = {x}");
+                assert_eq!(equal_range(&v, &x), lower..upper, "x = {x}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_searches() {
+        type Search = fn(&[i32], &i32) -> Result<usize, usize>;
+        let searches: [(&str, Search); 3] = [
+            ("binary", binary_search),
+            ("exponential", exponential_search),
+            ("interpolation", interpolation_search),
+        ];
+
+        for v in table() {
+            for x in -1..=v.last().map_or(1, |m| m + 1) {
+                let want = v.binary_search(&x);
+                for (name, search) in searches {
+                    match (search(&v, &x), want) {
+                        (Ok(i), Ok(_)) => assert_eq!(v[i], x, "{name} x = {x}"),
+                        (got, want) => assert_eq!(got, want, "{name} x = {x}"),
+                    }
+                }
+                assert_eq!(linear_search(&v, &x), v.iter().position(|e| *e == x));
+            }
+        }
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let v: Vec<i32> = (0..1024).collect();
+
+        let comparisons = Comparisons::new();
+        assert_eq!(
+            binary_search_by(&v, comparisons.counted(|e: &i32| e.cmp(&-5))),
+            Err(0)
+        );
+        // A miss takes floor(log2 n) + 1 probes.
+        assert_eq!(comparisons.count(), 11);
+
+        // Galloping finds things near the front in a few probes.
+        comparisons.reset();
+        assert_eq!(
+            exponential_search_by(&v, comparisons.counted(|e: &i32| e.cmp(&3))),
+            Ok(3)
+        );
+        assert!(comparisons.count() <= 4, "{}", comparisons.count());
+
+        // Evenly spread keys are found on the first guess.
+        comparisons.reset();
+        assert_eq!(
+            interpolation_search_counted(&v, &700, &comparisons),
+            Ok(700)
+        );
+        assert_eq!(comparisons.count(), 1);
+
+        comparisons.reset();
+        assert_eq!(
+            linear_search_by(&v, comparisons.counted(|e: &i32| *e == 9)),
+            Some(9)
+        );
+        assert_eq!(comparisons.count(), 10);
+    }
+
+    #[test]
+    fn test_interpolation_skewed() {
+        // Worst case for the guesses, but the answers are still right.
+        let v: Vec<u64> = (0..64).map(|i| 1 << i).collect();
+        for (i, x) in v.iter().enumerate() {
+            assert_eq!(interpolation_search(&v, x), Ok(i));
+            if i > 0 {
+                assert_eq!(interpolation_search(&v, &(x + 1)), Err(i + 1));
+            }
+        }
+        assert_eq!(interpolation_search(&[u64::MAX, u64::MAX], &0), Err(0));
+        assert_eq!(interpolation_search(&[i64::MIN, i64::MAX], &0), Err(1));
+    }
+
+    #[test]
+    fn test_ternary_search() {
+        assert_eq!(ternary_search(0..0, |i| i), None);
+        for n in 1..60 {
+            for peak in 0..n {
+                let f = |i: usize| -(i as i64 - peak as i64).abs();
+                assert_eq!(ternary_search(0..n, f), Some(peak), "n = {n}");
+            }
+        }
+        assert_eq!(ternary_search(10..20, |i| i), Some(19));
+
+        let x = ternary_search_f64(-10.0, 10.0, 1e-9, |x| -(x - 1.5) * (x - 1.5));
+        assert!((x - 1.5).abs() < 1e-6, "{x}");
+        let x = ternary_search_f64(0.0, std::f64::consts::PI, 1e-9, f64::sin);
+        assert!((x - std::f64::consts::FRAC_PI_2).abs() < 1e-6, "{x}");
+    }
+}
@@ -0,0 +1,350 @@
+//! Selection: the k-th smallest element, the k smallest elements, and
+//! percentiles, without sorting everything.
+use std::cmp::Ordering;
+
+use crate::introsort::{insertion_sort, median3, partition3};
+use crate::quicksort::partition;
+use crate::{introsort_by, stats, Heap, HeapKind};
+
+// Slices this short are insertion sorted.
+const INSERTION_CUTOFF: usize = 16;
+
+/// Moves the k-th smallest element (counting from 0) to `s[k]`, with
+/// everything before it no bigger and everything after it no smaller, and
+/// returns it. Returns `None` if `k` is out of bounds.
+///
+/// Quickselect with median-of-three pivots, on the same Lomuto `partition`
+/// as `quicksort`. If the partitions stop shrinking fast enough it switches
+/// to median-of-medians pivots, so it's O(n) worst case.
+pub fn select_nth<T: Ord>(s: &mut [T], k: usize) -> Option<&T> {
+    select_nth_by(s, k, T::cmp)
+}
+
+/// [`select_nth`] with a custom comparator.
+pub fn select_nth_by<T, F>(s: &mut [T], k: usize, mut cmp: F) -> Option<&T>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    if k >= s.len() {
+        return None;
+    }
+
+    // Each pass partitions s[lo..hi]. Well behaved pivots partition about
+    // 2n elements all told, so past 4n the pivots are going bad.
+    let mut budget = 4 * s.len();
+    let (mut lo, mut hi) = (0, s.len());
+    loop {
+        let sub = &mut s[lo..hi];
+        let len = sub.len();
+        if len <= INSERTION_CUTOFF {
+            insertion_sort(sub, &mut cmp);
+            break;
+        }
+        if len > budget {
+            median_of_medians_select(sub, k - lo, &mut cmp);
+            break;
+        }
+        budget -= len;
+
+        let m = median3(sub, 0, len / 2, len - 1, &mut cmp);
+        stats::swap(sub, m, len - 1);
+        let p = lo + partition(sub, &mut cmp);
+        match k.cmp(&p) {
+            Ordering::Equal => break,
+            Ordering::Less => hi = p,
+            Ordering::Greater => lo = p + 1,
+        }
+    }
+
+    Some(&s[k])
+}
+
+// Selection with the median of the medians of groups of five as the pivot,
+// which is always between the 30th and 70th percentile, so the slice
+// shrinks geometrically. Three-way partitioning keeps runs of equal keys
+// from stalling it.
+fn median_of_medians_select<T, F>(mut s: &mut [T], mut k: usize, cmp: &mut F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    loop {
+        if s.len() <= INSERTION_CUTOFF {
+            insertion_sort(s, cmp);
+            return;
+        }
+
+        let p = median_of_medians(s, cmp);
+        stats::swap(s, 0, p);
+        let (lt, gt) = partition3(s, cmp);
+        if k < lt {
+            s = &mut s[..lt];
+        } else if k >= gt {
+            k -= gt;
+            s = &mut s[gt..];
+        } else {
+            return;
+        }
+    }
+}
+
+// Gathers the median of each group of five at the front of `s`, then
+// selects the median of those. Returns its index.
+fn median_of_medians<T, F>(s: &mut [T], cmp: &mut F) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let groups = s.len().div_ceil(5);
+    for g in 0..groups {
+        let start = g * 5;
+        let end = (start + 5).min(s.len());
+        insertion_sort(&mut s[start..end], cmp);
+        stats::swap(s, g, start + (end - start) / 2);
+    }
+
+    let mid = groups / 2;
+    median_of_medians_select(&mut s[..groups], mid, cmp);
+
+    mid
+}
+
+/// Sorts just the `k` smallest elements into `s[..k]`, leaving the rest in
+/// no particular order. O(n + k log k).
+pub fn partial_sort<T: Ord>(s: &mut [T], k: usize) {
+    partial_sort_by(s, k, T::cmp);
+}
+
+/// [`partial_sort`] with a custom comparator.
+pub fn partial_sort_by<T, F>(s: &mut [T], k: usize, mut cmp: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let k = k.min(s.len());
+    if k == 0 {
+        return;
+    }
+    if k < s.len() {
+        select_nth_by(s, k - 1, &mut cmp);
+    }
+
+    introsort_by(&mut s[..k], cmp);
+}
+
+/// The `k` smallest elements of `s`, in order, found by partitioning `s`
+/// in place. See [`partial_sort`].
+pub fn top_k_partition<T: Ord>(s: &mut [T], k: usize) -> &mut [T] {
+    partial_sort(s, k);
+    let k = k.min(s.len());
+
+    &mut s[..k]
+}
+
+/// The `k` smallest items of any iterator, in order, keeping only `k` of
+/// them in memory at a time with a max heap. O(n log k), so it suits
+/// streams and k much smaller than n.
+pub fn top_k<T, I>(items: I, k: usize) -> Vec<T>
+where
+    T: Ord,
+    I: IntoIterator<Item = T>,
+{
+    if k == 0 {
+        return vec![];
+    }
+
+    // The biggest of the k smallest so far sits on top, ready to be evicted.
+    let mut heap = Heap::with_arity(4, HeapKind::Max);
+    for item in items {
+        if heap.len() < k {
+            heap.push(item);
+        } else if heap.peek().is_some_and(|top| item < *top) {
+            heap.pop();
+            heap.push(item);
+        }
+    }
+
+    let mut out = heap.into_sorted_vec();
+    out.reverse();
+
+    out
+}
+
+/// Tracks one percentile of a stream of values exactly, in O(log n) per
+/// value, by keeping everything at or below the percentile in a max heap
+/// and everything above it in a min heap.
+///
+/// Uses the nearest-rank definition: the p-th percentile of n values is
+/// the `ceil(p * n)`-th smallest (at least the first).
+#[derive(Clone, Debug)]
+pub struct StreamingPercentile<T> {
+    p: f64,
+    lower: Heap<T>,
+    upper: Heap<T>,
+}
+
+impl<T: Ord> StreamingPercentile<T> {
+    /// Tracks percentile `p`, from 0.0 (the minimum) to 1.0 (the maximum).
+    /// Values outside that range are clamped.
+    pub fn new(p: f64) -> Self {
+        Self {
+            p: p.clamp(0.0, 1.0),
+            lower: Heap::new(HeapKind::Max),
+            upper: Heap::new(HeapKind::Min),
+        }
+    }
+
+    /// Tracks the median (the lower one for an even count).
+    pub fn median() -> Self {
+        Self::new(0.5)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lower.len() + self.upper.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn push(&mut self, value: T) {
+        if self.lower.peek().is_some_and(|top| value <= *top) {
+            self.lower.push(value);
+        } else {
+            self.upper.push(value);
+        }
+
+        // Rebalance so lower holds exactly the values up to the rank.
+        let want = ((self.p * self.len() as f64).ceil() as usize).clamp(1, self.len());
+        while self.lower.len() > want {
+            if let Some(v) = self.lower.pop() {
+                self.upper.push(v);
+            }
+        }
+        while self.lower.len() < want {
+            if let Some(v) = self.upper.pop() {
+                self.lower.push(v);
+            }
+        }
+    }
+
+    /// The current percentile, or `None` before the first value.
+    pub fn value(&self) -> Option<&T> {
+        self.lower.peek()
+    }
+}
+
+impl<T: Ord> Extend<T> for StreamingPercentile<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|v| self.push(v));
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+    use crate::{make_one, quicksort};
+
+    fn sorted(v: &[i32]) -> Vec<i32> {
+        let mut v = v.to_vec();
+        quicksort(&mut v);
+        v
+    }
+
+    #[test]
+    fn test_select_nth() {
+        for n in [1, 2, 5, 17, 100, 1000] {
+            for max in [n / 4 + 1, n * 2] {
+                let v = make_one(n, max);
+                let want = sorted(&v);
+                for k in (0..n).step_by(n / 50 + 1).chain([n - 1]) {
+                    let mut got = v.clone();
+                    assert_eq!(select_nth(&mut got, k), Some(&want[k]));
+                    assert!(got[..k].iter().all(|x| *x <= want[k]));
+                    assert!(got[k + 1..].iter().all(|x| *x >= want[k]));
+                    assert_eq!(sorted(&got), want);
+                }
+            }
+        }
+
+        assert_eq!(select_nth(&mut [3, 1, 2], 3), None);
+        assert_eq!(select_nth::<i32>(&mut [], 0), None);
+    }
+
+    #[test]
+    fn test_select_nth_by() {
+        let mut v = make_one(500, 1000);
+        let want = sorted(&v);
+        assert_eq!(select_nth_by(&mut v, 0, |a, b| b.cmp(a)), want.last());
+    }
+
+    #[test]
+    fn test_select_bad_pivots() {
+        // Constant and sorted input run through the budget with Lomuto
+        // partitions, which forces the median-of-medians fallback.
+        let n = 100_000;
+        let mut v = vec![5; n];
+        assert_eq!(select_nth(&mut v, n / 2), Some(&5));
+
+        let mut v: Vec<i32> = (0..n as i32).collect();
+        assert_eq!(select_nth(&mut v, 12_345), Some(&12_345));
+
+        // The fallback on its own.
+        for n in [5, 16, 17, 1000, 5000] {
+            let v = make_one(n, n / 3 + 1);
+            let want = sorted(&v);
+            for k in [0, n / 3, n / 2, n - 1] {
+                let mut got = v.clone();
+                median_of_medians_select(&mut got, k, &mut i32::cmp);
+                assert_eq!(got[k], want[k]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_partial_sort_and_top_k() {
+        for n in [0, 1, 10, 100, 2000] {
+            let v = make_one(n, n * 2 + 1);
+            let want = sorted(&v);
+            for k in [0, 1, n / 2, n, n + 5] {
+                let k_want = &want[..k.min(n)];
+
+                let mut got = v.clone();
+                partial_sort(&mut got, k);
+                assert_eq!(&got[..k.min(n)], k_want);
+                assert_eq!(sorted(&got), want);
+
+                let mut got = v.clone();
+                assert_eq!(top_k_partition(&mut got, k), k_want);
+
+                assert_eq!(top_k(v.iter().copied(), k), k_want);
+            }
+        }
+    }
+
+    #[test]
+    fn test_streaming_percentile() {
+        let v = make_one(2000, 500);
+        for p in [0.0, 0.1, 0.5, 0.9, 0.99, 1.0] {
+            let mut tracker = StreamingPercentile::new(p);
+            assert_eq!(tracker.value(), None);
+
+            let mut seen = vec![];
+            for &x in &v {
+                tracker.push(x);
+                seen.insert(seen.partition_point(|&y| y <= x), x);
+                let rank = ((p * seen.len() as f64).ceil() as usize).max(1);
+                assert_eq!(
+                    tracker.value(),
+                    Some(&seen[rank - 1]),
+                    "p = {p}, n = {}",
+                    seen.len()
+                );
+            }
+            assert_eq!(tracker.len(), v.len());
+        }
+
+        let mut median = StreamingPercentile::median();
+        median.extend([5, 1, 4, 2, 3]);
+        assert_eq!(median.value(), Some(&3));
+        median.push(6);
+        assert_eq!(median.value(), Some(&3));
+    }
+}
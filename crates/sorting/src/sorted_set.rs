@@ -0,0 +1,368 @@
+//! Set operations, deduplication and joins on sorted slices, in one pass
+//! over both inputs.
+//!
+//! Each step gallops (see [`exponential_search`](crate::exponential_search))
+//! over the run of elements from one side that sort before the other
+//! side's next element, so intersecting 10 elements with a million takes a
+//! few hundred comparisons rather than a million, while interleaved inputs
+//! cost at most about twice a plain merge.
+//!
+//! Inputs with repeats are treated as multisets, as C++'s `std::set_union`
+//! and friends do: an element that's in `a` m times and in `b` n times is
+//! in the union max(m, n) times, the intersection min(m, n) times, the
+//! difference m - n times and the symmetric difference |m - n| times.
+use std::cmp::Ordering;
+
+use crate::search::gallop;
+
+// Which elements a set operation keeps.
+#[derive(Clone, Copy)]
+struct Keep {
+    only_a: bool,
+    only_b: bool,
+    both: bool,
+}
+
+fn merge_by<T, F>(a: &[T], b: &[T], mut cmp: F, keep: Keep) -> Vec<T>
+where
+    T: Clone,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let mut out = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        match cmp(&a[i], &b[j]) {
+            Ordering::Less => {
+                let n = 1 + gallop(&a[i + 1..], |x| cmp(x, &b[j]) == Ordering::Less);
+                if keep.only_a {
+                    out.extend_from_slice(&a[i..i + n]);
+                }
+                i += n;
+            }
+            Ordering::Greater => {
+                let n = 1 + gallop(&b[j + 1..], |y| cmp(&a[i], y) == Ordering::Greater);
+                if keep.only_b {
+                    out.extend_from_slice(&b[j..j + n]);
+                }
+                j += n;
+            }
+            Ordering::Equal => {
+                if keep.both {
+                    out.push(a[i].clone());
+                }
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    if keep.only_a {
+        out.extend_from_slice(&a[i..]);
+    }
+    if keep.only_b {
+        out.extend_from_slice(&b[j..]);
+    }
+
+    out
+}
+
+/// Everything in sorted `a` or sorted `b`, sorted. Where both have an
+/// element, it's taken from `a`.
+pub fn sorted_union<T: Ord + Clone>(a: &[T], b: &[T]) -> Vec<T> {
+    sorted_union_by(a, b, T::cmp)
+}
+
+/// [`sorted_union`] with a custom comparator, which both inputs must
+/// already be sorted by.
+pub fn sorted_union_by<T, F>(a: &[T], b: &[T], cmp: F) -> Vec<T>
+where
+    T: Clone,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let keep = Keep {
+        only_a: true,
+        only_b: true,
+        both: true,
+    };
+    merge_by(a, b, cmp, keep)
+}
+
+/// Everything in both sorted `a` and sorted `b`, sorted, taken from `a`.
+pub fn sorted_intersection<T: Ord + Clone>(a: &[T], b: &[T]) -> Vec<T> {
+    sorted_intersection_by(a, b, T::cmp)
+}
+
+/// [`sorted_intersection`] with a custom comparator.
+pub fn sorted_intersection_by<T, F>(a: &[T], b: &[T], cmp: F) -> Vec<T>
+where
+    T: Clone,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let keep = Keep {
+        only_a: false,
+        only_b: false,
+        both: true,
+    };
+    merge_by(a, b, cmp, keep)
+}
+
+/// Everything in sorted `a` but not in sorted `b`, sorted.
+pub fn sorted_difference<T: Ord + Clone>(a: &[T], b: &[T]) -> Vec<T> {
+    sorted_difference_by(a, b, T::cmp)
+}
+
+/// [`sorted_difference`] with a custom comparator.
+pub fn sorted_difference_by<T, F>(a: &[T], b: &[T], cmp: F) -> Vec<T>
+where
+    T: Clone,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let keep = Keep {
+        only_a: true,
+        only_b: false,
+        both: false,
+    };
+    merge_by(a, b, cmp, keep)
+}
+
+/// Everything in exactly one of sorted `a` and sorted `b`, sorted.
+pub fn sorted_symmetric_difference<T: Ord + Clone>(a: &[T], b: &[T]) -> Vec<T> {
+    sorted_symmetric_difference_by(a, b, T::cmp)
+}
+
+/// [`sorted_symmetric_difference`] with a custom comparator.
+pub fn sorted_symmetric_difference_by<T, F>(a: &[T], b: &[T], cmp: F) -> Vec<T>
+where
+    T: Clone,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let keep = Keep {
+        only_a: true,
+        only_b: true,
+        both: false,
+    };
+    merge_by(a, b, cmp, keep)
+}
+
+/// Removes repeats from sorted `v`, keeping the first of each. Like
+/// `Vec::dedup`, but gallops to the end of each run of equal elements, so
+/// long runs cost O(log run) comparisons.
+pub fn dedup_sorted<T: Ord>(v: &mut Vec<T>) {
+    dedup_sorted_by(v, T::cmp);
+}
+
+/// [`dedup_sorted`] with a custom comparator, which `v` must already be
+/// sorted by.
+pub fn dedup_sorted_by<T, F>(v: &mut Vec<T>, mut cmp: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let mut kept = 0;
+    let mut i = 0;
+    while i < v.len() {
+        let run = 1 + gallop(&v[i + 1..], |x| cmp(x, &v[i]) == Ordering::Equal);
+        v.swap(kept, i);
+        kept += 1;
+        i += run;
+    }
+    v.truncate(kept);
+}
+
+/// Inner merge join of `left` and `right`, both sorted by their keys: a
+/// pair for every left and right element with equal keys, in key order.
+/// A key repeated m times on the left and n on the right gives m × n pairs,
+/// left-major.
+///
+/// ```
+/// use sorting::merge_join;
+///
+/// let people = [(1, "ada"), (2, "bob"), (4, "cy")];
+/// let orders = [(1, "tea"), (1, "jam"), (3, "ham"), (4, "egg")];
+/// let joined: Vec<_> = merge_join(&people, &orders, |p| p.0, |o| o.0)
+///     .into_iter()
+///     .map(|(p, o)| (p.1, o.1))
+///     .collect();
+/// assert_eq!(joined, [("ada", "tea"), ("ada", "jam"), ("cy", "egg")]);
+/// ```
+pub fn merge_join<'a, 'b, L, R, K, FL, FR>(
+    left: &'a [L],
+    right: &'b [R],
+    mut left_key: FL,
+    mut right_key: FR,
+) -> Vec<(&'a L, &'b R)>
+where
+    K: Ord,
+    FL: FnMut(&L) -> K,
+    FR: FnMut(&R) -> K,
+{
+    let mut out = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() && j < right.len() {
+        let (a, b) = (left_key(&left[i]), right_key(&right[j]));
+        match a.cmp(&b) {
+            Ordering::Less => i += 1 + gallop(&left[i + 1..], |l| left_key(l) < b),
+            Ordering::Greater => j += 1 + gallop(&right[j + 1..], |r| right_key(r) < a),
+            Ordering::Equal => {
+                let m = 1 + gallop(&left[i + 1..], |l| left_key(l) == a);
+                let n = 1 + gallop(&right[j + 1..], |r| right_key(r) == b);
+                for l in &left[i..i + m] {
+                    out.extend(right[j..j + n].iter().map(|r| (l, r)));
+                }
+                i += m;
+                j += n;
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod unit {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::{Distribution, Prng};
+
+    fn counts(v: &[i32]) -> BTreeMap<i32, usize> {
+        let mut counts = BTreeMap::new();
+        for &x in v {
+            *counts.entry(x).or_default() += 1;
+        }
+        counts
+    }
+
+    // What each operation should give, from the counts in a and b.
+    fn expected(a: &[i32], b: &[i32], count: fn(usize, usize) -> usize) -> Vec<i32> {
+        let (ca, cb) = (counts(a), counts(b));
+        let mut keys: Vec<i32> = ca.keys().chain(cb.keys()).copied().collect();
+        keys.sort();
+        keys.dedup();
+
+        let mut out = vec![];
+        for k in keys {
+            let n = count(
+                ca.get(&k).copied().unwrap_or(0),
+                cb.get(&k).copied().unwrap_or(0),
+            );
+            out.extend(std::iter::repeat_n(k, n));
+        }
+        out
+    }
+
+    fn sorted(dist: Distribution, n: usize, seed: u64) -> Vec<i32> {
+        let mut v = dist.generate(n, seed);
+        v.sort();
+        v
+    }
+
+    #[test]
+    fn test_set_ops() {
+        let mut prng = Prng::from_seed(49);
+        for _ in 0..200 {
+            let (n, m) = (prng.next_u64() as usize % 60, prng.next_u64() as usize % 60);
+            let dist = Distribution::FewUnique { values: 12 };
+            let a = sorted(dist, n, prng.next_u64());
+            let b = sorted(dist, m, prng.next_u64());
+
+            assert_eq!(sorted_union(&a, &b), expected(&a, &b, usize::max));
+            assert_eq!(sorted_intersection(&a, &b), expected(&a, &b, usize::min));
+            assert_eq!(
+                sorted_difference(&a, &b),
+                expected(&a, &b, usize::saturating_sub)
+            );
+            assert_eq!(
+                sorted_symmetric_difference(&a, &b),
+                expected(&a, &b, usize::abs_diff)
+            );
+        }
+
+        // Equal elements come from a.
+        let a = [(1, 'a'), (2, 'a')];
+        let b = [(2, 'b'), (3, 'b')];
+        let by_key = |x: &(i32, char), y: &(i32, char)| x.0.cmp(&y.0);
+        assert_eq!(
+            sorted_union_by(&a, &b, by_key),
+            [(1, 'a'), (2, 'a'), (3, 'b')]
+        );
+        assert_eq!(sorted_intersection_by(&a, &b, by_key), [(2, 'a')]);
+        assert_eq!(sorted_difference_by(&a, &b, by_key), [(1, 'a')]);
+        assert_eq!(
+            sorted_symmetric_difference_by(&a, &b, by_key),
+            [(1, 'a'), (3, 'b')]
+        );
+    }
+
+    #[test]
+    fn test_galloping() {
+        let big: Vec<i32> = (0..1_000_000).collect();
+        let small = [10, 500_000, 999_999, 2_000_000];
+        let mut comparisons = 0;
+        let common = sorted_intersection_by(&small, &big, |x, y| {
+            comparisons += 1;
+            x.cmp(y)
+        });
+        assert_eq!(common, [10, 500_000, 999_999]);
+        assert!(comparisons < 200, "{comparisons} comparisons");
+    }
+
+    #[test]
+    fn test_dedup_sorted() {
+        for dist in Distribution::all() {
+            let v = sorted(dist, 1000, 3);
+            let mut got = v.clone();
+            dedup_sorted(&mut got);
+            let mut want = v;
+            want.dedup();
+            assert_eq!(got, want, "{dist}");
+        }
+
+        // The first of each run is kept.
+        let mut v = vec![(1, 'a'), (1, 'b'), (2, 'c'), (2, 'd'), (2, 'e')];
+        dedup_sorted_by(&mut v, |x, y| x.0.cmp(&y.0));
+        assert_eq!(v, [(1, 'a'), (2, 'c')]);
+
+        let mut comparisons = 0;
+        let mut v = vec![7; 100_000];
+        dedup_sorted_by(&mut v, |x, y| {
+            comparisons += 1;
+            x.cmp(y)
+        });
+        assert_eq!(v, [7]);
+        assert!(comparisons < 100, "{comparisons} comparisons");
+    }
+
+    #[test]
+    fn test_merge_join() {
+        let left = [(1, 'a'), (2, 'b'), (2, 'c'), (5, 'd')];
+        let right = [(0, 'w'), (2, 'x'), (2, 'y'), (5, 'z'), (6, 'z')];
+        let joined: Vec<(char, char)> = merge_join(&left, &right, |l| l.0, |r| r.0)
+            .into_iter()
+            .map(|(l, r)| (l.1, r.1))
+            .collect();
+        assert_eq!(
+            joined,
+            [('b', 'x'), ('b', 'y'), ('c', 'x'), ('c', 'y'), ('d', 'z')]
+        );
+
+        // Against a nested loop join, on keys of different types.
+        let mut prng = Prng::from_seed(7);
+        let left = sorted(Distribution::FewUnique { values: 20 }, 200, prng.next_u64());
+        let right: Vec<String> =
+            sorted(Distribution::FewUnique { values: 20 }, 150, prng.next_u64())
+                .iter()
+                .map(|x| x.to_string())
+                .collect();
+        let key = |s: &String| s.parse::<i32>().expect("not a number");
+        let got = merge_join(&left, &right, |&l| l, key);
+        let mut want = vec![];
+        for l in &left {
+            for r in right.iter().filter(|r| key(r) == *l) {
+                want.push((l, r));
+            }
+        }
+        assert_eq!(got, want);
+
+        let empty: [i32; 0] = [];
+        assert!(merge_join(&empty, &left, |&l| l, |&r| r).is_empty());
+    }
+}
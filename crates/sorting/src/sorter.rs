@@ -0,0 +1,118 @@
+//! A common interface over the sorts in this crate.
+//!
+//! Every algorithm has a unit struct implementing [`Sorter`], so callers and
+//! benchmarks can hold a `&dyn Sorter<T>` and swap algorithms without
+//! touching the call site.
+use crate::{counting_sort_by_signed_key, quicksort, radix_sort};
+
+/// Sorts a slice of `T` in place, in ascending order.
+pub trait Sorter<T> {
+    /// A short name for reports and benchmark labels.
+    fn name(&self) -> &'static str;
+
+    fn sort(&self, v: &mut [T]);
+}
+
+/// Lomuto partition quicksort, see [`quicksort`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Quicksort;
+
+impl<T: Ord> Sorter<T> for Quicksort {
+    fn name(&self) -> &'static str {
+        "quicksort"
+    }
+
+    fn sort(&self, v: &mut [T]) {
+        quicksort(v);
+    }
+}
+
+// CountingSort counts when the key range needs at most this many counters
+// per element, or MIN_COUNTERS for short slices, and radix sorts otherwise.
+const COUNTERS_PER_ELEMENT: usize = 4;
+const MIN_COUNTERS: usize = 1 << 16;
+
+/// Counting sort, see [`counting_sort_by_signed_key`].
+///
+/// Counting needs a counter for every value between the smallest and the
+/// largest, so when that's more than a few per element it radix sorts
+/// instead, and any `i32` slice sorts in O(n).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CountingSort;
+
+impl Sorter<i32> for CountingSort {
+    fn name(&self) -> &'static str {
+        "counting_sort"
+    }
+
+    fn sort(&self, v: &mut [i32]) {
+        let (Some(&min), Some(&max)) = (v.iter().min(), v.iter().max()) else {
+            return;
+        };
+        let span = max.abs_diff(min) as usize + 1;
+        let limit = v
+            .len()
+            .saturating_mul(COUNTERS_PER_ELEMENT)
+            .max(MIN_COUNTERS);
+        if span <= limit && counting_sort_by_signed_key(v, |&x| x as i64).is_ok() {
+            return;
+        }
+
+        radix_sort(v);
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+    use crate::{
+        check_sorted, make_one, BottomUpMergeSort, Heapsort, Introsort, MergeSort, RadixSort,
+        Timsort,
+    };
+
+    #[test]
+    fn test_sorters() {
+        let sorters: Vec<Box<dyn Sorter<i32>>> = vec![
+            Box::new(Quicksort),
+            Box::new(CountingSort),
+            Box::new(Introsort),
+            Box::new(MergeSort),
+            Box::new(BottomUpMergeSort),
+            Box::new(Timsort),
+            Box::new(Heapsort),
+            Box::new(RadixSort),
+        ];
+
+        for s in &sorters {
+            for i in [0, 1, 2, 10, 500] {
+                // Shifted so some values are negative.
+                let mut v: Vec<i32> = make_one(i, 1000).into_iter().map(|x| x - 500).collect();
+                let mut want = v.clone();
+                want.sort();
+
+                s.sort(&mut v);
+                assert!(check_sorted(&v), "{} failed", s.name());
+                assert_eq!(v, want, "{} failed", s.name());
+            }
+        }
+    }
+
+    #[test]
+    fn test_counting_sort_wide_range() {
+        // 2^32 counters would be 32 GiB, so this has to radix sort.
+        let mut v = vec![i32::MAX, 0, i32::MIN, -1, i32::MAX];
+        CountingSort.sort(&mut v);
+        assert_eq!(v, [i32::MIN, -1, 0, i32::MAX, i32::MAX]);
+    }
+
+    #[test]
+    fn test_sorter_generic() {
+        fn sort_with<S: Sorter<String>>(s: S, v: &mut [String]) {
+            s.sort(v);
+        }
+
+        let mut v: Vec<String> = ["b", "c", "a"].iter().map(|s| s.to_string()).collect();
+        sort_with(Quicksort, &mut v);
+        assert_eq!(v, ["a", "b", "c"]);
+    }
+}
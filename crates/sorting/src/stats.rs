@@ -0,0 +1,360 @@
+//! Counting what a sort does, to check it against the theory.
+//!
+//! Comparisons are counted by sorting [`Counted`] elements, so that works
+//! for any sort, the standard library's included. Swaps, element reads and
+//! writes, and recursion depth come from probes in this crate's own sorts,
+//! which only count inside [`measure`] and cost one thread-local check
+//! otherwise. Work done on other threads, as by the parallel sorts, isn't
+//! seen. The same probes feed [`crate::trace`].
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::fmt;
+use std::mem;
+use std::ops::AddAssign;
+
+use crate::{trace, Sorter};
+
+/// What a sort did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SortStats {
+    /// Element comparisons, from [`Counted`] elements.
+    pub comparisons: u64,
+    /// Element swaps.
+    pub swaps: u64,
+    /// Elements read, by comparisons, swaps and copies.
+    pub reads: u64,
+    /// Elements written, by swaps and copies.
+    pub writes: u64,
+    /// The deepest recursion reached.
+    pub max_depth: u32,
+}
+
+impl SortStats {
+    /// Comparisons over `n log2 n`, which the best comparison sorts keep
+    /// close to 1 and never much below it on random input.
+    pub fn comparisons_per_n_log_n(&self, n: usize) -> f64 {
+        if n < 2 {
+            return 0.0;
+        }
+
+        self.comparisons as f64 / (n as f64 * (n as f64).log2())
+    }
+}
+
+impl AddAssign for SortStats {
+    fn add_assign(&mut self, other: Self) {
+        self.comparisons += other.comparisons;
+        self.swaps += other.swaps;
+        self.reads += other.reads;
+        self.writes += other.writes;
+        self.max_depth = self.max_depth.max(other.max_depth);
+    }
+}
+
+impl fmt::Display for SortStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} comparisons, {} swaps, {} reads, {} writes, depth {}",
+            self.comparisons, self.swaps, self.reads, self.writes, self.max_depth
+        )
+    }
+}
+
+thread_local! {
+    static ACTIVE: Cell<bool> = const { Cell::new(false) };
+    static STATS: Cell<SortStats> = const {
+        Cell::new(SortStats {
+            comparisons: 0,
+            swaps: 0,
+            reads: 0,
+            writes: 0,
+            max_depth: 0,
+        })
+    };
+    static DEPTH: Cell<u32> = const { Cell::new(0) };
+}
+
+/// Runs `f`, counting what the sorts it calls on this thread do. Nested
+/// calls count towards the outer one too.
+pub fn measure<R>(f: impl FnOnce() -> R) -> (R, SortStats) {
+    // Puts the outer measurement back even if `f` panics.
+    struct Restore {
+        active: bool,
+        stats: SortStats,
+        depth: u32,
+    }
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let inner = STATS.get();
+            let mut outer = self.stats;
+            if self.active {
+                outer += SortStats {
+                    max_depth: self.depth + inner.max_depth,
+                    ..inner
+                };
+            }
+            STATS.set(outer);
+            DEPTH.set(self.depth);
+            ACTIVE.set(self.active);
+        }
+    }
+
+    let restore = Restore {
+        active: ACTIVE.replace(true),
+        stats: STATS.replace(SortStats::default()),
+        depth: DEPTH.replace(0),
+    };
+    let result = f();
+    let stats = STATS.get();
+    drop(restore);
+
+    (result, stats)
+}
+
+/// Sorts a copy of `v` with `sorter` and returns what it did.
+pub fn sort_stats<T: Clone>(sorter: &dyn Sorter<Counted<T>>, v: &[T]) -> SortStats {
+    let mut counted: Vec<Counted<T>> = v.iter().cloned().map(Counted).collect();
+
+    measure(|| sorter.sort(&mut counted)).1
+}
+
+fn add(f: impl FnOnce(&mut SortStats)) {
+    let mut stats = STATS.get();
+    f(&mut stats);
+    STATS.set(stats);
+}
+
+// The probes below only check ACTIVE inline, and hand anything more to
+// functions kept out of line so the sorts' inner loops stay small. Traces
+// place elements by address.
+
+fn addr<T>(s: &[T], i: usize) -> usize {
+    s.as_ptr() as usize + i * mem::size_of::<T>()
+}
+
+// `s.swap(a, b)`, counted.
+#[inline]
+pub(crate) fn swap<T>(s: &mut [T], a: usize, b: usize) {
+    s.swap(a, b);
+    if ACTIVE.get() {
+        swapped(addr(s, a), addr(s, b));
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn swapped(a: usize, b: usize) {
+    add(|st| {
+        st.swaps += 1;
+        st.reads += 2;
+        st.writes += 2;
+    });
+    trace::swapped(a, b);
+}
+
+// `s.reverse()`, counted as the swaps it makes.
+#[inline]
+pub(crate) fn reverse<T>(s: &mut [T]) {
+    s.reverse();
+    if ACTIVE.get() {
+        let n = s.len();
+        for i in 0..n / 2 {
+            swapped(addr(s, i), addr(s, n - 1 - i));
+        }
+    }
+}
+
+// Counts `src` copied over `dst`, once it has been.
+#[inline]
+pub(crate) fn copied<T>(src: &[T], dst: &[T]) {
+    if ACTIVE.get() {
+        copied_at(addr(src, 0), addr(dst, 0), src.len(), mem::size_of::<T>());
+    }
+}
+
+// Counts one element copied over another, once it has been.
+#[inline]
+pub(crate) fn copied_one<T>(src: &T, dst: &T) {
+    copied(std::slice::from_ref(src), std::slice::from_ref(dst));
+}
+
+#[cold]
+#[inline(never)]
+fn copied_at(src: usize, dst: usize, len: usize, size: usize) {
+    add(|st| {
+        st.reads += len as u64;
+        st.writes += len as u64;
+    });
+    trace::copied(src, dst, len, size);
+}
+
+// One level of recursion, until it's dropped.
+pub(crate) struct Depth(bool);
+
+#[inline]
+pub(crate) fn enter() -> Depth {
+    if !ACTIVE.get() {
+        return Depth(false);
+    }
+
+    let depth = DEPTH.get() + 1;
+    DEPTH.set(depth);
+    add(|st| st.max_depth = st.max_depth.max(depth));
+
+    Depth(true)
+}
+
+impl Drop for Depth {
+    fn drop(&mut self) {
+        if self.0 {
+            DEPTH.set(DEPTH.get() - 1);
+        }
+    }
+}
+
+/// An element that counts its comparisons, for [`measure`] and
+/// [`sort_stats`], and records them for [`crate::trace`].
+// Transparent, so a traced slice of these lines up with the plain one.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(transparent)]
+pub struct Counted<T>(pub T);
+
+impl<T> Counted<T> {
+    fn compared(&self, other: &Self) {
+        if ACTIVE.get() {
+            add(|st| {
+                st.comparisons += 1;
+                st.reads += 2;
+            });
+            trace::compared(self as *const _ as usize, other as *const _ as usize);
+        }
+    }
+}
+
+impl<T: PartialEq> PartialEq for Counted<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.compared(other);
+        self.0 == other.0
+    }
+}
+
+impl<T: Eq> Eq for Counted<T> {}
+
+impl<T: PartialOrd> PartialOrd for Counted<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.compared(other);
+        self.0.partial_cmp(&other.0)
+    }
+}
+
+impl<T: Ord> Ord for Counted<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.compared(other);
+        self.0.cmp(&other.0)
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+    use crate::{
+        heapsort, introsort, introsort::insertion_sort, merge_sort, quicksort, radix_sort,
+        BottomUpMergeSort, Distribution, Heapsort, Introsort, MergeSort, Quicksort, Timsort,
+    };
+
+    #[test]
+    fn test_measure() {
+        let ((), stats) = measure(|| ());
+        assert_eq!(stats, SortStats::default());
+
+        // Nothing is counted outside measure.
+        let mut v: Vec<Counted<i32>> = (0..100).rev().map(Counted).collect();
+        introsort(&mut v);
+        assert_eq!(STATS.get(), SortStats::default());
+
+        // Inner measurements add to the outer one.
+        let mut v: Vec<Counted<i32>> = (0..100).rev().map(Counted).collect();
+        let (inner, outer) = measure(|| {
+            let _ = Counted(1) < Counted(2);
+            measure(|| heapsort(&mut v)).1
+        });
+        assert_eq!(outer.comparisons, inner.comparisons + 1);
+        assert_eq!(outer.swaps, inner.swaps);
+    }
+
+    #[test]
+    fn test_bounds() {
+        let n = 1 << 12;
+        let v = Distribution::Random.generate(n, 1);
+
+        let sorters: [Box<dyn Sorter<Counted<i32>>>; 6] = [
+            Box::new(Quicksort),
+            Box::new(Introsort),
+            Box::new(MergeSort),
+            Box::new(BottomUpMergeSort),
+            Box::new(Timsort),
+            Box::new(Heapsort),
+        ];
+        for sorter in &sorters {
+            let stats = sort_stats(sorter.as_ref(), &v);
+            // No comparison sort can beat log2(n!), about n log2 n - 1.44n.
+            let lower = n as f64 * (n as f64).log2() - 1.443 * n as f64;
+            assert!(
+                stats.comparisons as f64 > lower,
+                "{} {stats}",
+                sorter.name()
+            );
+            let ratio = stats.comparisons_per_n_log_n(n);
+            assert!(ratio < 2.0, "{} {ratio}", sorter.name());
+            assert!(stats.reads >= 2 * stats.comparisons, "{}", sorter.name());
+            assert!(stats.max_depth < 64, "{} {stats}", sorter.name());
+        }
+
+        // Timsort finds the one run in sorted input and stops.
+        let sorted = Distribution::Sorted.generate(n, 0);
+        let stats = sort_stats(&Timsort, &sorted);
+        assert_eq!(stats.comparisons, n as u64 - 1);
+        assert_eq!(stats.writes, 0);
+    }
+
+    #[test]
+    fn test_quicksort_stats() {
+        // Sorted input is quicksort's worst case: it peels off one element
+        // per level until 16 are left for the 60-comparator network, so
+        // n(n-1)/2 comparisons less the 16*15/2 partitioning would take.
+        let n = 500;
+        let sorted: Vec<i32> = (0..n).collect();
+        let stats = sort_stats(&Quicksort, &sorted);
+        let n = n as u64;
+        assert_eq!(stats.comparisons, n * (n - 1) / 2 - 120 + 60);
+        assert_eq!(stats.max_depth as u64, n - 16);
+
+        let mut v: Vec<Counted<i32>> = sorted.into_iter().map(Counted).collect();
+        let ((), direct) = measure(|| quicksort(&mut v));
+        assert_eq!(direct, stats);
+    }
+
+    #[test]
+    fn test_moves() {
+        // Insertion sort on reversed input swaps every pair once.
+        let mut v: Vec<Counted<i32>> = (0..10).rev().map(Counted).collect();
+        let ((), stats) = measure(|| insertion_sort(&mut v, &mut Counted::cmp));
+        assert_eq!(stats.swaps, 45);
+        assert_eq!(stats.comparisons, 45);
+        assert_eq!(stats.writes, 90);
+
+        // Merging copies one half out, and the rest back in.
+        let mut v: Vec<Counted<i32>> = (0..1000).rev().map(Counted).collect();
+        let ((), stats) = measure(|| merge_sort(&mut v));
+        assert!(stats.writes > 0 && stats.swaps > 0, "{stats}");
+
+        // Radix sort doesn't compare, but moves every element per pass.
+        let mut v: Vec<u16> = (0..1000).rev().collect();
+        let ((), stats) = measure(|| radix_sort(&mut v));
+        assert_eq!(stats.comparisons, 0);
+        assert_eq!(stats.writes, 2 * 1000);
+        assert!(v.windows(2).all(|w| w[0] <= w[1]));
+    }
+}
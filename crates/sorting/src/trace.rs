@@ -0,0 +1,432 @@
+//! Recording every step of a sort, to replay or draw it.
+//!
+//! [`trace`] runs a sort under the same probes [`measure`] counts with and
+//! logs each comparison, swap and copy in order. Probes see element
+//! addresses, so each step is placed in a slot of the slice being sorted, or
+//! of scratch space such as a merge buffer. [`Trace::replay`] then steps
+//! through the states of the slice, and [`crate::animate`] and
+//! [`crate::svg_frame`] draw them.
+//!
+//! Unlike [`measure`], a trace follows the parallel sorts onto the threads
+//! they spawn. Those threads work on disjoint pieces, so the order their
+//! steps interleave in doesn't change the replay.
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::{measure, Counted, SortStats, Sorter};
+
+/// Where an element was.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Slot {
+    /// An index into the slice being sorted.
+    Main(usize),
+    /// Anywhere else, numbered in the order first seen.
+    Scratch(usize),
+}
+
+impl Slot {
+    /// The index into the slice being sorted, if that's where it was.
+    pub fn main(self) -> Option<usize> {
+        match self {
+            Slot::Main(i) => Some(i),
+            Slot::Scratch(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Slot::Main(i) => write!(f, "{i}"),
+            Slot::Scratch(i) => write!(f, "scratch {i}"),
+        }
+    }
+}
+
+/// One step of a sort.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// Two elements compared.
+    Compare(Slot, Slot),
+    /// Two elements swapped.
+    Swap(Slot, Slot),
+    /// An element copied over another.
+    Copy { from: Slot, to: Slot },
+}
+
+impl Event {
+    /// Whether the step changed anything.
+    pub fn writes(&self) -> bool {
+        !matches!(self, Event::Compare(..))
+    }
+
+    /// The indexes into the slice being sorted that the step touched.
+    pub fn main_slots(&self) -> impl Iterator<Item = usize> {
+        let (a, b) = match *self {
+            Event::Compare(a, b) | Event::Swap(a, b) => (a, b),
+            Event::Copy { from, to } => (from, to),
+        };
+        a.main().into_iter().chain(b.main())
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Compare(a, b) => write!(f, "compare {a} and {b}"),
+            Event::Swap(a, b) => write!(f, "swap {a} and {b}"),
+            Event::Copy { from, to } => write!(f, "copy {from} to {to}"),
+        }
+    }
+}
+
+/// Everything a sort did to a slice.
+#[derive(Clone, Debug)]
+pub struct Trace<T> {
+    /// The slice before sorting.
+    pub initial: Vec<T>,
+    /// Every step, in order.
+    pub events: Vec<Event>,
+    /// The totals, as [`measure`] gives them. Only covers the calling
+    /// thread.
+    pub stats: SortStats,
+}
+
+impl<T: Clone> Trace<T> {
+    /// Steps through the sort from the initial slice, calling `f` with each
+    /// event and the slice as it is after it.
+    pub fn replay(&self, f: impl FnMut(&Event, &[T])) {
+        self.run(f);
+    }
+
+    /// The slice after the last step.
+    pub fn result(&self) -> Vec<T> {
+        self.run(|_, _| {})
+    }
+
+    fn run(&self, mut f: impl FnMut(&Event, &[T])) -> Vec<T> {
+        let mut main = self.initial.clone();
+        let mut scratch: Vec<Option<T>> = vec![];
+
+        let get = |main: &[T], scratch: &[Option<T>], slot| match slot {
+            Slot::Main(i) => Some(main[i].clone()),
+            Slot::Scratch(i) => scratch.get(i).cloned().flatten(),
+        };
+        let set = |main: &mut [T], scratch: &mut Vec<Option<T>>, slot, x: Option<T>| match slot {
+            Slot::Main(i) => {
+                if let Some(x) = x {
+                    main[i] = x;
+                }
+            }
+            Slot::Scratch(i) => {
+                if scratch.len() <= i {
+                    scratch.resize(i + 1, None);
+                }
+                scratch[i] = x;
+            }
+        };
+
+        for e in &self.events {
+            match *e {
+                Event::Compare(..) => {}
+                Event::Swap(a, b) => {
+                    let (x, y) = (get(&main, &scratch, a), get(&main, &scratch, b));
+                    set(&mut main, &mut scratch, a, y);
+                    set(&mut main, &mut scratch, b, x);
+                }
+                Event::Copy { from, to } => {
+                    let x = get(&main, &scratch, from);
+                    set(&mut main, &mut scratch, to, x);
+                }
+            }
+            f(e, &main);
+        }
+
+        main
+    }
+}
+
+impl<T> Trace<T> {
+    /// The same trace over different elements, e.g. to draw a trace of
+    /// byte strings as numbers.
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Trace<U> {
+        Trace {
+            initial: self.initial.into_iter().map(f).collect(),
+            events: self.events,
+            stats: self.stats,
+        }
+    }
+}
+
+/// Sorts a copy of `v` with `sort`, recording every swap and copy it
+/// makes. Comparisons are only seen on [`Counted`] elements, which
+/// [`trace_sorter`] takes care of.
+pub fn trace<T: Clone>(v: &[T], sort: impl FnOnce(&mut [T])) -> Trace<T> {
+    let mut s = v.to_vec();
+    let (events, stats) = record(&mut s, sort);
+
+    Trace {
+        initial: v.to_vec(),
+        events,
+        stats,
+    }
+}
+
+/// Sorts a copy of `v` with `sorter`, recording every comparison, swap and
+/// copy it makes.
+pub fn trace_sorter<T: Clone>(sorter: &dyn Sorter<Counted<T>>, v: &[T]) -> Trace<T> {
+    let mut counted: Vec<Counted<T>> = v.iter().cloned().map(Counted).collect();
+    let (events, stats) = record(&mut counted, |s| sorter.sort(s));
+
+    Trace {
+        initial: v.to_vec(),
+        events,
+        stats,
+    }
+}
+
+// A step as the probes see it, by address.
+#[derive(Clone, Copy, Debug)]
+enum Raw {
+    Compare(usize, usize),
+    Swap(usize, usize),
+    Copy {
+        from: usize,
+        to: usize,
+        len: usize,
+        size: usize,
+    },
+}
+
+type Sink = Arc<Mutex<Vec<Raw>>>;
+
+thread_local! {
+    static SINK: RefCell<Option<Sink>> = const { RefCell::new(None) };
+}
+
+fn push(raw: Raw) {
+    SINK.with_borrow(|sink| {
+        if let Some(sink) = sink {
+            sink.lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(raw);
+        }
+    });
+}
+
+pub(crate) fn compared(a: usize, b: usize) {
+    push(Raw::Compare(a, b));
+}
+
+pub(crate) fn swapped(a: usize, b: usize) {
+    push(Raw::Swap(a, b));
+}
+
+pub(crate) fn copied(from: usize, to: usize, len: usize, size: usize) {
+    push(Raw::Copy {
+        from,
+        to,
+        len,
+        size,
+    });
+}
+
+// Sets this thread's sink until dropped.
+struct Install(Option<Sink>);
+
+impl Install {
+    fn new(sink: Option<Sink>) -> Self {
+        Self(SINK.replace(sink))
+    }
+}
+
+impl Drop for Install {
+    fn drop(&mut self) {
+        SINK.set(self.0.take());
+    }
+}
+
+/// The trace being recorded on this thread, if any, to carry onto the
+/// threads it spawns.
+#[derive(Clone)]
+pub(crate) struct Tracer(Option<Sink>);
+
+pub(crate) fn tracer() -> Tracer {
+    Tracer(SINK.with_borrow(Clone::clone))
+}
+
+impl Tracer {
+    /// Runs `f`, recording into this trace if there is one.
+    pub(crate) fn run<R>(self, f: impl FnOnce() -> R) -> R {
+        match self.0 {
+            None => f(),
+            Some(sink) => {
+                let _install = Install::new(Some(sink));
+                measure(f).0
+            }
+        }
+    }
+}
+
+fn record<R>(s: &mut [R], sort: impl FnOnce(&mut [R])) -> (Vec<Event>, SortStats) {
+    let base = s.as_ptr() as usize;
+    let size = mem::size_of::<R>().max(1);
+    let end = base + s.len() * size;
+
+    let sink = Sink::default();
+    let stats = {
+        let _install = Install::new(Some(sink.clone()));
+        measure(|| sort(s)).1
+    };
+    let raw = mem::take(&mut *sink.lock().unwrap_or_else(PoisonError::into_inner));
+
+    let mut scratch = HashMap::new();
+    let mut slot = |a: usize| {
+        if (base..end).contains(&a) && (a - base).is_multiple_of(size) {
+            Slot::Main((a - base) / size)
+        } else {
+            let next = scratch.len();
+            Slot::Scratch(*scratch.entry(a).or_insert(next))
+        }
+    };
+
+    let mut events = Vec::with_capacity(raw.len());
+    for r in raw {
+        match r {
+            Raw::Compare(a, b) => events.push(Event::Compare(slot(a), slot(b))),
+            Raw::Swap(a, b) => events.push(Event::Swap(slot(a), slot(b))),
+            Raw::Copy {
+                from,
+                to,
+                len,
+                size,
+            } => {
+                for i in 0..len {
+                    let (from, to) = (slot(from + i * size), slot(to + i * size));
+                    events.push(Event::Copy { from, to });
+                }
+            }
+        }
+    }
+
+    (events, stats)
+}
+
+#[cfg(test)]
+mod unit {
+    use super::*;
+    use crate::{
+        msd_radix_sort, BottomUpMergeSort, CountingSort, Distribution, Heapsort, Introsort,
+        MergeSort, ParMergeSort, ParQuicksort, ParRadixSort, ParallelConfig, Quicksort, RadixSort,
+        Timsort,
+    };
+
+    // Small enough to split at these sizes.
+    const PAR: ParallelConfig = ParallelConfig {
+        threads: 4,
+        cutoff: 16,
+    };
+
+    #[test]
+    fn test_replay() {
+        let comparison: [Box<dyn Sorter<Counted<i32>>>; 8] = [
+            Box::new(Quicksort),
+            Box::new(Introsort),
+            Box::new(MergeSort),
+            Box::new(BottomUpMergeSort),
+            Box::new(Timsort),
+            Box::new(Heapsort),
+            Box::new(ParQuicksort(PAR)),
+            Box::new(ParMergeSort(PAR)),
+        ];
+        let other: [Box<dyn Sorter<i32>>; 3] = [
+            Box::new(RadixSort),
+            Box::new(CountingSort),
+            Box::new(ParRadixSort(ParallelConfig {
+                threads: 4,
+                cutoff: 1,
+            })),
+        ];
+
+        for dist in Distribution::all() {
+            let v: Vec<i32> = dist.generate(300, 5).iter().map(|x| x - 150).collect();
+            let mut want = v.clone();
+            want.sort();
+
+            for sorter in &comparison {
+                let t = trace_sorter(sorter.as_ref(), &v);
+                assert_eq!(t.result(), want, "{} on {dist}", sorter.name());
+                let compares = t.events.iter().filter(|e| !e.writes()).count();
+                assert!(compares > 0, "{} on {dist}", sorter.name());
+            }
+            for sorter in &other {
+                let t = trace(&v, |s| sorter.sort(s));
+                assert_eq!(t.result(), want, "{} on {dist}", sorter.name());
+            }
+        }
+
+        // Byte strings, drawn as the numbers they came from.
+        let v: Vec<u32> = Distribution::Random
+            .generate(500, 1)
+            .iter()
+            .map(|&x| x as u32)
+            .collect();
+        let bytes: Vec<[u8; 4]> = v.iter().map(|x| x.to_be_bytes()).collect();
+        let t = trace(&bytes, msd_radix_sort).map(u32::from_be_bytes);
+        let mut want = v.clone();
+        want.sort();
+        assert_eq!(t.result(), want);
+    }
+
+    #[test]
+    fn test_events() {
+        let t = trace_sorter(&Quicksort, &[2, 1]);
+        assert_eq!(
+            t.events,
+            [
+                Event::Compare(Slot::Main(0), Slot::Main(1)),
+                Event::Swap(Slot::Main(0), Slot::Main(1)),
+            ]
+        );
+        assert_eq!(t.result(), [1, 2]);
+        assert_eq!(t.events[1].to_string(), "swap 0 and 1");
+
+        // Every comparison on this thread is both counted and traced.
+        let v = Distribution::Random.generate(1000, 2);
+        let t = trace_sorter(&Introsort, &v);
+        let compares = t.events.iter().filter(|e| !e.writes()).count();
+        assert_eq!(compares as u64, t.stats.comparisons);
+        assert_eq!(
+            t.events
+                .iter()
+                .filter(|e| matches!(e, Event::Swap(..)))
+                .count() as u64,
+            t.stats.swaps
+        );
+
+        // A merge copies out to a buffer and back.
+        let t = trace_sorter(&MergeSort, &Distribution::Reversed.generate(40, 0));
+        assert!(t.events.iter().any(|e| matches!(
+            e,
+            Event::Copy {
+                from: Slot::Main(_),
+                to: Slot::Scratch(_)
+            }
+        )));
+        assert!(t.events.iter().any(|e| matches!(
+            e,
+            Event::Copy {
+                from: Slot::Scratch(_),
+                to: Slot::Main(_)
+            }
+        )));
+
+        // Nothing is recorded outside a trace.
+        let mut v: Vec<Counted<i32>> = (0..100).rev().map(Counted).collect();
+        crate::introsort(&mut v);
+        assert!(SINK.with_borrow(Option::is_none));
+    }
+}